@@ -0,0 +1,15 @@
+CREATE TABLE "public"."generation_jobs" (
+  "id" VARCHAR(64) PRIMARY KEY,
+  "user_id" VARCHAR(64) NOT NULL,
+  "generate_template_id" VARCHAR(64) NOT NULL,
+  "text" TEXT NOT NULL,
+  -- 0: queued, 1: running, 2: succeeded, 3: failed
+  "status" INTEGER NOT NULL,
+  "error_message" TEXT,
+  "result_file_id" VARCHAR(64),
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "version" INTEGER NOT NULL
+);
+CREATE INDEX "generation_jobs_user_id_idx" ON "public"."generation_jobs" ("user_id");
+CREATE INDEX "generation_jobs_version_idx" ON "public"."generation_jobs" ("version");
@@ -0,0 +1,4 @@
+DELETE FROM "public"."generation_jobs" WHERE "kind" <> 0;
+ALTER TABLE "public"."generation_jobs" ALTER COLUMN "text" SET NOT NULL;
+ALTER TABLE "public"."generation_jobs" ALTER COLUMN "generate_template_id" SET NOT NULL;
+ALTER TABLE "public"."generation_jobs" DROP COLUMN "kind";
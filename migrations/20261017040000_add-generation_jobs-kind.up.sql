@@ -0,0 +1,3 @@
+ALTER TABLE "public"."generation_jobs" ADD COLUMN "kind" integer NOT NULL DEFAULT 0;
+ALTER TABLE "public"."generation_jobs" ALTER COLUMN "generate_template_id" DROP NOT NULL;
+ALTER TABLE "public"."generation_jobs" ALTER COLUMN "text" DROP NOT NULL;
@@ -0,0 +1,2 @@
+DROP INDEX "public"."figure_records_disabled_at_idx";
+ALTER TABLE "public"."figure_records" DROP COLUMN "disabled_at";
@@ -0,0 +1,4 @@
+ALTER TABLE "public"."figure_records" ADD COLUMN "disabled_at" timestamptz;
+-- 既にdisabledのものは今から保持期間を数える
+UPDATE "public"."figure_records" SET "disabled_at" = now() WHERE "disabled";
+CREATE INDEX "figure_records_disabled_at_idx" ON "public"."figure_records" ("disabled_at");
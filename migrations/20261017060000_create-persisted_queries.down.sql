@@ -0,0 +1 @@
+DROP TABLE "public"."persisted_queries";
@@ -0,0 +1,6 @@
+CREATE TABLE "public"."persisted_queries" (
+  -- クエリ文字列のsha256
+  "hash" VARCHAR(64) PRIMARY KEY,
+  "query" TEXT NOT NULL,
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
@@ -0,0 +1 @@
+DROP INDEX "public"."figure_records_user_id_character_stroke_count_idx";
@@ -0,0 +1 @@
+CREATE INDEX "figure_records_user_id_character_stroke_count_idx" ON "public"."figure_records" ("user_id", "character", "stroke_count");
@@ -0,0 +1 @@
+DROP TABLE "public"."character_config_reference_figures";
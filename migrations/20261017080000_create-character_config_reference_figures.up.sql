@@ -0,0 +1,11 @@
+CREATE TABLE "public"."character_config_reference_figures" (
+  "user_id" VARCHAR(64) NOT NULL,
+  "character" VARCHAR(8) NOT NULL,
+  "stroke_count" INTEGER NOT NULL,
+  -- 同じ文字・画数で字形の違う手本を区別する名前
+  "variant" VARCHAR(32) NOT NULL,
+  "figure" JSONB NOT NULL,
+  "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "version" INTEGER NOT NULL,
+  PRIMARY KEY ("user_id", "character", "stroke_count", "variant")
+);
@@ -7,7 +7,8 @@ CREATE TABLE "public"."figure_records" (
   "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
   "stroke_count" INTEGER NOT NULL,
   "version" INTEGER NOT NULL DEFAULT 1,
-  "disabled" BOOLEAN NOT NULL DEFAULT FALSE
+  "disabled" BOOLEAN NOT NULL DEFAULT FALSE,
+  "disabled_at" TIMESTAMP WITH TIME ZONE
 );
 
 CREATE INDEX "figure_records_user_id_idx" ON "public"."figure_records" ("user_id");
@@ -16,6 +17,8 @@ CREATE INDEX "figure_records_created_at_idx" ON "public"."figure_records" ("crea
 CREATE INDEX "figure_records_stroke_count_idx" ON "public"."figure_records" ("stroke_count");
 CREATE INDEX "figure_records_version_idx" ON "public"."figure_records" ("version");
 CREATE INDEX "figure_records_disabled_idx" ON "public"."figure_records" ("disabled");
+CREATE INDEX "figure_records_disabled_at_idx" ON "public"."figure_records" ("disabled_at");
+CREATE INDEX "figure_records_user_id_character_stroke_count_idx" ON "public"."figure_records" ("user_id", "character", "stroke_count");
 
 CREATE TABLE "public"."character_configs" (
   "user_id" VARCHAR(64) NOT NULL,
@@ -35,6 +38,18 @@ CREATE INDEX "character_configs_updated_at_idx" ON "public"."character_configs"
 CREATE INDEX "character_configs_version_idx" ON "public"."character_configs" ("version");
 CREATE INDEX "character_configs_disabled_idx" ON "public"."character_configs" ("disabled");
 
+CREATE TABLE "public"."character_config_reference_figures" (
+  "user_id" VARCHAR(64) NOT NULL,
+  "character" VARCHAR(8) NOT NULL,
+  "stroke_count" INTEGER NOT NULL,
+  -- 同じ文字・画数で字形の違う手本を区別する名前
+  "variant" VARCHAR(32) NOT NULL,
+  "figure" JSONB NOT NULL,
+  "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "version" INTEGER NOT NULL,
+  PRIMARY KEY ("user_id", "character", "stroke_count", "variant")
+);
+
 CREATE TABLE "public"."user_configs" (
   "user_id" VARCHAR(64) PRIMARY KEY,
   "allow_sharing_character_configs" BOOLEAN NOT NULL,
@@ -94,3 +109,30 @@ CREATE INDEX "generate_templates_version_idx" ON "public"."generate_templates" (
 CREATE INDEX "generate_templates_disabled_idx" ON "public"."generate_templates" ("disabled");
 CREATE INDEX "generate_templates_created_at_idx" ON "public"."generate_templates" ("created_at");
 CREATE INDEX "generate_templates_updated_at_idx" ON "public"."generate_templates" ("updated_at");
+
+CREATE TABLE "public"."generation_jobs" (
+  "id" VARCHAR(64) PRIMARY KEY,
+  "user_id" VARCHAR(64) NOT NULL,
+  -- 0: document, 1: data_export
+  "kind" INTEGER NOT NULL DEFAULT 0,
+  -- kind=documentの時のみ
+  "generate_template_id" VARCHAR(64),
+  "text" TEXT,
+  -- 0: queued, 1: running, 2: succeeded, 3: failed
+  "status" INTEGER NOT NULL,
+  "error_message" TEXT,
+  "result_file_id" VARCHAR(64),
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "updated_at" TIMESTAMP WITH TIME ZONE NOT NULL,
+  "version" INTEGER NOT NULL
+);
+
+CREATE INDEX "generation_jobs_user_id_idx" ON "public"."generation_jobs" ("user_id");
+CREATE INDEX "generation_jobs_version_idx" ON "public"."generation_jobs" ("version");
+
+CREATE TABLE "public"."persisted_queries" (
+  -- クエリ文字列のsha256
+  "hash" VARCHAR(64) PRIMARY KEY,
+  "query" TEXT NOT NULL,
+  "created_at" TIMESTAMP WITH TIME ZONE NOT NULL
+);
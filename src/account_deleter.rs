@@ -0,0 +1,63 @@
+use anyhow::Context;
+use r2d2::Pool;
+use sqlx::PgPool;
+
+use crate::{
+    adapters::{AccountsRepositoryImpl, FilesRepositoryImpl},
+    entities,
+    faktory::FaktoryConnectionManager,
+    job::Job,
+    jobs,
+    ports::{AccountsRepository, FilesRepository},
+};
+
+/*
+ユーザーの全データを削除する
+共有していたfigure_records/character_configsも残さずに削除し、シードは再計算する
+DBの行は1つのトランザクションで削除し、ストレージのオブジェクト(アップロードしたファイルとサムネイル)はワーカーで削除する
+*/
+#[derive(Debug, Clone)]
+pub struct AccountDeleter {
+    pool: PgPool,
+    faktory_pool: Pool<FaktoryConnectionManager>,
+}
+
+impl AccountDeleter {
+    pub fn new(pool: PgPool, faktory_pool: Pool<FaktoryConnectionManager>) -> Self {
+        Self { pool, faktory_pool }
+    }
+
+    pub async fn delete(&mut self, user_id: entities::UserId) -> anyhow::Result<()> {
+        let mut accounts_repository = AccountsRepositoryImpl::new(self.pool.clone());
+        let mut files_repository = FilesRepositoryImpl::new(self.pool.clone());
+
+        // 行を消すとキーが分からなくなるので先に集めておく
+        let keys = files_repository
+            .get_all_by_user_id(user_id.clone())
+            .await
+            .context("load files")?
+            .into_iter()
+            .map(|file| String::from(file.key))
+            .collect();
+        let prefixes = vec![entities::FileKey::user_thumbnails_prefix(&user_id)];
+
+        accounts_repository
+            .delete_all_by_user_id(user_id)
+            .await
+            .context("delete account")?;
+
+        // 行の削除に失敗した場合にオブジェクトだけ消えないように、コミット後に登録する
+        jobs::DeleteStorageObjects { keys, prefixes }
+            .enqueue(&self.faktory_pool)
+            .await
+            .context("enqueue delete_storage_objects")?;
+
+        // 削除したcharacter_configsをシードから除く
+        jobs::UpdateSeeds {}
+            .enqueue(&self.faktory_pool)
+            .await
+            .context("enqueue update_seeds")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use anyhow::Context;
+use sqlx::{Acquire, Postgres};
+
+use crate::{entities, ports};
+
+#[derive(Debug, Clone)]
+pub struct AccountsRepositoryImpl<A> {
+    db: A,
+}
+
+impl<A> AccountsRepositoryImpl<A> {
+    pub fn new(db: A) -> Self {
+        Self { db }
+    }
+}
+
+impl<A> ports::AccountsRepository for AccountsRepositoryImpl<A>
+where
+    A: Send,
+    for<'c> &'c A: Acquire<'c, Database = Postgres>,
+{
+    type Error = anyhow::Error;
+
+    async fn delete_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<(), Self::Error> {
+        let mut trx = self.db.begin().await?;
+        let user_id = String::from(user_id);
+
+        sqlx::query!(
+            r#"
+                DELETE FROM generation_jobs
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *trx)
+        .await
+        .context("delete generation_jobs")?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM generate_templates
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *trx)
+        .await
+        .context("delete generate_templates")?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM figure_records
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *trx)
+        .await
+        .context("delete figure_records")?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM character_config_reference_figures
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *trx)
+        .await
+        .context("delete character_config_reference_figures")?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM character_configs
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *trx)
+        .await
+        .context("delete character_configs")?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM user_configs
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *trx)
+        .await
+        .context("delete user_configs")?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM files
+                WHERE
+                    user_id = $1
+            "#,
+            user_id,
+        )
+        .execute(&mut *trx)
+        .await
+        .context("delete files")?;
+
+        trx.commit().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::adapters::{
+        CharacterConfigsRepositoryImpl, FigureRecordsRepositoryImpl, FilesRepositoryImpl,
+        ReferenceFiguresRepositoryImpl, UserConfigsRepositoryImpl,
+    };
+    use crate::ports::{
+        AccountsRepository, CharacterConfigsRepository, FigureRecordsRepository, FilesRepository,
+        ReferenceFiguresRepository, UserConfigsRepository,
+    };
+
+    #[sqlx::test]
+    async fn test_delete_all_by_user_id(pool: sqlx::PgPool) {
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool.clone());
+        let mut character_configs_repository = CharacterConfigsRepositoryImpl::new(pool.clone());
+        let mut reference_figures_repository = ReferenceFiguresRepositoryImpl::new(pool.clone());
+        let mut user_configs_repository = UserConfigsRepositoryImpl::new(pool.clone());
+        let mut files_repository = FilesRepositoryImpl::new(pool.clone());
+        let now = Utc::now();
+
+        let user_ids = [
+            entities::UserId::from("deleted_user".to_string()),
+            entities::UserId::from("other_user".to_string()),
+        ];
+        for user_id in &user_ids {
+            let figure = entities::Figure::from_json(
+                r#"{"strokes":[{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]}],"width":1,"height":1}"#,
+            )
+            .unwrap();
+            let character = entities::Character::from('一');
+            figure_records_repository
+                .create(user_id.clone(), now, character.clone(), figure.clone())
+                .await
+                .unwrap();
+            character_configs_repository
+                .save(
+                    now,
+                    entities::CharacterConfig::default_config(
+                        user_id.clone(),
+                        character.clone(),
+                        figure.stroke_count(),
+                    ),
+                )
+                .await
+                .unwrap();
+            reference_figures_repository
+                .save(
+                    now,
+                    entities::ReferenceFigure::new(
+                        user_id.clone(),
+                        character,
+                        figure.stroke_count(),
+                        entities::ReferenceFigureVariant::default(),
+                        figure.clone(),
+                    ),
+                )
+                .await
+                .unwrap();
+            let mut user_config = user_configs_repository.get(user_id.clone()).await.unwrap();
+            user_config.allow_sharing_figure_records = true;
+            user_configs_repository
+                .save(now, user_config)
+                .await
+                .unwrap();
+            files_repository
+                .create(
+                    user_id.clone(),
+                    now,
+                    entities::MimeType::try_from("image/png".to_string()).unwrap(),
+                    entities::FileSize::try_from(1).unwrap(),
+                )
+                .await
+                .unwrap();
+        }
+
+        let mut repo = AccountsRepositoryImpl::new(pool.clone());
+        repo.delete_all_by_user_id(user_ids[0].clone())
+            .await
+            .unwrap();
+
+        for (user_id, expected) in [(&user_ids[0], 0), (&user_ids[1], 1)] {
+            let figure_records = figure_records_repository
+                .get_all_by_user_id(user_id.clone())
+                .await
+                .unwrap();
+            assert_eq!(figure_records.len(), expected);
+            let character_configs = character_configs_repository
+                .get_all_by_user_id(user_id.clone())
+                .await
+                .unwrap();
+            assert_eq!(character_configs.len(), expected);
+            let reference_figures = reference_figures_repository
+                .get_all_by_user_id(user_id.clone())
+                .await
+                .unwrap();
+            assert_eq!(reference_figures.len(), expected);
+            let files = files_repository
+                .get_all_by_user_id(user_id.clone())
+                .await
+                .unwrap();
+            assert_eq!(files.len(), expected);
+            // 削除後はデフォルト値になる
+            let user_config = user_configs_repository.get(user_id.clone()).await.unwrap();
+            assert_eq!(user_config.updated_at.is_some(), expected == 1);
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::collections::HashMap;
 use crate::{entities, ports};
 use anyhow::{anyhow, Context};
 use chrono::{DateTime, Utc};
-use sqlx::{Acquire, Postgres};
+use sqlx::{Acquire, PgConnection, Postgres};
 
 #[derive(Debug, Clone)]
 pub struct CharacterConfigModel {
@@ -32,6 +32,79 @@ impl CharacterConfigModel {
     }
 }
 
+// versionが一致しない(他で更新・作成された)場合はNone
+async fn save_character_config(
+    conn: &mut PgConnection,
+    now: DateTime<Utc>,
+    mut character_config: entities::CharacterConfig,
+) -> anyhow::Result<Option<entities::CharacterConfig>> {
+    let prev_version = character_config.version;
+    character_config.version = character_config.version.next();
+    character_config.updated_at = Some(now);
+
+    if prev_version.is_none() {
+        let result = sqlx::query!(
+            r#"
+                INSERT INTO character_configs (user_id, character, updated_at, stroke_count, ratio, version, disabled)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT DO NOTHING
+            "#,
+            String::from(character_config.user_id.clone()),
+            String::from(character_config.character.clone()),
+            character_config.updated_at,
+            i32::from(character_config.stroke_count),
+            i32::from(character_config.ratio),
+            i32::from(character_config.version),
+            character_config.disabled,
+        )
+        .execute(&mut *conn)
+        .await
+        .context("insert character_configs")?;
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+    } else {
+        let result = sqlx::query!(
+            r#"
+                UPDATE character_configs
+                    SET
+                        updated_at = $1,
+                        ratio = $2,
+                        version = $3,
+                        disabled = $8
+                    WHERE
+                        user_id = $4
+                        AND
+                        character = $5
+                        AND
+                        stroke_count = $6
+                        AND
+                        version = $7
+            "#,
+            &character_config
+                .updated_at
+                .ok_or(anyhow!("updated_at is None"))?,
+            i32::from(character_config.ratio),
+            i32::from(character_config.version),
+            String::from(character_config.user_id.clone()),
+            String::from(character_config.character.clone()),
+            i32::from(character_config.stroke_count),
+            i32::from(prev_version),
+            character_config.disabled,
+        )
+        .execute(&mut *conn)
+        .await
+        .context("update character_config")?;
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(character_config))
+}
+
 #[derive(Debug, Clone)]
 pub struct CharacterConfigsRepositoryImpl<A> {
     db: A,
@@ -53,70 +126,31 @@ where
     async fn save(
         &mut self,
         now: DateTime<Utc>,
-        mut character_config: entities::CharacterConfig,
+        character_config: entities::CharacterConfig,
     ) -> Result<entities::CharacterConfig, Self::Error> {
         let mut trx = self.db.begin().await?;
-        let prev_version = character_config.version;
-        character_config.version = character_config.version.next();
-        character_config.updated_at = Some(now);
-
-        if prev_version.is_none() {
-            sqlx::query!(
-                r#"
-                    INSERT INTO character_configs (user_id, character, updated_at, stroke_count, ratio, version, disabled)
-                    VALUES ($1, $2, $3, $4, $5, $6, $7)
-                "#,
-                String::from(character_config.user_id.clone()),
-                String::from(character_config.character.clone()),
-                character_config.updated_at,
-                i32::from(character_config.stroke_count),
-                i32::from(character_config.ratio),
-                i32::from(character_config.version),
-                character_config.disabled,
-            )
-            .execute(&mut *trx)
-            .await
-            .context("insert character_configs")?;
-        } else {
-            let result = sqlx::query!(
-                r#"
-                    UPDATE character_configs
-                        SET
-                            updated_at = $1,
-                            ratio = $2,
-                            version = $3,
-                            disabled = $8
-                        WHERE
-                            user_id = $4
-                            AND
-                            character = $5
-                            AND
-                            stroke_count = $6
-                            AND
-                            version = $7
-                "#,
-                &character_config
-                    .updated_at
-                    .ok_or(anyhow!("updated_at is None"))?,
-                i32::from(character_config.ratio),
-                i32::from(character_config.version),
-                String::from(character_config.user_id.clone()),
-                String::from(character_config.character.clone()),
-                i32::from(character_config.stroke_count),
-                i32::from(prev_version),
-                character_config.disabled,
-            )
-            .execute(&mut *trx)
-            .await
-            .context("update character_config")?;
+        let character_config = save_character_config(&mut trx, now, character_config)
+            .await?
+            .ok_or(ports::ConflictError)?;
 
-            if result.rows_affected() == 0 {
-                return Err(anyhow!("conflict"));
-            }
+        trx.commit().await?;
+        Ok(character_config)
+    }
+
+    async fn save_many(
+        &mut self,
+        now: DateTime<Utc>,
+        character_configs: Vec<entities::CharacterConfig>,
+    ) -> Result<Vec<Result<entities::CharacterConfig, ports::ConflictError>>, Self::Error> {
+        let mut trx = self.db.begin().await?;
+        let mut results = Vec::with_capacity(character_configs.len());
+        for character_config in character_configs {
+            let result = save_character_config(&mut trx, now, character_config).await?;
+            results.push(result.ok_or(ports::ConflictError));
         }
 
         trx.commit().await?;
-        Ok(character_config)
+        Ok(results)
     }
 
     async fn get_by_characters(
@@ -169,52 +203,128 @@ where
     async fn query(
         &mut self,
         user_id: entities::UserId,
-        after_id: Option<(entities::Character, entities::StrokeCount)>,
-        before_id: Option<(entities::Character, entities::StrokeCount)>,
+        filter: &ports::CharacterConfigsQueryFilter,
+        order: ports::CharacterConfigsOrder,
+        after: Option<ports::CharacterConfigsCursor>,
+        before: Option<ports::CharacterConfigsCursor>,
         limit: entities::Limit,
-    ) -> Result<Vec<entities::CharacterConfig>, Self::Error> {
+    ) -> Result<Vec<(entities::CharacterConfig, ports::CharacterConfigsCursor)>, Self::Error> {
         let mut conn = self.db.acquire().await?;
 
-        let after_character = after_id
-            .clone()
-            .map(|(character, _)| String::from(character));
-        let after_stroke_count = after_id.map(|(_, stroke_count)| i32::from(stroke_count));
-        let before_character = before_id
-            .clone()
-            .map(|(character, _)| String::from(character));
-        let before_stroke_count = before_id.map(|(_, stroke_count)| i32::from(stroke_count));
+        let characters = filter.characters.as_ref().map(|characters| {
+            characters
+                .iter()
+                .map(|character| String::from(character.clone()))
+                .collect::<Vec<_>>()
+        });
+        let order_by = match order.by {
+            ports::CharacterConfigsOrderBy::Character => 0,
+            ports::CharacterConfigsOrderBy::UpdatedAt => 1,
+            ports::CharacterConfigsOrderBy::FigureRecordCount => 2,
+            ports::CharacterConfigsOrderBy::Ratio => 3,
+        };
+        let sign: i64 = if order.descending { -1 } else { 1 };
 
-        let models = sqlx::query_as!(
-            CharacterConfigModel,
+        // 降順はsort_keyの符号を反転して昇順に並べることで、カーソルの比較を常に同じ向きにする
+        // figure_recordsの件数は重いので件数順の時のみ数える
+        let rows = sqlx::query!(
             r#"
+            WITH configs AS (
+                SELECT
+                    c.user_id,
+                    c.character,
+                    c.stroke_count,
+                    c.ratio,
+                    c.updated_at,
+                    c.version,
+                    c.disabled,
+                    (
+                        CASE WHEN $6::INTEGER = 2 THEN (
+                            SELECT
+                                COUNT(*)
+                            FROM
+                                figure_records AS r
+                            WHERE
+                                r.user_id = c.user_id
+                                AND r.character = c.character
+                                AND r.stroke_count = c.stroke_count
+                                AND NOT r.disabled
+                        ) ELSE 0 END
+                    ) AS figure_record_count
+                FROM
+                    character_configs AS c
+                WHERE
+                    c.user_id = $1
+                    AND ($2::VARCHAR(8)[] IS NULL OR c.character = Any($2))
+                    AND ($3::INTEGER IS NULL OR c.stroke_count >= $3)
+                    AND ($4::INTEGER IS NULL OR c.stroke_count <= $4)
+                    AND ($5 OR NOT c.disabled)
+                    AND (
+                        NOT $8
+                        OR NOT EXISTS (
+                            SELECT
+                                1
+                            FROM
+                                figure_records AS r
+                            WHERE
+                                r.user_id = c.user_id
+                                AND r.character = c.character
+                                AND r.stroke_count = c.stroke_count
+                                AND NOT r.disabled
+                        )
+                    )
+            ), keyed AS (
+                SELECT
+                    *,
+                    (
+                        CASE $6::INTEGER
+                            WHEN 1 THEN (EXTRACT(EPOCH FROM updated_at) * 1000000)::BIGINT
+                            WHEN 2 THEN figure_record_count
+                            WHEN 3 THEN ratio::BIGINT
+                            ELSE 0::BIGINT
+                        END
+                    ) * $7::BIGINT AS sort_key
+                FROM
+                    configs
+            )
             SELECT
-                user_id,
-                character,
-                stroke_count,
-                ratio,
-                updated_at,
-                version,
-                disabled
+                user_id AS "user_id!",
+                character AS "character!",
+                stroke_count AS "stroke_count!",
+                ratio AS "ratio!",
+                updated_at AS "updated_at!",
+                version AS "version!",
+                disabled AS "disabled!",
+                sort_key AS "sort_key!"
             FROM
-                character_configs
+                keyed
             WHERE
-                user_id = $1
-                AND
-                ($2::VARCHAR(64) IS NULL OR (character, stroke_count) > ($2, $3))
+                ($9::BIGINT IS NULL OR (sort_key, character, stroke_count) > ($9, $10::VARCHAR(8), $11::INTEGER))
                 AND
-                ($4::VARCHAR(64) IS NULL OR (character, stroke_count) < ($4, $5))
-                AND
-                disabled = false
+                ($12::BIGINT IS NULL OR (sort_key, character, stroke_count) < ($12, $13::VARCHAR(8), $14::INTEGER))
             ORDER BY
-                CASE WHEN $6 = 0 THEN (character, stroke_count) END ASC,
-                CASE WHEN $6 = 1 THEN (character, stroke_count) END DESC
-            LIMIT $7
+                CASE WHEN $15 = 0 THEN (sort_key, character, stroke_count) END ASC,
+                CASE WHEN $15 = 1 THEN (sort_key, character, stroke_count) END DESC
+            LIMIT $16
         "#,
             String::from(user_id.clone()),
-            after_character,
-            after_stroke_count,
-            before_character,
-            before_stroke_count,
+            characters.as_ref().map(|characters| characters.as_slice()),
+            filter.min_stroke_count.map(i32::from),
+            filter.max_stroke_count.map(i32::from),
+            filter.include_disabled,
+            order_by,
+            sign,
+            filter.without_figure_records,
+            after.as_ref().map(|cursor| cursor.sort_key),
+            after
+                .as_ref()
+                .map(|cursor| String::from(cursor.character.clone())),
+            after.as_ref().map(|cursor| i32::from(cursor.stroke_count)),
+            before.as_ref().map(|cursor| cursor.sort_key),
+            before
+                .as_ref()
+                .map(|cursor| String::from(cursor.character.clone())),
+            before.as_ref().map(|cursor| i32::from(cursor.stroke_count)),
             i32::from(limit.kind() == entities::LimitKind::Last),
             i64::from(limit.value()),
         )
@@ -222,9 +332,27 @@ where
         .await
         .context("fetch character_configs")?;
 
-        let character_configs = models
+        let character_configs = rows
             .into_iter()
-            .map(|row| row.into_entity())
+            .map(|row| {
+                let sort_key = row.sort_key;
+                let character_config = CharacterConfigModel {
+                    user_id: row.user_id,
+                    character: row.character,
+                    stroke_count: row.stroke_count,
+                    updated_at: row.updated_at,
+                    version: row.version,
+                    ratio: row.ratio,
+                    disabled: row.disabled,
+                }
+                .into_entity()?;
+                let cursor = ports::CharacterConfigsCursor {
+                    sort_key,
+                    character: character_config.character.clone(),
+                    stroke_count: character_config.stroke_count,
+                };
+                Ok((character_config, cursor))
+            })
             .collect::<anyhow::Result<Vec<_>>>()
             .context("convert CharacterConfig")?;
 
@@ -313,4 +441,315 @@ where
 
         Ok(character_config_map)
     }
+
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::CharacterConfig>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let models = sqlx::query_as!(
+            CharacterConfigModel,
+            r#"
+            SELECT
+                user_id,
+                character,
+                stroke_count,
+                ratio,
+                updated_at,
+                version,
+                disabled
+            FROM
+                character_configs
+            WHERE
+                user_id = $1
+            ORDER BY
+                character, stroke_count
+        "#,
+            String::from(user_id.clone()),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch character_configs")?;
+
+        let character_configs = models
+            .into_iter()
+            .map(|row| row.into_entity())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert CharacterConfig")?;
+
+        Ok(character_configs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::adapters::FigureRecordsRepositoryImpl;
+    use crate::ports::{CharacterConfigsRepository, FigureRecordsRepository};
+
+    fn figure(stroke_count: i32) -> entities::Figure {
+        let strokes = (0..stroke_count)
+            .map(|_| r#"{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]}"#)
+            .collect::<Vec<_>>()
+            .join(",");
+        entities::Figure::from_json(&format!(
+            r#"{{"strokes":[{}],"width":1,"height":1}}"#,
+            strokes
+        ))
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_query_order(pool: sqlx::PgPool) {
+        let mut repo = CharacterConfigsRepositoryImpl::new(pool.clone());
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let now = Utc::now();
+
+        // (character, stroke_count, ratio, figure_recordsの件数)
+        let configs = [
+            ('a', 1, 50, 2),
+            ('b', 1, 50, 0),
+            ('c', 1, 10, 2),
+            ('c', 2, 50, 1),
+        ];
+        for (character, stroke_count, ratio, count) in configs {
+            let character = entities::Character::from(character);
+            repo.save(
+                now,
+                entities::CharacterConfig::default_config(
+                    user_id.clone(),
+                    character.clone(),
+                    entities::StrokeCount::try_from(stroke_count).unwrap(),
+                )
+                .with_ratio(entities::Ratio::try_from(ratio).unwrap())
+                .with_disabled(false),
+            )
+            .await
+            .unwrap();
+            for _ in 0..count {
+                figure_records_repository
+                    .create(
+                        user_id.clone(),
+                        now,
+                        character.clone(),
+                        figure(stroke_count),
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let cases = [
+            (
+                ports::CharacterConfigsOrderBy::Character,
+                false,
+                vec![('a', 1), ('b', 1), ('c', 1), ('c', 2)],
+            ),
+            // 同じsort_keyは(character, stroke_count)の順
+            (
+                ports::CharacterConfigsOrderBy::FigureRecordCount,
+                true,
+                vec![('a', 1), ('c', 1), ('c', 2), ('b', 1)],
+            ),
+            (
+                ports::CharacterConfigsOrderBy::Ratio,
+                false,
+                vec![('c', 1), ('a', 1), ('b', 1), ('c', 2)],
+            ),
+            (
+                ports::CharacterConfigsOrderBy::Ratio,
+                true,
+                vec![('a', 1), ('b', 1), ('c', 2), ('c', 1)],
+            ),
+        ];
+        for (by, descending, expected) in cases {
+            let order = ports::CharacterConfigsOrder { by, descending };
+            let filter = ports::CharacterConfigsQueryFilter::default();
+
+            // 1件ずつカーソルで辿っても同じ順になる
+            let mut forward = Vec::new();
+            let mut after = None;
+            loop {
+                let page = repo
+                    .query(
+                        user_id.clone(),
+                        &filter,
+                        order,
+                        after.clone(),
+                        None,
+                        entities::Limit::new(entities::LimitKind::First, 1).unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let Some((character_config, cursor)) = page.into_iter().next() else {
+                    break;
+                };
+                forward.push((
+                    char::from(character_config.character),
+                    i32::from(character_config.stroke_count),
+                ));
+                after = Some(cursor);
+            }
+            assert_eq!(forward, expected, "{:?}", order);
+
+            let mut backward = Vec::new();
+            let mut before = None;
+            loop {
+                let page = repo
+                    .query(
+                        user_id.clone(),
+                        &filter,
+                        order,
+                        None,
+                        before.clone(),
+                        entities::Limit::new(entities::LimitKind::Last, 1).unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let Some((character_config, cursor)) = page.into_iter().next() else {
+                    break;
+                };
+                backward.push((
+                    char::from(character_config.character),
+                    i32::from(character_config.stroke_count),
+                ));
+                before = Some(cursor);
+            }
+            backward.reverse();
+            assert_eq!(backward, expected, "{:?}", order);
+        }
+
+        let without_figure_records = repo
+            .query(
+                user_id.clone(),
+                &ports::CharacterConfigsQueryFilter {
+                    without_figure_records: true,
+                    ..Default::default()
+                },
+                ports::CharacterConfigsOrder::default(),
+                None,
+                None,
+                entities::Limit::new(entities::LimitKind::First, 100).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            without_figure_records
+                .into_iter()
+                .map(|(character_config, _)| char::from(character_config.character))
+                .collect::<Vec<_>>(),
+            vec!['b']
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_save_many_conflict(pool: sqlx::PgPool) {
+        let mut repo = CharacterConfigsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let stroke_count = entities::StrokeCount::try_from(1).unwrap();
+        let config = |character: char| {
+            entities::CharacterConfig::default_config(
+                user_id.clone(),
+                entities::Character::from(character),
+                stroke_count,
+            )
+        };
+        let ratio = |value: i32| entities::Ratio::try_from(value).unwrap();
+
+        let a = repo.save(now, config('a')).await.unwrap();
+        let c = repo.save(now, config('c')).await.unwrap();
+        repo.save(now, config('d')).await.unwrap();
+
+        // 途中の要素が競合しても、他の要素は保存される
+        let mut stale_a = a.clone().with_ratio(ratio(10));
+        stale_a.version = a.version.next();
+        let results = repo
+            .save_many(
+                now,
+                vec![
+                    config('b').with_ratio(ratio(10)),
+                    stale_a,
+                    c.clone().with_ratio(ratio(10)),
+                    // 未作成のつもりで作成しようとしたが既に作成されていた
+                    config('d').with_ratio(ratio(10)),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result
+                    .as_ref()
+                    .map(|character_config| (
+                        character_config.character.clone(),
+                        character_config.version
+                    ))
+                    .map_err(|_| ()))
+                .collect::<Vec<_>>(),
+            vec![
+                Ok((entities::Character::from('b'), entities::Version::new())),
+                Err(()),
+                Ok((entities::Character::from('c'), c.version.next())),
+                Err(()),
+            ]
+        );
+
+        let saved = repo
+            .get_by_ids(
+                user_id.clone(),
+                &['a', 'b', 'c', 'd']
+                    .map(|character| (entities::Character::from(character), stroke_count)),
+            )
+            .await
+            .unwrap();
+        let ratios = ['a', 'b', 'c', 'd'].map(|character| {
+            let character_config = &saved[&(entities::Character::from(character), stroke_count)];
+            (i32::from(character_config.ratio), character_config.version)
+        });
+        assert_eq!(
+            ratios,
+            [
+                (i32::from(entities::Ratio::default()), a.version),
+                (10, entities::Version::new()),
+                (10, c.version.next()),
+                (
+                    i32::from(entities::Ratio::default()),
+                    entities::Version::new()
+                ),
+            ]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_save_conflict(pool: sqlx::PgPool) {
+        let mut repo = CharacterConfigsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let config = entities::CharacterConfig::default_config(
+            user_id.clone(),
+            entities::Character::from('a'),
+            entities::StrokeCount::try_from(1).unwrap(),
+        );
+
+        let saved = repo.save(now, config.clone()).await.unwrap();
+        assert_eq!(saved.version, entities::Version::new());
+
+        // 未作成(version 0)のつもりで作成したが、既に作成されていた(ON CONFLICT DO NOTHING)
+        let err = repo.save(now, config.clone()).await.unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+
+        // 古いversionでの更新
+        let updated = repo.save(now, saved.clone()).await.unwrap();
+        let err = repo.save(now, saved).await.unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+
+        let updated = repo.save(now, updated).await.unwrap();
+        assert_eq!(updated.version, entities::Version::new().next().next());
+    }
 }
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use anyhow::{anyhow, ensure, Context};
-use sqlx::{Acquire, Postgres};
+use sqlx::{Acquire, PgConnection, Postgres};
 use ulid::Ulid;
 
 use crate::{entities, ports};
@@ -16,6 +16,7 @@ struct FigureRecordModel {
     created_at: DateTime<Utc>,
     stroke_count: i32,
     disabled: bool,
+    disabled_at: Option<DateTime<Utc>>,
     version: i32,
 }
 
@@ -40,11 +41,54 @@ impl FigureRecordModel {
             figure,
             created_at: self.created_at,
             disabled: self.disabled,
+            disabled_at: self.disabled_at,
             version: entities::Version::try_from(self.version)?,
         })
     }
 }
 
+fn new_figure_record(
+    id: Ulid,
+    user_id: entities::UserId,
+    now: DateTime<Utc>,
+    character: entities::Character,
+    figure: entities::Figure,
+) -> entities::FigureRecord {
+    entities::FigureRecord {
+        id: entities::FigureRecordId::from(id),
+        user_id,
+        character,
+        figure,
+        created_at: now,
+        disabled: false,
+        disabled_at: None,
+        version: entities::Version::new(),
+    }
+}
+
+async fn insert_figure_record(
+    conn: &mut PgConnection,
+    record: &entities::FigureRecord,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        r#"
+                INSERT INTO figure_records (id, user_id, character, figure, created_at, stroke_count)
+                VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        Ulid::from(record.id).to_string(),
+        String::from(record.user_id.clone()),
+        String::from(record.character.clone()),
+        record.figure.to_json_ast(),
+        record.created_at,
+        i32::from(record.figure.stroke_count()),
+    )
+    .execute(&mut *conn)
+    .await
+    .context("insert figure_record")?;
+
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct FigureRecordsRepositoryImpl<A> {
     db: A,
@@ -71,63 +115,60 @@ where
         figure: entities::Figure,
     ) -> Result<entities::FigureRecord, Self::Error> {
         let mut trx = self.db.begin().await?;
-        let record = entities::FigureRecord {
-            id: entities::FigureRecordId::from(Ulid::from_datetime(now)),
-            user_id,
-            character,
-            figure,
-            created_at: now,
-            disabled: false,
-            version: entities::Version::new(),
-        };
-
-        sqlx::query!(
-            r#"
-                INSERT INTO figure_records (id, user_id, character, figure, created_at, stroke_count)
-                VALUES ($1, $2, $3, $4, $5, $6)
-            "#,
-            Ulid::from(record.id).to_string(),
-            String::from(record.user_id.clone()),
-            String::from(record.character.clone()),
-            record.figure.to_json_ast(),
-            record.created_at,
-            i32::from(record.figure.stroke_count()),
-        )
-        .execute(&mut *trx)
-        .await
-        .context("fetch figure_records")?;
+        let record = new_figure_record(Ulid::from_datetime(now), user_id, now, character, figure);
+        insert_figure_record(&mut trx, &record).await?;
 
         trx.commit().await?;
         Ok(record)
     }
 
+    async fn create_many(
+        &mut self,
+        user_id: entities::UserId,
+        now: DateTime<Utc>,
+        generator: &mut ulid::Generator,
+        figures: Vec<(entities::Character, entities::Figure)>,
+    ) -> Result<Vec<entities::FigureRecord>, Self::Error> {
+        let mut trx = self.db.begin().await?;
+        let mut records = Vec::with_capacity(figures.len());
+        for (character, figure) in figures {
+            let id = generator
+                .generate_from_datetime(now)
+                .context("generate figure_record id")?;
+            let record = new_figure_record(id, user_id.clone(), now, character, figure);
+            insert_figure_record(&mut trx, &record).await?;
+            records.push(record);
+        }
+
+        trx.commit().await?;
+        Ok(records)
+    }
+
     async fn update(
         &mut self,
         mut figure_record: entities::FigureRecord,
-        disabled: Option<bool>,
     ) -> Result<entities::FigureRecord, Self::Error> {
         let mut trx = self.db.begin().await?;
         let prev_version = figure_record.version;
 
         figure_record.version = figure_record.version.next();
-        if let Some(disabled) = disabled {
-            figure_record.disabled = disabled;
-        }
 
         let result = sqlx::query!(
             r#"
             UPDATE figure_records
                 SET
                     disabled = $1,
-                    version = $2
+                    disabled_at = $2,
+                    version = $3
                 WHERE
-                    user_id = $3
+                    user_id = $4
                     AND
-                    id = $4
+                    id = $5
                     AND
-                    version = $5
+                    version = $6
             "#,
-            disabled,
+            figure_record.disabled,
+            figure_record.disabled_at,
             i32::from(figure_record.version),
             String::from(figure_record.user_id.clone()),
             Ulid::from(figure_record.id.clone()).to_string(),
@@ -138,7 +179,7 @@ where
         .context("update figure_record")?;
 
         if result.rows_affected() == 0 {
-            return Err(anyhow!("conflict"));
+            return Err(ports::ConflictError.into());
         }
 
         trx.commit().await?;
@@ -167,6 +208,7 @@ where
                     r.created_at,
                     r.stroke_count,
                     r.disabled,
+                    r.disabled_at,
                     r.version
                 FROM
                     figure_records AS r
@@ -174,6 +216,7 @@ where
                 WHERE
                     r.id = Any($1)
                     AND (r.user_id = $2 OR user_configs.allow_sharing_figure_records)
+                    AND NOT r.disabled
             "#,
             ids.as_slice(),
             String::from(user_id.clone()),
@@ -191,52 +234,152 @@ where
         Ok(figure_records)
     }
 
-    async fn get_by_character_config_ids(
+    async fn get_by_ids_include_disabled(
         &mut self,
         user_id: entities::UserId,
-        character_config_ids: &[(entities::Character, entities::StrokeCount)],
-        ids: Option<&[entities::FigureRecordId]>,
-        after_id: Option<entities::FigureRecordId>,
-        before_id: Option<entities::FigureRecordId>,
-        limit_per_character: entities::Limit,
-        user_type: Option<ports::UserType>,
+        ids: &[entities::FigureRecordId],
     ) -> Result<Vec<entities::FigureRecord>, Self::Error> {
         let mut conn = self.db.acquire().await?;
+        let ids = ids
+            .iter()
+            .map(|&id| Ulid::from(id).to_string())
+            .collect::<Vec<_>>();
 
-        let ids = ids.as_ref().map(|ids| {
-            ids.iter()
-                .map(|&id| Ulid::from(id).to_string())
-                .collect::<Vec<_>>()
-        });
+        let models = sqlx::query_as!(
+            FigureRecordModel,
+            r#"
+                SELECT
+                    id,
+                    user_id,
+                    character,
+                    figure,
+                    created_at,
+                    stroke_count,
+                    disabled,
+                    disabled_at,
+                    version
+                FROM
+                    figure_records
+                WHERE
+                    id = Any($1)
+                    AND user_id = $2
+            "#,
+            ids.as_slice(),
+            String::from(user_id),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch figure_records")?;
 
-        let character_values = character_config_ids
+        let figure_records = models
+            .into_iter()
+            .map(|model| model.into_entity())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert FigureRecord")?;
+
+        Ok(figure_records)
+    }
+
+    async fn get_by_character_config_ids(
+        &mut self,
+        user_id: entities::UserId,
+        queries: &[ports::FigureRecordsByCharacterConfigIdQuery],
+    ) -> Result<Vec<Vec<entities::FigureRecord>>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        // 問い合わせごとにカーソルなどが異なっても1回のクエリで取得できるように、列ごとの配列にして渡す
+        // idxはWITH ORDINALITYと同じく1始まり
+        let character_values = queries
             .iter()
-            .map(|(character, _)| String::from(character.clone()))
+            .map(|query| String::from(query.character.clone()))
             .collect::<Vec<_>>();
-
-        let stroke_count_values = character_config_ids
+        let stroke_count_values = queries
             .iter()
-            .map(|(_, stroke_count)| i32::from(*stroke_count))
+            .map(|query| i32::from(query.stroke_count))
             .collect::<Vec<_>>();
+        let after_id_values = queries
+            .iter()
+            .map(|query| query.after_id.map(|id| Ulid::from(id).to_string()))
+            .collect::<Vec<_>>();
+        let before_id_values = queries
+            .iter()
+            .map(|query| query.before_id.map(|id| Ulid::from(id).to_string()))
+            .collect::<Vec<_>>();
+        let is_last_values = queries
+            .iter()
+            .map(|query| i32::from(query.limit.kind() == entities::LimitKind::Last))
+            .collect::<Vec<_>>();
+        let limit_values = queries
+            .iter()
+            .map(|query| i64::from(query.limit.value()))
+            .collect::<Vec<_>>();
+        let user_type_values = queries
+            .iter()
+            .map(|query| match query.user_type {
+                None => 0,
+                Some(ports::UserType::Myself) => 1,
+                Some(ports::UserType::Other) => 2,
+            })
+            .collect::<Vec<_>>();
+        let has_ids_values = queries
+            .iter()
+            .map(|query| query.ids.is_some())
+            .collect::<Vec<_>>();
+        let (id_idx_values, id_values): (Vec<_>, Vec<_>) = queries
+            .iter()
+            .enumerate()
+            .flat_map(|(i, query)| {
+                query
+                    .ids
+                    .iter()
+                    .flatten()
+                    .map(move |&id| ((i + 1) as i64, Ulid::from(id).to_string()))
+            })
+            .unzip();
 
-        let models = sqlx::query_as!(
-            FigureRecordModel,
+        let rows = sqlx::query!(
             r#"
                     WITH inputs AS (
-                        SELECT character, stroke_count
-                        FROM unnest($1::VARCHAR(8)[], $2::INTEGER[]) AS t(character, stroke_count)
+                        SELECT *
+                        FROM unnest(
+                            $1::VARCHAR(8)[],
+                            $2::INTEGER[],
+                            $3::VARCHAR(64)[],
+                            $4::VARCHAR(64)[],
+                            $5::INTEGER[],
+                            $6::BIGINT[],
+                            $7::INTEGER[],
+                            $8::BOOLEAN[]
+                        ) WITH ORDINALITY AS t(
+                            character,
+                            stroke_count,
+                            after_id,
+                            before_id,
+                            is_last,
+                            row_limit,
+                            user_type,
+                            has_ids,
+                            idx
+                        )
+                    ), input_ids AS (
+                        SELECT *
+                        FROM unnest($9::BIGINT[], $10::VARCHAR(64)[]) AS t(idx, id)
                     )
                     SELECT
-                        id,
-                        user_id,
-                        character,
-                        figure,
-                        created_at,
-                        stroke_count,
-                        disabled,
-                        version
+                        idx AS "idx!",
+                        id AS "id!",
+                        user_id AS "user_id!",
+                        character AS "character!",
+                        figure AS "figure!",
+                        created_at AS "created_at!",
+                        stroke_count AS "stroke_count!",
+                        disabled AS "disabled!",
+                        disabled_at,
+                        version AS "version!"
                     FROM (
                         SELECT
+                            inputs.idx,
+                            inputs.row_limit,
                             r.id,
                             r.user_id,
                             r.character,
@@ -244,50 +387,155 @@ where
                             r.created_at,
                             r.stroke_count,
                             rank() OVER (
-                                PARTITION BY r.character, r.stroke_count
+                                PARTITION BY inputs.idx
                                 ORDER BY
-                                    CASE WHEN $7 = 0 THEN r.id END DESC,
-                                    CASE WHEN $7 = 1 THEN r.id END ASC
+                                    CASE WHEN inputs.is_last = 0 THEN r.id END DESC,
+                                    CASE WHEN inputs.is_last = 1 THEN r.id END ASC
                             ) AS rank,
                             r.disabled,
+                            r.disabled_at,
                             r.version
                         FROM
-                            figure_records AS r
+                            inputs
                         JOIN
-                            inputs ON r.character = inputs.character
+                            figure_records AS r ON r.character = inputs.character
                             AND
                             r.stroke_count = inputs.stroke_count
                         LEFT OUTER JOIN user_configs ON r.user_id = user_configs.user_id
                         WHERE
-                            (r.user_id = $3 OR user_configs.allow_sharing_figure_records)
+                            (r.user_id = $11 OR user_configs.allow_sharing_figure_records)
                             AND
-                            ($4::VARCHAR(64)[] IS NULL OR r.id = Any($4))
+                            (
+                                NOT inputs.has_ids
+                                OR
+                                EXISTS (
+                                    SELECT 1
+                                    FROM input_ids
+                                    WHERE input_ids.idx = inputs.idx AND input_ids.id = r.id
+                                )
+                            )
                             AND
-                            ($5::VARCHAR(64) IS NULL OR r.id < $5)
+                            (inputs.after_id IS NULL OR r.id < inputs.after_id)
                             AND
-                            ($6::VARCHAR(64) IS NULL OR r.id > $6)
+                            (inputs.before_id IS NULL OR r.id > inputs.before_id)
                             AND
-                            (NOT $9 OR r.user_id = $3)
+                            (inputs.user_type <> 1 OR r.user_id = $11)
                             AND
-                            (NOT $10 OR r.user_id <> $3)
+                            (inputs.user_type <> 2 OR r.user_id <> $11)
                             AND
                             NOT r.disabled
                     ) as r
                     WHERE
-                        rank <= $8
+                        rank <= row_limit
                     ORDER BY
+                        idx ASC,
                         id DESC
                 "#,
             character_values.as_slice(),
             stroke_count_values.as_slice(),
+            after_id_values.as_slice() as &[Option<String>],
+            before_id_values.as_slice() as &[Option<String>],
+            is_last_values.as_slice(),
+            limit_values.as_slice(),
+            user_type_values.as_slice(),
+            has_ids_values.as_slice(),
+            id_idx_values.as_slice(),
+            id_values.as_slice(),
+            String::from(user_id.clone()),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch figure_records")?;
+
+        let mut figure_records = vec![Vec::new(); queries.len()];
+        for row in rows {
+            let i = usize::try_from(row.idx - 1).context("into usize")?;
+            let figure_record = FigureRecordModel {
+                id: row.id,
+                user_id: row.user_id,
+                character: row.character,
+                figure: row.figure,
+                created_at: row.created_at,
+                stroke_count: row.stroke_count,
+                disabled: row.disabled,
+                disabled_at: row.disabled_at,
+                version: row.version,
+            }
+            .into_entity()
+            .context("convert FigureRecord")?;
+            figure_records
+                .get_mut(i)
+                .ok_or_else(|| anyhow!("idx out of range"))?
+                .push(figure_record);
+        }
+
+        Ok(figure_records)
+    }
+
+    async fn query(
+        &mut self,
+        user_id: entities::UserId,
+        filter: &ports::FigureRecordsQueryFilter,
+        after_id: Option<entities::FigureRecordId>,
+        before_id: Option<entities::FigureRecordId>,
+        limit: entities::Limit,
+    ) -> Result<Vec<entities::FigureRecord>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let characters = filter.characters.as_ref().map(|characters| {
+            characters
+                .iter()
+                .map(|character| String::from(character.clone()))
+                .collect::<Vec<_>>()
+        });
+
+        let models = sqlx::query_as!(
+            FigureRecordModel,
+            r#"
+                SELECT
+                    r.id,
+                    r.user_id,
+                    r.character,
+                    r.figure,
+                    r.created_at,
+                    r.stroke_count,
+                    r.disabled,
+                    r.disabled_at,
+                    r.version
+                FROM
+                    figure_records AS r
+                    LEFT OUTER JOIN user_configs ON r.user_id = user_configs.user_id
+                WHERE
+                    (r.user_id = $1 OR user_configs.allow_sharing_figure_records)
+                    AND (NOT r.disabled OR r.user_id = $1)
+                    AND ($2::VARCHAR(8)[] IS NULL OR r.character = Any($2))
+                    AND ($3::INTEGER IS NULL OR r.stroke_count >= $3)
+                    AND ($4::INTEGER IS NULL OR r.stroke_count <= $4)
+                    AND ($5::TIMESTAMPTZ IS NULL OR r.created_at >= $5)
+                    AND ($6::TIMESTAMPTZ IS NULL OR r.created_at < $6)
+                    AND (NOT $7 OR r.user_id = $1)
+                    AND (NOT $8 OR r.user_id <> $1)
+                    AND ($9::BOOLEAN IS NULL OR r.disabled = $9)
+                    AND ($10::VARCHAR(64) IS NULL OR r.id > $10)
+                    AND ($11::VARCHAR(64) IS NULL OR r.id < $11)
+                ORDER BY
+                    CASE WHEN $12 = 0 THEN r.id END ASC,
+                    CASE WHEN $12 = 1 THEN r.id END DESC
+                LIMIT $13
+            "#,
             String::from(user_id.clone()),
-            ids.as_ref().map(|ids| ids.as_slice()),
+            characters.as_ref().map(|characters| characters.as_slice()),
+            filter.min_stroke_count.map(i32::from),
+            filter.max_stroke_count.map(i32::from),
+            filter.created_after,
+            filter.created_before,
+            filter.user_type == Some(ports::UserType::Myself),
+            filter.user_type == Some(ports::UserType::Other),
+            filter.disabled,
             after_id.map(|id| Ulid::from(id).to_string()),
             before_id.map(|id| Ulid::from(id).to_string()),
-            i32::from(limit_per_character.kind() == entities::LimitKind::Last),
-            i64::from(limit_per_character.value()),
-            user_type == Some(ports::UserType::Myself),
-            user_type == Some(ports::UserType::Other),
+            i32::from(limit.kind() == entities::LimitKind::Last),
+            i64::from(limit.value()),
         )
         .fetch_all(&mut *conn)
         .await
@@ -301,4 +549,890 @@ where
 
         Ok(figure_records)
     }
+
+    async fn query_trashed(
+        &mut self,
+        user_id: entities::UserId,
+        after: Option<ports::TrashedFigureRecordsCursor>,
+        before: Option<ports::TrashedFigureRecordsCursor>,
+        limit: entities::Limit,
+    ) -> Result<Vec<(entities::FigureRecord, ports::TrashedFigureRecordsCursor)>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+                SELECT
+                    id,
+                    user_id,
+                    character,
+                    figure,
+                    created_at,
+                    stroke_count,
+                    disabled,
+                    disabled_at AS "disabled_at!",
+                    version
+                FROM
+                    figure_records
+                WHERE
+                    user_id = $1
+                    AND disabled
+                    AND disabled_at IS NOT NULL
+                    AND ($2::TIMESTAMPTZ IS NULL OR (disabled_at, id) < ($2, $3))
+                    AND ($4::TIMESTAMPTZ IS NULL OR (disabled_at, id) > ($4, $5))
+                ORDER BY
+                    CASE WHEN $6 = 0 THEN (disabled_at, id) END DESC,
+                    CASE WHEN $6 = 1 THEN (disabled_at, id) END ASC
+                LIMIT $7
+            "#,
+            String::from(user_id.clone()),
+            after.as_ref().map(|after| after.disabled_at),
+            after.as_ref().map(|after| Ulid::from(after.id).to_string()),
+            before.as_ref().map(|before| before.disabled_at),
+            before
+                .as_ref()
+                .map(|before| Ulid::from(before.id).to_string()),
+            i32::from(limit.kind() == entities::LimitKind::Last),
+            i64::from(limit.value()),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch figure_records")?;
+
+        let figure_records = rows
+            .into_iter()
+            .map(|row| -> anyhow::Result<_> {
+                let figure_record = FigureRecordModel {
+                    id: row.id,
+                    user_id: row.user_id,
+                    character: row.character,
+                    figure: row.figure,
+                    created_at: row.created_at,
+                    stroke_count: row.stroke_count,
+                    disabled: row.disabled,
+                    disabled_at: Some(row.disabled_at),
+                    version: row.version,
+                }
+                .into_entity()?;
+                let cursor = ports::TrashedFigureRecordsCursor {
+                    disabled_at: row.disabled_at,
+                    id: figure_record.id,
+                };
+                Ok((figure_record, cursor))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert FigureRecord")?;
+
+        Ok(figure_records)
+    }
+
+    async fn get_stats_by_characters(
+        &mut self,
+        user_id: entities::UserId,
+        characters: &[entities::Character],
+    ) -> Result<Vec<ports::FigureRecordStats>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let character_values = characters
+            .iter()
+            .map(|character| String::from(character.clone()))
+            .collect::<Vec<_>>();
+
+        let rows = sqlx::query!(
+            r#"
+                SELECT
+                    r.character,
+                    r.stroke_count,
+                    COUNT(*) FILTER (WHERE r.user_id = $1) AS "my_count!",
+                    COUNT(*) FILTER (WHERE r.user_id <> $1) AS "other_count!",
+                    MAX(r.created_at) FILTER (WHERE r.user_id = $1) AS last_recorded_at
+                FROM
+                    figure_records AS r
+                    LEFT OUTER JOIN user_configs ON r.user_id = user_configs.user_id
+                WHERE
+                    r.character = Any($2)
+                    AND
+                    (r.user_id = $1 OR user_configs.allow_sharing_figure_records)
+                    AND
+                    NOT r.disabled
+                GROUP BY
+                    r.character,
+                    r.stroke_count
+            "#,
+            String::from(user_id),
+            character_values.as_slice(),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch figure_record stats")?;
+
+        let stats = rows
+            .into_iter()
+            .map(|row| -> anyhow::Result<_> {
+                Ok(ports::FigureRecordStats {
+                    character: entities::Character::try_from(row.character.as_str())?,
+                    stroke_count: entities::StrokeCount::try_from(row.stroke_count)?,
+                    my_count: row.my_count,
+                    other_count: row.other_count,
+                    last_recorded_at: row.last_recorded_at,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert FigureRecordStats")?;
+
+        Ok(stats)
+    }
+
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::FigureRecord>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let models = sqlx::query_as!(
+            FigureRecordModel,
+            r#"
+                SELECT
+                    id,
+                    user_id,
+                    character,
+                    figure,
+                    created_at,
+                    stroke_count,
+                    disabled,
+                    disabled_at,
+                    version
+                FROM
+                    figure_records
+                WHERE
+                    user_id = $1
+                ORDER BY
+                    id
+            "#,
+            String::from(user_id.clone()),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch figure_records")?;
+
+        let records = models
+            .into_iter()
+            .map(|model| model.into_entity())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert FigureRecord")?;
+
+        Ok(records)
+    }
+
+    async fn get_disabled_before(
+        &mut self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<(entities::UserId, entities::FigureRecordId)>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let rows = sqlx::query!(
+            r#"
+                SELECT
+                    user_id,
+                    id
+                FROM
+                    figure_records
+                WHERE
+                    disabled
+                    AND
+                    disabled_at < $1
+            "#,
+            before,
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch figure_records")?;
+
+        let ids = rows
+            .into_iter()
+            .map(|row| {
+                let id = Ulid::from_str(&row.id)
+                    .map(entities::FigureRecordId::from)
+                    .context("ulid decode error")?;
+                Ok((entities::UserId::from(row.user_id), id))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(ids)
+    }
+
+    async fn delete_disabled_before(
+        &mut self,
+        before: DateTime<Utc>,
+        ids: &[entities::FigureRecordId],
+    ) -> Result<(), Self::Error> {
+        let mut conn = self.db.acquire().await?;
+        let ids = ids
+            .iter()
+            .map(|id| Ulid::from(*id).to_string())
+            .collect::<Vec<_>>();
+
+        sqlx::query!(
+            r#"
+                DELETE FROM figure_records
+                WHERE
+                    id = Any($1)
+                    AND
+                    disabled
+                    AND
+                    disabled_at < $2
+            "#,
+            ids.as_slice(),
+            before,
+        )
+        .execute(&mut *conn)
+        .await
+        .context("delete figure_records")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::adapters::UserConfigsRepositoryImpl;
+    use crate::ports::{FigureRecordsRepository, UserConfigsRepository};
+
+    fn figure() -> entities::Figure {
+        entities::Figure::from_json(
+            r#"{"strokes":[{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]}],"width":1,"height":1}"#,
+        )
+        .unwrap()
+    }
+
+    fn figure2() -> entities::Figure {
+        entities::Figure::from_json(
+            r#"{"strokes":[{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]},{"points":[{"x":1,"y":0,"z":1},{"x":0,"y":1,"z":1}]}],"width":1,"height":1}"#,
+        )
+        .unwrap()
+    }
+
+    async fn allow_sharing(pool: &sqlx::PgPool, user_id: &entities::UserId, now: DateTime<Utc>) {
+        let mut user_configs_repository = UserConfigsRepositoryImpl::new(pool.clone());
+        let mut user_config = user_configs_repository.get(user_id.clone()).await.unwrap();
+        user_config.allow_sharing_figure_records = true;
+        user_configs_repository
+            .save(now, user_config)
+            .await
+            .unwrap();
+    }
+
+    fn ids(figure_records: &[entities::FigureRecord]) -> Vec<entities::FigureRecordId> {
+        figure_records.iter().map(|record| record.id).collect()
+    }
+
+    #[sqlx::test]
+    async fn test_query(pool: sqlx::PgPool) {
+        let mut repo = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let sharing_user_id = entities::UserId::from("sharing_user".to_string());
+        let private_user_id = entities::UserId::from("private_user".to_string());
+        let now = Utc::now();
+        allow_sharing(&pool, &sharing_user_id, now).await;
+
+        let mine_a = repo
+            .create(
+                user_id.clone(),
+                now,
+                entities::Character::from('一'),
+                figure(),
+            )
+            .await
+            .unwrap();
+        let mine_b = repo
+            .create(
+                user_id.clone(),
+                now + Duration::seconds(1),
+                entities::Character::from('二'),
+                figure2(),
+            )
+            .await
+            .unwrap();
+        let mine_disabled = repo
+            .create(
+                user_id.clone(),
+                now + Duration::seconds(2),
+                entities::Character::from('一'),
+                figure(),
+            )
+            .await
+            .unwrap();
+        repo.update(mine_disabled.clone().disable(now))
+            .await
+            .unwrap();
+        let shared = repo
+            .create(
+                sharing_user_id.clone(),
+                now + Duration::seconds(3),
+                entities::Character::from('一'),
+                figure(),
+            )
+            .await
+            .unwrap();
+        let shared_disabled = repo
+            .create(
+                sharing_user_id.clone(),
+                now + Duration::seconds(4),
+                entities::Character::from('一'),
+                figure(),
+            )
+            .await
+            .unwrap();
+        repo.update(shared_disabled.disable(now)).await.unwrap();
+        repo.create(
+            private_user_id.clone(),
+            now + Duration::seconds(5),
+            entities::Character::from('一'),
+            figure(),
+        )
+        .await
+        .unwrap();
+
+        let first = entities::Limit::new(entities::LimitKind::First, 100).unwrap();
+        let cases = vec![
+            // 他人のものは共有されていてdisabledでないもののみ
+            (
+                ports::FigureRecordsQueryFilter::default(),
+                vec![mine_a.id, mine_b.id, mine_disabled.id, shared.id],
+            ),
+            (
+                ports::FigureRecordsQueryFilter {
+                    characters: Some(vec![entities::Character::from('二')]),
+                    ..Default::default()
+                },
+                vec![mine_b.id],
+            ),
+            (
+                ports::FigureRecordsQueryFilter {
+                    min_stroke_count: Some(entities::StrokeCount::try_from(2).unwrap()),
+                    ..Default::default()
+                },
+                vec![mine_b.id],
+            ),
+            (
+                ports::FigureRecordsQueryFilter {
+                    max_stroke_count: Some(entities::StrokeCount::try_from(1).unwrap()),
+                    ..Default::default()
+                },
+                vec![mine_a.id, mine_disabled.id, shared.id],
+            ),
+            (
+                ports::FigureRecordsQueryFilter {
+                    created_after: Some(now + Duration::seconds(1)),
+                    created_before: Some(now + Duration::seconds(3)),
+                    ..Default::default()
+                },
+                vec![mine_b.id, mine_disabled.id],
+            ),
+            (
+                ports::FigureRecordsQueryFilter {
+                    user_type: Some(ports::UserType::Myself),
+                    ..Default::default()
+                },
+                vec![mine_a.id, mine_b.id, mine_disabled.id],
+            ),
+            (
+                ports::FigureRecordsQueryFilter {
+                    user_type: Some(ports::UserType::Other),
+                    ..Default::default()
+                },
+                vec![shared.id],
+            ),
+            (
+                ports::FigureRecordsQueryFilter {
+                    disabled: Some(true),
+                    ..Default::default()
+                },
+                vec![mine_disabled.id],
+            ),
+            (
+                ports::FigureRecordsQueryFilter {
+                    disabled: Some(false),
+                    ..Default::default()
+                },
+                vec![mine_a.id, mine_b.id, shared.id],
+            ),
+        ];
+        for (filter, expected) in cases {
+            let figure_records = repo
+                .query(user_id.clone(), &filter, None, None, first)
+                .await
+                .unwrap();
+            assert_eq!(ids(&figure_records), expected, "{:?}", filter);
+        }
+
+        let filter = ports::FigureRecordsQueryFilter::default();
+        let page = repo
+            .query(
+                user_id.clone(),
+                &filter,
+                Some(mine_a.id),
+                None,
+                entities::Limit::new(entities::LimitKind::First, 2).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec![mine_b.id, mine_disabled.id]);
+
+        // Lastは末尾から降順に返す
+        let page = repo
+            .query(
+                user_id.clone(),
+                &filter,
+                None,
+                Some(shared.id),
+                entities::Limit::new(entities::LimitKind::Last, 2).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ids(&page), vec![mine_disabled.id, mine_b.id]);
+    }
+
+    #[sqlx::test]
+    async fn test_query_trashed(pool: sqlx::PgPool) {
+        let mut repo = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let other_user_id = entities::UserId::from("other_user".to_string());
+        let now = Utc::now();
+
+        let mut records = Vec::new();
+        for i in 0..4 {
+            records.push(
+                repo.create(
+                    user_id.clone(),
+                    now + Duration::seconds(i),
+                    entities::Character::from('一'),
+                    figure(),
+                )
+                .await
+                .unwrap(),
+            );
+        }
+        let other_record = repo
+            .create(
+                other_user_id.clone(),
+                now,
+                entities::Character::from('一'),
+                figure(),
+            )
+            .await
+            .unwrap();
+        repo.update(other_record.disable(now)).await.unwrap();
+
+        // 作成順とは逆に削除し、records[1]とrecords[2]は同時刻に削除する
+        let disabled_at = [
+            now + Duration::minutes(3),
+            now + Duration::minutes(1),
+            now + Duration::minutes(1),
+        ];
+        for (record, disabled_at) in records.iter().zip(disabled_at) {
+            repo.update(record.clone().disable(disabled_at))
+                .await
+                .unwrap();
+        }
+
+        // disabled_atの新しい順、同時刻ならidの降順。records[3]はdisabledでないので含まない
+        let expected_ids = [records[0].id, records[2].id, records[1].id];
+
+        let first = repo
+            .query_trashed(
+                user_id.clone(),
+                None,
+                None,
+                entities::Limit::new(entities::LimitKind::First, 2).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            first
+                .iter()
+                .map(|(record, _)| record.id)
+                .collect::<Vec<_>>(),
+            expected_ids[..2]
+        );
+        assert_eq!(first[0].1.disabled_at, first[0].0.disabled_at.unwrap());
+
+        let next = repo
+            .query_trashed(
+                user_id.clone(),
+                Some(first[1].1.clone()),
+                None,
+                entities::Limit::new(entities::LimitKind::First, 2).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            next.iter().map(|(record, _)| record.id).collect::<Vec<_>>(),
+            expected_ids[2..]
+        );
+
+        // Lastは末尾から古い順に返す
+        let last = repo
+            .query_trashed(
+                user_id.clone(),
+                None,
+                Some(next[0].1.clone()),
+                entities::Limit::new(entities::LimitKind::Last, 1).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            last.iter().map(|(record, _)| record.id).collect::<Vec<_>>(),
+            vec![expected_ids[1]]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_get_stats_by_characters(pool: sqlx::PgPool) {
+        let mut repo = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let sharing_user_id = entities::UserId::from("sharing_user".to_string());
+        let private_user_id = entities::UserId::from("private_user".to_string());
+        // DBに保存するとマイクロ秒に丸められるので、比較するlast_recorded_atは秒単位にしておく
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        allow_sharing(&pool, &sharing_user_id, now).await;
+
+        for (user_id, character, figure, created_at) in [
+            (&user_id, '一', figure(), now),
+            (&user_id, '一', figure(), now + Duration::seconds(1)),
+            (&user_id, '一', figure2(), now + Duration::seconds(2)),
+            (&sharing_user_id, '一', figure(), now + Duration::seconds(3)),
+            (&private_user_id, '一', figure(), now + Duration::seconds(4)),
+            (&sharing_user_id, '二', figure(), now + Duration::seconds(5)),
+            (&user_id, '三', figure(), now + Duration::seconds(6)),
+        ] {
+            repo.create(
+                user_id.clone(),
+                created_at,
+                entities::Character::from(character),
+                figure,
+            )
+            .await
+            .unwrap();
+        }
+        // disabledなものは数えない
+        let disabled = repo
+            .create(
+                user_id.clone(),
+                now + Duration::seconds(10),
+                entities::Character::from('一'),
+                figure(),
+            )
+            .await
+            .unwrap();
+        repo.update(disabled.disable(now)).await.unwrap();
+
+        let mut stats = repo
+            .get_stats_by_characters(
+                user_id.clone(),
+                &[
+                    entities::Character::from('一'),
+                    entities::Character::from('二'),
+                    entities::Character::from('四'),
+                ],
+            )
+            .await
+            .unwrap();
+        stats.sort_by_key(|stats| (char::from(stats.character.clone()), stats.stroke_count));
+
+        // 記録のない'四'と指定していない'三'は含まない
+        let expected = vec![
+            ports::FigureRecordStats {
+                character: entities::Character::from('一'),
+                stroke_count: entities::StrokeCount::try_from(1).unwrap(),
+                my_count: 2,
+                other_count: 1,
+                last_recorded_at: Some(now + Duration::seconds(1)),
+            },
+            ports::FigureRecordStats {
+                character: entities::Character::from('一'),
+                stroke_count: entities::StrokeCount::try_from(2).unwrap(),
+                my_count: 1,
+                other_count: 0,
+                last_recorded_at: Some(now + Duration::seconds(2)),
+            },
+            ports::FigureRecordStats {
+                character: entities::Character::from('二'),
+                stroke_count: entities::StrokeCount::try_from(1).unwrap(),
+                my_count: 0,
+                other_count: 1,
+                last_recorded_at: None,
+            },
+        ];
+        assert_eq!(stats, expected);
+    }
+
+    #[sqlx::test]
+    async fn test_get_by_character_config_ids(pool: sqlx::PgPool) {
+        let mut repo = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let sharing_user_id = entities::UserId::from("sharing_user".to_string());
+        let now = Utc::now();
+        allow_sharing(&pool, &sharing_user_id, now).await;
+
+        let mut mine = Vec::new();
+        for i in 0..5 {
+            mine.push(
+                repo.create(
+                    user_id.clone(),
+                    now + Duration::seconds(i),
+                    entities::Character::from('一'),
+                    figure(),
+                )
+                .await
+                .unwrap(),
+            );
+        }
+        let shared = repo
+            .create(
+                sharing_user_id.clone(),
+                now + Duration::seconds(5),
+                entities::Character::from('一'),
+                figure(),
+            )
+            .await
+            .unwrap();
+        let other_stroke_count = repo
+            .create(
+                user_id.clone(),
+                now + Duration::seconds(6),
+                entities::Character::from('一'),
+                figure2(),
+            )
+            .await
+            .unwrap();
+
+        let query = |limit: entities::Limit| ports::FigureRecordsByCharacterConfigIdQuery {
+            character: entities::Character::from('一'),
+            stroke_count: entities::StrokeCount::try_from(1).unwrap(),
+            ids: None,
+            after_id: None,
+            before_id: None,
+            limit,
+            user_type: None,
+        };
+        let first = |value| entities::Limit::new(entities::LimitKind::First, value).unwrap();
+        let last = |value| entities::Limit::new(entities::LimitKind::Last, value).unwrap();
+
+        // 問い合わせごとにカーソルなどが異なっても、それぞれの結果を同じ順で返す
+        let queries = vec![
+            query(first(2)),
+            ports::FigureRecordsByCharacterConfigIdQuery {
+                after_id: Some(mine[3].id),
+                ..query(first(2))
+            },
+            ports::FigureRecordsByCharacterConfigIdQuery {
+                before_id: Some(mine[0].id),
+                ..query(last(2))
+            },
+            ports::FigureRecordsByCharacterConfigIdQuery {
+                ids: Some(vec![mine[1].id, shared.id, other_stroke_count.id]),
+                ..query(first(10))
+            },
+            ports::FigureRecordsByCharacterConfigIdQuery {
+                user_type: Some(ports::UserType::Myself),
+                ..query(first(10))
+            },
+            ports::FigureRecordsByCharacterConfigIdQuery {
+                user_type: Some(ports::UserType::Other),
+                ..query(first(10))
+            },
+            ports::FigureRecordsByCharacterConfigIdQuery {
+                stroke_count: entities::StrokeCount::try_from(2).unwrap(),
+                ..query(first(10))
+            },
+            ports::FigureRecordsByCharacterConfigIdQuery {
+                character: entities::Character::from('二'),
+                ..query(first(10))
+            },
+        ];
+        let results = repo
+            .get_by_character_config_ids(user_id.clone(), &queries)
+            .await
+            .unwrap();
+
+        // Lastでもidの降順
+        let expected = vec![
+            vec![shared.id, mine[4].id],
+            vec![mine[2].id, mine[1].id],
+            vec![mine[2].id, mine[1].id],
+            vec![shared.id, mine[1].id],
+            vec![mine[4].id, mine[3].id, mine[2].id, mine[1].id, mine[0].id],
+            vec![shared.id],
+            vec![other_stroke_count.id],
+            vec![],
+        ];
+        assert_eq!(
+            results
+                .iter()
+                .map(|figure_records| ids(figure_records))
+                .collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_create_many(pool: sqlx::PgPool) {
+        let mut repo = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let characters = ['一', '二', '三', '四', '五'].map(entities::Character::from);
+
+        // 同じnowでも分けて作成しても、IDは入力順になる
+        let mut generator = ulid::Generator::new();
+        let mut records = repo
+            .create_many(
+                user_id.clone(),
+                now,
+                &mut generator,
+                characters[..3]
+                    .iter()
+                    .map(|character| (character.clone(), figure()))
+                    .collect(),
+            )
+            .await
+            .unwrap();
+        records.extend(
+            repo.create_many(
+                user_id.clone(),
+                now,
+                &mut generator,
+                characters[3..]
+                    .iter()
+                    .map(|character| (character.clone(), figure()))
+                    .collect(),
+            )
+            .await
+            .unwrap(),
+        );
+
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| record.character.clone())
+                .collect::<Vec<_>>(),
+            characters.to_vec()
+        );
+        assert!(records.windows(2).all(|w| w[0].id < w[1].id));
+        assert!(records.iter().all(|record| record.created_at == now));
+
+        let mut saved = repo.get_by_ids(user_id, &ids(&records)).await.unwrap();
+        saved.sort_by_key(|record| record.id);
+        assert_eq!(ids(&saved), ids(&records));
+    }
+
+    #[sqlx::test]
+    async fn test_update_conflict(pool: sqlx::PgPool) {
+        let mut repo = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+
+        let record = repo
+            .create(
+                user_id.clone(),
+                now,
+                entities::Character::from('一'),
+                figure(),
+            )
+            .await
+            .unwrap();
+
+        let mut disabled = record.clone();
+        disabled.disabled = true;
+        disabled.disabled_at = Some(now);
+        let disabled = repo.update(disabled).await.unwrap();
+        assert_eq!(disabled.version, record.version.next());
+
+        // 古いversionでの更新
+        let err = repo.update(record.clone()).await.unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+
+        // 削除済みのものはget_by_idsでは返さない
+        assert!(repo
+            .get_by_ids(user_id.clone(), &[record.id])
+            .await
+            .unwrap()
+            .is_empty());
+
+        let saved = repo
+            .get_by_ids_include_disabled(user_id, &[record.id])
+            .await
+            .unwrap();
+        assert_eq!(saved.len(), 1);
+        assert!(saved[0].disabled);
+        assert_eq!(saved[0].version, disabled.version);
+    }
+
+    #[sqlx::test]
+    async fn test_delete_disabled_before(pool: sqlx::PgPool) {
+        let mut repo = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let before = now - Duration::days(1);
+
+        let mut records = Vec::new();
+        for _ in 0..3 {
+            let record = repo
+                .create(
+                    user_id.clone(),
+                    now,
+                    entities::Character::from('一'),
+                    figure(),
+                )
+                .await
+                .unwrap();
+            records.push(record);
+        }
+        let expired = repo
+            .update(records[0].clone().disable(before - Duration::days(1)))
+            .await
+            .unwrap();
+        let restored = repo
+            .update(records[1].clone().disable(before - Duration::days(1)))
+            .await
+            .unwrap();
+        repo.update(records[2].clone().disable(now)).await.unwrap();
+
+        let mut targets = repo.get_disabled_before(before).await.unwrap();
+        targets.sort_by_key(|(_, id)| *id);
+        let mut expected = vec![expired.id, restored.id];
+        expected.sort();
+        assert_eq!(
+            targets,
+            expected
+                .into_iter()
+                .map(|id| (user_id.clone(), id))
+                .collect::<Vec<_>>()
+        );
+
+        // 対象を取得した後に復元されたものは削除しない
+        repo.update(restored.restore(before).unwrap())
+            .await
+            .unwrap();
+        repo.delete_disabled_before(
+            before,
+            &targets.into_iter().map(|(_, id)| id).collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+
+        let mut remaining = repo
+            .get_all_by_user_id(user_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|record| record.id)
+            .collect::<Vec<_>>();
+        remaining.sort();
+        let mut expected = vec![records[1].id, records[2].id];
+        expected.sort();
+        assert_eq!(remaining, expected);
+    }
 }
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 
 use crate::{entities, ports};
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use chrono::{DateTime, Utc};
 use sqlx::{Acquire, Postgres};
 use ulid::Ulid;
@@ -27,8 +27,9 @@ impl FileModel {
             id: entities::FileId::from(id),
             user_id: entities::UserId::from(self.user_id),
             key: entities::FileKey::from_unchecked(self.key),
-            mime_type: entities::MimeType::try_from(self.mime_type)?,
-            size: entities::FileSize::try_from(self.size)?,
+            mime_type: entities::MimeType::from_stored(self.mime_type)?,
+            // エクスポートのようにアップロードの上限を超えるものもある
+            size: entities::FileSize::from_generated(u64::try_from(self.size)?)?,
             verified: self.verified,
             created_at: self.created_at,
             updated_at: self.updated_at,
@@ -139,7 +140,7 @@ where
         .context("update file")?;
 
         if result.rows_affected() == 0 {
-            return Err(anyhow!("conflict"));
+            return Err(ports::ConflictError.into());
         }
 
         trx.commit().await?;
@@ -194,4 +195,45 @@ where
 
         Ok(files)
     }
+
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::File>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let models = sqlx::query_as!(
+            FileModel,
+            r#"
+                SELECT
+                    id,
+                    user_id,
+                    key,
+                    mime_type,
+                    size,
+                    verified,
+                    created_at,
+                    updated_at,
+                    version
+                FROM
+                    files
+                WHERE
+                    user_id = $1
+                ORDER BY
+                    id
+            "#,
+            String::from(user_id.clone()),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch files")?;
+
+        let files = models
+            .into_iter()
+            .map(|model| model.into_entity())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert File")?;
+
+        Ok(files)
+    }
 }
@@ -1,6 +1,6 @@
 use std::str::FromStr;
 
-use anyhow::{anyhow, Context};
+use anyhow::Context;
 use sqlx::{Acquire, Postgres};
 use ulid::Ulid;
 
@@ -236,7 +236,7 @@ where
         .context("update generate_template")?;
 
         if result.rows_affected() == 0 {
-            return Err(anyhow!("conflict"));
+            return Err(ports::ConflictError.into());
         }
 
         trx.commit().await?;
@@ -246,6 +246,7 @@ where
     async fn query(
         &mut self,
         user_id: entities::UserId,
+        disabled: bool,
         after_id: Option<entities::GenerateTemplateId>,
         before_id: Option<entities::GenerateTemplateId>,
         limit: entities::Limit,
@@ -279,15 +280,16 @@ where
                     user_id = $1
                     AND ($2::VARCHAR(64) IS NULL OR id > $2)
                     AND ($3::VARCHAR(64) IS NULL OR id < $3)
-                    AND disabled = false
+                    AND disabled = $4
                 ORDER BY
-                    CASE WHEN $4 = 0 THEN id END ASC,
-                    CASE WHEN $4 = 1 THEN id END DESC
-                LIMIT $5
+                    CASE WHEN $5 = 0 THEN id END ASC,
+                    CASE WHEN $5 = 1 THEN id END DESC
+                LIMIT $6
             "#,
             String::from(user_id.clone()),
             after_id.as_deref(),
             before_id.as_deref(),
+            disabled,
             i32::from(limit.kind() == entities::LimitKind::Last),
             i64::from(limit.value()),
         )
@@ -303,4 +305,107 @@ where
 
         Ok(generate_templates)
     }
+
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::GenerateTemplate>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let models = sqlx::query_as!(
+            GenerateTemplateModel,
+            r#"
+                SELECT
+                    id,
+                    user_id,
+                    background_image_file_id,
+                    font_color,
+                    writing_mode,
+                    margin_block_start,
+                    margin_inline_start,
+                    line_spacing,
+                    letter_spacing,
+                    font_size,
+                    font_weight,
+                    created_at,
+                    updated_at,
+                    disabled,
+                    version
+                FROM
+                    generate_templates
+                WHERE
+                    user_id = $1
+                ORDER BY
+                    id
+            "#,
+            String::from(user_id.clone()),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch generate_templates")?;
+
+        let generate_templates = models
+            .into_iter()
+            .map(|model| model.into_entity())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert GenerateTemplate")?;
+
+        Ok(generate_templates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::ports::GenerateTemplatesRepository;
+
+    #[sqlx::test]
+    async fn test_update_conflict(pool: sqlx::PgPool) {
+        let mut repo = GenerateTemplatesRepositoryImpl::new(pool);
+        let user_id = entities::UserId::from("user".to_string());
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+
+        let generate_template = repo
+            .create(entities::GenerateTemplate {
+                id: entities::GenerateTemplateId::from(Ulid::from_datetime(now)),
+                user_id: user_id.clone(),
+                background_image_file_id: entities::FileId::from(Ulid::from_datetime(now)),
+                font_color: entities::Color::try_from(0).unwrap(),
+                writing_mode: entities::WritingMode::Horizontal,
+                margin_block_start: entities::Margin::try_from(0).unwrap(),
+                margin_inline_start: entities::Margin::try_from(0).unwrap(),
+                line_spacing: entities::Spacing::try_from(0).unwrap(),
+                letter_spacing: entities::Spacing::try_from(0).unwrap(),
+                font_size: entities::FontSize::try_from(20).unwrap(),
+                font_weight: entities::FontWeight::try_from(50).unwrap(),
+                created_at: now,
+                updated_at: now,
+                disabled: false,
+                version: entities::Version::new(),
+            })
+            .await
+            .unwrap();
+
+        let mut updated = generate_template.clone();
+        updated.font_size = entities::FontSize::try_from(30).unwrap();
+        let updated = repo.update(now, updated).await.unwrap();
+        assert_eq!(updated.version, generate_template.version.next());
+
+        // 古いversionでの更新
+        let err = repo
+            .update(now, generate_template.clone())
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+
+        let saved = repo
+            .get_by_ids(user_id, &[generate_template.id])
+            .await
+            .unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].font_size, updated.font_size);
+        assert_eq!(saved[0].version, updated.version);
+    }
 }
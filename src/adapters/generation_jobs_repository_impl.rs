@@ -0,0 +1,233 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use sqlx::{Acquire, Postgres};
+use ulid::Ulid;
+
+use crate::{entities, ports};
+use chrono::{DateTime, Utc};
+
+const KIND_DOCUMENT: i32 = 0;
+const KIND_DATA_EXPORT: i32 = 1;
+
+#[derive(Debug, Clone)]
+struct GenerationJobModel {
+    id: String,
+    user_id: String,
+    kind: i32,
+    generate_template_id: Option<String>,
+    text: Option<String>,
+    status: i32,
+    error_message: Option<String>,
+    result_file_id: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    version: i32,
+}
+
+impl GenerationJobModel {
+    pub fn into_entity(self) -> anyhow::Result<entities::GenerationJob> {
+        let id = Ulid::from_str(&self.id).context("ulid decode error")?;
+        let kind = match (self.kind, self.generate_template_id, self.text) {
+            (KIND_DOCUMENT, Some(generate_template_id), Some(text)) => {
+                entities::GenerationJobKind::Document {
+                    generate_template_id: entities::GenerateTemplateId::from(
+                        Ulid::from_str(&generate_template_id).context("ulid decode error")?,
+                    ),
+                    text: entities::DocumentText::try_from(text)?,
+                }
+            }
+            (KIND_DATA_EXPORT, _, _) => entities::GenerationJobKind::DataExport,
+            (kind, _, _) => return Err(anyhow!("invalid generation_job kind: {}", kind)),
+        };
+        let result_file_id = self
+            .result_file_id
+            .map(|id| Ulid::from_str(&id).context("ulid decode error"))
+            .transpose()?
+            .map(entities::FileId::from);
+
+        Ok(entities::GenerationJob {
+            id: entities::GenerationJobId::from(id),
+            user_id: entities::UserId::from(self.user_id),
+            kind,
+            status: entities::GenerationJobStatus::try_from(self.status)?,
+            error_message: self.error_message,
+            result_file_id,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            version: entities::Version::try_from(self.version)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GenerationJobsRepositoryImpl<A> {
+    db: A,
+}
+
+impl<A> GenerationJobsRepositoryImpl<A> {
+    pub fn new(db: A) -> Self {
+        Self { db }
+    }
+}
+
+impl<A> ports::GenerationJobsRepository for GenerationJobsRepositoryImpl<A>
+where
+    A: Send,
+    for<'c> &'c A: Acquire<'c, Database = Postgres>,
+{
+    type Error = anyhow::Error;
+
+    async fn create(
+        &mut self,
+        mut generation_job: entities::GenerationJob,
+    ) -> Result<entities::GenerationJob, Self::Error> {
+        let mut trx = self.db.begin().await?;
+        generation_job.version = generation_job.version.next();
+
+        let (kind, generate_template_id, text) = match &generation_job.kind {
+            entities::GenerationJobKind::Document {
+                generate_template_id,
+                text,
+            } => (
+                KIND_DOCUMENT,
+                Some(Ulid::from(*generate_template_id).to_string()),
+                Some(text.value()),
+            ),
+            entities::GenerationJobKind::DataExport => (KIND_DATA_EXPORT, None, None),
+        };
+
+        sqlx::query!(
+            r#"
+                INSERT INTO generation_jobs (
+                    id,
+                    user_id,
+                    kind,
+                    generate_template_id,
+                    text,
+                    status,
+                    error_message,
+                    result_file_id,
+                    created_at,
+                    updated_at,
+                    version
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            "#,
+            Ulid::from(generation_job.id).to_string(),
+            String::from(generation_job.user_id.clone()),
+            kind,
+            generate_template_id,
+            text,
+            i32::from(generation_job.status),
+            generation_job.error_message.as_deref(),
+            generation_job
+                .result_file_id
+                .map(|id| Ulid::from(id).to_string()),
+            generation_job.created_at,
+            generation_job.updated_at,
+            i32::from(generation_job.version),
+        )
+        .execute(&mut *trx)
+        .await
+        .context("insert generation_job")?;
+
+        trx.commit().await?;
+        Ok(generation_job)
+    }
+
+    async fn get_by_ids(
+        &mut self,
+        user_id: entities::UserId,
+        ids: &[entities::GenerationJobId],
+    ) -> Result<Vec<entities::GenerationJob>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+        let ids = ids
+            .iter()
+            .map(|&id| Ulid::from(id).to_string())
+            .collect::<Vec<_>>();
+
+        let models = sqlx::query_as!(
+            GenerationJobModel,
+            r#"
+                SELECT
+                    id,
+                    user_id,
+                    kind,
+                    generate_template_id,
+                    text,
+                    status,
+                    error_message,
+                    result_file_id,
+                    created_at,
+                    updated_at,
+                    version
+                FROM
+                    generation_jobs
+                WHERE
+                    id = Any($1)
+                    AND user_id = $2
+            "#,
+            ids.as_slice(),
+            String::from(user_id.clone()),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch generation_jobs")?;
+
+        let generation_jobs = models
+            .into_iter()
+            .map(|model| model.into_entity())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert GenerationJob")?;
+
+        Ok(generation_jobs)
+    }
+
+    async fn update(
+        &mut self,
+        now: DateTime<Utc>,
+        mut generation_job: entities::GenerationJob,
+    ) -> Result<entities::GenerationJob, Self::Error> {
+        let mut trx = self.db.begin().await?;
+        let prev_version = generation_job.version;
+        generation_job.version = generation_job.version.next();
+        generation_job.updated_at = now;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE generation_jobs
+                SET
+                    status = $1,
+                    error_message = $2,
+                    result_file_id = $3,
+                    updated_at = $4,
+                    version = $5
+                WHERE
+                    user_id = $6
+                    AND id = $7
+                    AND version = $8
+            "#,
+            i32::from(generation_job.status),
+            generation_job.error_message.as_deref(),
+            generation_job
+                .result_file_id
+                .map(|id| Ulid::from(id).to_string()),
+            generation_job.updated_at,
+            i32::from(generation_job.version),
+            String::from(generation_job.user_id.clone()),
+            Ulid::from(generation_job.id).to_string(),
+            i32::from(prev_version),
+        )
+        .execute(&mut *trx)
+        .await
+        .context("update generation_job")?;
+
+        if result.rows_affected() == 0 {
+            return Err(ports::ConflictError.into());
+        }
+
+        trx.commit().await?;
+        Ok(generation_job)
+    }
+}
@@ -1,15 +1,23 @@
+mod accounts_repository_impl;
 mod character_config_seeds_repository_impl;
 mod character_configs_repository_impl;
 mod figure_records_repository_impl;
 mod files_repository_impl;
 mod generate_templates_repository_impl;
+mod generation_jobs_repository_impl;
+mod persisted_queries_repository_impl;
+mod reference_figures_repository_impl;
 mod storage_impl;
 mod user_configs_repository_impl;
 
+pub use accounts_repository_impl::AccountsRepositoryImpl;
 pub use character_config_seeds_repository_impl::CharacterConfigSeedsRepositoryImpl;
 pub use character_configs_repository_impl::CharacterConfigsRepositoryImpl;
 pub use figure_records_repository_impl::FigureRecordsRepositoryImpl;
 pub use files_repository_impl::FilesRepositoryImpl;
 pub use generate_templates_repository_impl::GenerateTemplatesRepositoryImpl;
+pub use generation_jobs_repository_impl::GenerationJobsRepositoryImpl;
+pub use persisted_queries_repository_impl::PersistedQueriesRepositoryImpl;
+pub use reference_figures_repository_impl::ReferenceFiguresRepositoryImpl;
 pub use storage_impl::StorageImpl;
 pub use user_configs_repository_impl::UserConfigsRepositoryImpl;
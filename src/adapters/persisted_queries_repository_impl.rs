@@ -0,0 +1,92 @@
+use anyhow::Context;
+use sqlx::{Acquire, Postgres};
+
+use crate::{entities, ports};
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone)]
+pub struct PersistedQueriesRepositoryImpl<A> {
+    db: A,
+}
+
+impl<A> PersistedQueriesRepositoryImpl<A> {
+    pub fn new(db: A) -> Self {
+        Self { db }
+    }
+}
+
+impl<A> ports::PersistedQueriesRepository for PersistedQueriesRepositoryImpl<A>
+where
+    A: Send,
+    for<'c> &'c A: Acquire<'c, Database = Postgres>,
+{
+    type Error = anyhow::Error;
+
+    async fn get_by_hash(
+        &mut self,
+        hash: &entities::PersistedQueryHash,
+    ) -> Result<Option<entities::PersistedQuery>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let record = sqlx::query!(
+            r#"
+                SELECT
+                    hash,
+                    query
+                FROM
+                    persisted_queries
+                WHERE
+                    hash = $1
+            "#,
+            hash.value(),
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .context("fetch persisted_query")?;
+
+        let persisted_query = record
+            .map(|record| -> anyhow::Result<_> {
+                Ok(entities::PersistedQuery {
+                    hash: entities::PersistedQueryHash::try_from(record.hash)?,
+                    query: record.query,
+                })
+            })
+            .transpose()?;
+
+        Ok(persisted_query)
+    }
+
+    async fn create_many(
+        &mut self,
+        now: DateTime<Utc>,
+        persisted_queries: &[entities::PersistedQuery],
+    ) -> Result<u64, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let hashes = persisted_queries
+            .iter()
+            .map(|persisted_query| persisted_query.hash.value().to_string())
+            .collect::<Vec<_>>();
+        let queries = persisted_queries
+            .iter()
+            .map(|persisted_query| persisted_query.query.clone())
+            .collect::<Vec<_>>();
+
+        let result = sqlx::query!(
+            r#"
+                INSERT INTO persisted_queries (hash, query, created_at)
+                SELECT hash, query, $3
+                FROM unnest($1::VARCHAR(64)[], $2::TEXT[]) AS t(hash, query)
+                ON CONFLICT (hash) DO NOTHING
+            "#,
+            hashes.as_slice(),
+            queries.as_slice(),
+            now,
+        )
+        .execute(&mut *conn)
+        .await
+        .context("insert persisted_queries")?;
+
+        Ok(result.rows_affected())
+    }
+}
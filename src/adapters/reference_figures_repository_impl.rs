@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+
+use crate::{entities, ports};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::{Acquire, Postgres};
+
+#[derive(Debug, Clone)]
+struct ReferenceFigureModel {
+    user_id: String,
+    character: String,
+    stroke_count: i32,
+    variant: String,
+    figure: serde_json::Value,
+    updated_at: DateTime<Utc>,
+    version: i32,
+}
+
+impl ReferenceFigureModel {
+    fn into_entity(self) -> anyhow::Result<entities::ReferenceFigure> {
+        Ok(entities::ReferenceFigure {
+            user_id: entities::UserId::from(self.user_id),
+            character: entities::Character::try_from(self.character.as_str())?,
+            stroke_count: entities::StrokeCount::try_from(self.stroke_count)?,
+            variant: entities::ReferenceFigureVariant::try_from(self.variant)?,
+            figure: entities::Figure::from_json_ast(self.figure)
+                .ok_or_else(|| anyhow!("figure must be valid json"))?,
+            updated_at: Some(self.updated_at),
+            version: entities::Version::try_from(self.version)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ReferenceFiguresRepositoryImpl<A> {
+    db: A,
+}
+
+impl<A> ReferenceFiguresRepositoryImpl<A> {
+    pub fn new(db: A) -> Self {
+        Self { db }
+    }
+}
+
+impl<A> ports::ReferenceFiguresRepository for ReferenceFiguresRepositoryImpl<A>
+where
+    A: Send,
+    for<'c> &'c A: Acquire<'c, Database = Postgres>,
+{
+    type Error = anyhow::Error;
+
+    async fn save(
+        &mut self,
+        now: DateTime<Utc>,
+        mut reference_figure: entities::ReferenceFigure,
+    ) -> Result<entities::ReferenceFigure, Self::Error> {
+        let mut trx = self.db.begin().await?;
+        let prev_version = reference_figure.version;
+        reference_figure.version = reference_figure.version.next();
+        reference_figure.updated_at = Some(now);
+
+        let result = if prev_version.is_none() {
+            sqlx::query!(
+                r#"
+                    INSERT INTO character_config_reference_figures (user_id, character, stroke_count, variant, figure, updated_at, version)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7)
+                    ON CONFLICT DO NOTHING
+                "#,
+                String::from(reference_figure.user_id.clone()),
+                String::from(reference_figure.character.clone()),
+                i32::from(reference_figure.stroke_count),
+                reference_figure.variant.value(),
+                reference_figure.figure.to_json_ast(),
+                now,
+                i32::from(reference_figure.version),
+            )
+            .execute(&mut *trx)
+            .await
+            .context("insert character_config_reference_figures")?
+        } else {
+            sqlx::query!(
+                r#"
+                    UPDATE character_config_reference_figures
+                        SET
+                            figure = $1,
+                            updated_at = $2,
+                            version = $3
+                        WHERE
+                            user_id = $4
+                            AND
+                            character = $5
+                            AND
+                            stroke_count = $6
+                            AND
+                            variant = $7
+                            AND
+                            version = $8
+                "#,
+                reference_figure.figure.to_json_ast(),
+                now,
+                i32::from(reference_figure.version),
+                String::from(reference_figure.user_id.clone()),
+                String::from(reference_figure.character.clone()),
+                i32::from(reference_figure.stroke_count),
+                reference_figure.variant.value(),
+                i32::from(prev_version),
+            )
+            .execute(&mut *trx)
+            .await
+            .context("update character_config_reference_figures")?
+        };
+
+        if result.rows_affected() == 0 {
+            return Err(ports::ConflictError.into());
+        }
+
+        trx.commit().await?;
+        Ok(reference_figure)
+    }
+
+    async fn delete(
+        &mut self,
+        reference_figure: entities::ReferenceFigure,
+    ) -> Result<(), Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let result = sqlx::query!(
+            r#"
+                DELETE FROM character_config_reference_figures
+                WHERE
+                    user_id = $1
+                    AND
+                    character = $2
+                    AND
+                    stroke_count = $3
+                    AND
+                    variant = $4
+                    AND
+                    version = $5
+            "#,
+            String::from(reference_figure.user_id),
+            String::from(reference_figure.character),
+            i32::from(reference_figure.stroke_count),
+            reference_figure.variant.value(),
+            i32::from(reference_figure.version),
+        )
+        .execute(&mut *conn)
+        .await
+        .context("delete character_config_reference_figures")?;
+
+        if result.rows_affected() == 0 {
+            return Err(ports::ConflictError.into());
+        }
+
+        Ok(())
+    }
+
+    async fn get_by_character_config_ids(
+        &mut self,
+        user_id: entities::UserId,
+        keys: &[(entities::Character, entities::StrokeCount)],
+    ) -> Result<
+        HashMap<(entities::Character, entities::StrokeCount), Vec<entities::ReferenceFigure>>,
+        Self::Error,
+    > {
+        let mut conn = self.db.acquire().await?;
+
+        let character_values = keys
+            .iter()
+            .map(|(character, _)| String::from(character.clone()))
+            .collect::<Vec<_>>();
+
+        let stroke_count_values = keys
+            .iter()
+            .map(|(_, stroke_count)| i32::from(*stroke_count))
+            .collect::<Vec<_>>();
+
+        let models = sqlx::query_as!(
+            ReferenceFigureModel,
+            r#"
+            WITH input_pairs AS (
+                SELECT a, b
+                FROM unnest($1::VARCHAR(8)[], $2::INTEGER[]) AS t(a, b)
+            )
+            SELECT
+                user_id,
+                character,
+                stroke_count,
+                variant,
+                figure,
+                updated_at,
+                version
+            FROM
+                character_config_reference_figures
+            JOIN
+                input_pairs ON character_config_reference_figures.character = input_pairs.a
+                AND character_config_reference_figures.stroke_count = input_pairs.b
+            WHERE
+                user_id = $3
+            ORDER BY
+                variant
+        "#,
+            character_values.as_slice(),
+            stroke_count_values.as_slice(),
+            String::from(user_id),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch character_config_reference_figures")?;
+
+        let reference_figures = models
+            .into_iter()
+            .map(|model| model.into_entity())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert ReferenceFigure")?;
+
+        Ok(reference_figures
+            .into_iter()
+            .fold(HashMap::new(), |mut acc, reference_figure| {
+                acc.entry((
+                    reference_figure.character.clone(),
+                    reference_figure.stroke_count,
+                ))
+                .or_insert_with(Vec::new)
+                .push(reference_figure);
+                acc
+            }))
+    }
+
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::ReferenceFigure>, Self::Error> {
+        let mut conn = self.db.acquire().await?;
+
+        let models = sqlx::query_as!(
+            ReferenceFigureModel,
+            r#"
+            SELECT
+                user_id,
+                character,
+                stroke_count,
+                variant,
+                figure,
+                updated_at,
+                version
+            FROM
+                character_config_reference_figures
+            WHERE
+                user_id = $1
+            ORDER BY
+                character, stroke_count, variant
+        "#,
+            String::from(user_id),
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .context("fetch character_config_reference_figures")?;
+
+        let reference_figures = models
+            .into_iter()
+            .map(|model| model.into_entity())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .context("convert ReferenceFigure")?;
+
+        Ok(reference_figures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::ports::ReferenceFiguresRepository;
+
+    fn figure(stroke_count: i32) -> entities::Figure {
+        let strokes = (0..stroke_count)
+            .map(|_| r#"{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]}"#)
+            .collect::<Vec<_>>()
+            .join(",");
+        entities::Figure::from_json(&format!(
+            r#"{{"strokes":[{}],"width":1,"height":1}}"#,
+            strokes
+        ))
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_reference_figures_repository(pool: sqlx::PgPool) {
+        let mut repo = ReferenceFiguresRepositoryImpl::new(pool);
+        let user_id = entities::UserId::from("test_user".to_string());
+        let now = Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap();
+        let character = entities::Character::try_from("あ").unwrap();
+        let stroke_count = entities::StrokeCount::try_from(3).unwrap();
+
+        // 画数が同じでも字形(variant)が違えば別の手本として保存できる
+        let mut saved = Vec::new();
+        for variant in ["b", "a"] {
+            let reference_figure = entities::ReferenceFigure::new(
+                user_id.clone(),
+                character.clone(),
+                stroke_count,
+                entities::ReferenceFigureVariant::try_from(variant.to_string()).unwrap(),
+                figure(3),
+            );
+            saved.push(repo.save(now, reference_figure).await.unwrap());
+        }
+        assert_eq!(saved[0].updated_at, Some(now));
+        assert_eq!(saved[0].version, entities::Version::none().next());
+
+        let other_stroke_count = entities::StrokeCount::try_from(2).unwrap();
+        let mut map = repo
+            .get_by_character_config_ids(
+                user_id.clone(),
+                &[
+                    (character.clone(), stroke_count),
+                    (character.clone(), other_stroke_count),
+                ],
+            )
+            .await
+            .unwrap();
+        assert!(!map.contains_key(&(character.clone(), other_stroke_count)));
+        let variants = map
+            .remove(&(character.clone(), stroke_count))
+            .unwrap()
+            .into_iter()
+            .map(|reference_figure| String::from(reference_figure.variant))
+            .collect::<Vec<_>>();
+        assert_eq!(variants, vec!["a", "b"]);
+
+        // 同じvariantを未作成のつもりで作成、古いversionでの更新・削除は競合
+        let err = repo
+            .save(
+                now,
+                entities::ReferenceFigure::new(
+                    user_id.clone(),
+                    character.clone(),
+                    stroke_count,
+                    saved[0].variant.clone(),
+                    figure(3),
+                ),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+
+        let updated = repo.save(now, saved[0].clone()).await.unwrap();
+        let err = repo.save(now, saved[0].clone()).await.unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+        let err = repo.delete(saved[0].clone()).await.unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+
+        repo.delete(updated).await.unwrap();
+        let all = repo.get_all_by_user_id(user_id).await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].variant, saved[1].variant);
+    }
+}
@@ -1,9 +1,15 @@
+use std::path::Path;
 use std::time::Duration;
 
 use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier};
 
 use crate::{app_config::AppConfig, entities, ports::Storage};
 
+// DeleteObjectsで一度に指定できるキーの上限
+const DELETE_OBJECTS_MAX_KEYS: usize = 1000;
+
 #[derive(Debug, Clone)]
 pub struct StorageImpl {
     pub config: AppConfig,
@@ -42,6 +48,95 @@ impl Storage for StorageImpl {
     async fn generate_download_url(
         &mut self,
         file: &entities::File,
+    ) -> Result<String, Self::Error> {
+        self.generate_download_url_by_key(&file.key).await
+    }
+
+    async fn verify(&mut self, file: &entities::File) -> Result<(), Self::Error> {
+        let req = self
+            .client
+            .head_object()
+            .bucket(&self.config.storage.bucket)
+            .key(String::from(file.key.clone()).as_str());
+        let _ = req.send().await?;
+
+        Ok(())
+    }
+
+    async fn exists(&mut self, key: &entities::FileKey) -> Result<bool, Self::Error> {
+        let result = self
+            .client
+            .head_object()
+            .bucket(&self.config.storage.bucket)
+            .key(String::from(key.clone()).as_str())
+            .send()
+            .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(e)
+                if e.as_service_error()
+                    .map(|e| e.is_not_found())
+                    .unwrap_or(false) =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn put(
+        &mut self,
+        key: &entities::FileKey,
+        mime_type: &entities::MimeType,
+        body: Vec<u8>,
+    ) -> Result<(), Self::Error> {
+        self.client
+            .put_object()
+            .bucket(&self.config.storage.bucket)
+            .key(String::from(key.clone()).as_str())
+            .content_type(mime_type.value())
+            .body(ByteStream::from(body))
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn put_file(
+        &mut self,
+        key: &entities::FileKey,
+        mime_type: &entities::MimeType,
+        path: &Path,
+    ) -> Result<(), Self::Error> {
+        self.client
+            .put_object()
+            .bucket(&self.config.storage.bucket)
+            .key(String::from(key.clone()).as_str())
+            .content_type(mime_type.value())
+            .body(ByteStream::from_path(path).await?)
+            .send()
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get(&mut self, key: &entities::FileKey) -> Result<Vec<u8>, Self::Error> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.config.storage.bucket)
+            .key(String::from(key.clone()).as_str())
+            .send()
+            .await?;
+        let body = output.body.collect().await?;
+
+        Ok(body.into_bytes().to_vec())
+    }
+
+    async fn generate_download_url_by_key(
+        &mut self,
+        key: &entities::FileKey,
     ) -> Result<String, Self::Error> {
         let expires_in =
             Duration::from_secs(self.config.storage.presigned_download_expires_in_secs);
@@ -50,7 +145,7 @@ impl Storage for StorageImpl {
             .client
             .get_object()
             .bucket(&self.config.storage.bucket)
-            .key(String::from(file.key.clone()).as_str());
+            .key(String::from(key.clone()).as_str());
 
         let presigned_req = req
             .presigned(PresigningConfig::expires_in(expires_in)?)
@@ -60,13 +155,65 @@ impl Storage for StorageImpl {
         Ok(url)
     }
 
-    async fn verify(&mut self, file: &entities::File) -> Result<(), Self::Error> {
-        let req = self
-            .client
-            .head_object()
-            .bucket(&self.config.storage.bucket)
-            .key(String::from(file.key.clone()).as_str());
-        let _ = req.send().await?;
+    async fn delete(&mut self, keys: &[entities::FileKey]) -> Result<(), Self::Error> {
+        for chunk in keys.chunks(DELETE_OBJECTS_MAX_KEYS) {
+            let objects = chunk
+                .iter()
+                .map(|key| {
+                    ObjectIdentifier::builder()
+                        .key(String::from(key.clone()))
+                        .build()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let output = self
+                .client
+                .delete_objects()
+                .bucket(&self.config.storage.bucket)
+                .delete(
+                    Delete::builder()
+                        .set_objects(Some(objects))
+                        .quiet(true)
+                        .build()?,
+                )
+                .send()
+                .await?;
+            if let Some(error) = output.errors().first() {
+                return Err(anyhow::anyhow!(
+                    "delete object error: key={:?} code={:?} message={:?}",
+                    error.key(),
+                    error.code(),
+                    error.message()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_by_prefix(&mut self, prefix: &str) -> Result<(), Self::Error> {
+        let mut continuation_token = None;
+        loop {
+            let output = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.config.storage.bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await?;
+            let keys = output
+                .contents()
+                .iter()
+                .filter_map(|object| object.key())
+                .map(|key| entities::FileKey::from_unchecked(key.to_string()))
+                .collect::<Vec<_>>();
+            self.delete(&keys).await?;
+
+            continuation_token = output.next_continuation_token().map(str::to_string);
+            if continuation_token.is_none() {
+                break;
+            }
+        }
 
         Ok(())
     }
@@ -89,7 +89,7 @@ where
         user_config.updated_at = Some(now);
 
         if prev_version.is_none() {
-            sqlx::query!(
+            let result = sqlx::query!(
                 r#" 
                     INSERT
                         INTO user_configs (
@@ -101,6 +101,7 @@ where
                             updated_at,
                             version
                         ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+                        ON CONFLICT DO NOTHING
                     "#,
                 &String::from(user_config.user_id.clone()),
                 user_config.allow_sharing_character_configs,
@@ -113,6 +114,10 @@ where
             .execute(&mut *trx)
             .await
             .context("insert character_config")?;
+
+            if result.rows_affected() == 0 {
+                return Err(ports::ConflictError.into());
+            }
         } else {
             let result = sqlx::query!(
                 r#"
@@ -145,7 +150,7 @@ where
             .context("update character_config")?;
 
             if result.rows_affected() == 0 {
-                return Err(anyhow!("conflict"));
+                return Err(ports::ConflictError.into());
             }
         }
 
@@ -204,4 +209,25 @@ mod tests {
         let fetched_config = repo.get(user_id).await.unwrap();
         assert_eq!(fetched_config, saved_config);
     }
+
+    #[sqlx::test]
+    async fn test_save_conflict(pool: sqlx::PgPool) {
+        let mut repo = UserConfigsRepositoryImpl::new(pool);
+        let user_id = entities::UserId::from("test_user".to_string());
+        let now = Utc::now().with_nanosecond(0).unwrap();
+
+        let config = repo.get(user_id.clone()).await.unwrap();
+        let saved = repo.save(now, config.clone()).await.unwrap();
+
+        // 未作成(version 0)のつもりで作成したが、既に作成されていた(ON CONFLICT DO NOTHING)
+        let err = repo.save(now, config).await.unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+
+        // 古いversionでの更新
+        let updated = repo.save(now, saved.clone()).await.unwrap();
+        let err = repo.save(now, saved).await.unwrap_err();
+        assert!(err.downcast_ref::<ports::ConflictError>().is_some());
+
+        assert_eq!(repo.get(user_id).await.unwrap().version, updated.version);
+    }
 }
@@ -46,6 +46,10 @@ pub struct AppConfig {
     #[serde(default = "workers_default")]
     pub workers: usize,
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub persisted_queries: PersistedQueriesConfig,
+    #[serde(default)]
+    pub query_limits: QueryLimitsConfig,
 }
 
 // serde_envがprefixに未対応なので
@@ -100,6 +104,48 @@ pub struct StorageConfig {
     pub path_style: bool,
 }
 
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PersistedQueriesConfig {
+    // 起動時に読み込む {"<sha256>": "<query>"} のJSONファイル。DBに登録されたものも使われる
+    pub file: Option<String>,
+    // trueなら登録されていないクエリを拒否する(本番用)
+    #[serde(default)]
+    pub only: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct QueryLimitsConfig {
+    #[serde(default = "query_limits_max_depth_default")]
+    pub max_depth: usize,
+    #[serde(default = "query_limits_max_aliases_default")]
+    pub max_aliases: usize,
+    // 推定コストの上限。計算方法はquery_limitsを参照
+    #[serde(default = "query_limits_max_complexity_default")]
+    pub max_complexity: u64,
+}
+
+impl Default for QueryLimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: query_limits_max_depth_default(),
+            max_aliases: query_limits_max_aliases_default(),
+            max_complexity: query_limits_max_complexity_default(),
+        }
+    }
+}
+
+fn query_limits_max_depth_default() -> usize {
+    12
+}
+
+fn query_limits_max_aliases_default() -> usize {
+    30
+}
+
+fn query_limits_max_complexity_default() -> u64 {
+    20000
+}
+
 fn storage_presigned_upload_expires_in_secs_default() -> u64 {
     60 * 60 // 1 hour
 }
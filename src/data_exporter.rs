@@ -0,0 +1,328 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, Write};
+use std::path::PathBuf;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use ulid::Ulid;
+use zip::write::SimpleFileOptions;
+
+use crate::{
+    adapters::{
+        CharacterConfigsRepositoryImpl, FigureRecordsRepositoryImpl, FilesRepositoryImpl,
+        GenerateTemplatesRepositoryImpl, ReferenceFiguresRepositoryImpl, StorageImpl,
+        UserConfigsRepositoryImpl,
+    },
+    entities,
+    ports::{
+        CharacterConfigsRepository, FigureRecordsRepository, FilesRepository,
+        GenerateTemplatesRepository, ReferenceFiguresRepository, Storage, UserConfigsRepository,
+    },
+};
+
+/*
+ユーザーの全データをJSONにしてzipにまとめFileとして保存する
+ファイルはメタデータのみで中身は含めない(ダウンロードURLはFileから取得できる)
+*/
+#[derive(Debug, Clone)]
+pub struct DataExporter {
+    pool: PgPool,
+    storage: StorageImpl,
+}
+
+impl DataExporter {
+    pub fn new(pool: PgPool, storage: StorageImpl) -> Self {
+        Self { pool, storage }
+    }
+
+    pub async fn export(
+        &mut self,
+        user_id: entities::UserId,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<entities::File> {
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(self.pool.clone());
+        let mut character_configs_repository =
+            CharacterConfigsRepositoryImpl::new(self.pool.clone());
+        let mut reference_figures_repository =
+            ReferenceFiguresRepositoryImpl::new(self.pool.clone());
+        let mut user_configs_repository = UserConfigsRepositoryImpl::new(self.pool.clone());
+        let mut generate_templates_repository =
+            GenerateTemplatesRepositoryImpl::new(self.pool.clone());
+        let mut files_repository = FilesRepositoryImpl::new(self.pool.clone());
+
+        let figure_records = figure_records_repository
+            .get_all_by_user_id(user_id.clone())
+            .await
+            .context("load figure_records")?;
+        let character_configs = character_configs_repository
+            .get_all_by_user_id(user_id.clone())
+            .await
+            .context("load character_configs")?;
+        let reference_figures = reference_figures_repository
+            .get_all_by_user_id(user_id.clone())
+            .await
+            .context("load reference_figures")?;
+        let user_config = user_configs_repository
+            .get(user_id.clone())
+            .await
+            .context("load user_config")?;
+        let generate_templates = generate_templates_repository
+            .get_all_by_user_id(user_id.clone())
+            .await
+            .context("load generate_templates")?;
+        let files = files_repository
+            .get_all_by_user_id(user_id.clone())
+            .await
+            .context("load files")?;
+
+        // 行はすべてメモリに読み込んでいるが、zip自体はメモリに持たずに一時ファイルに書き出し、そこからアップロードする
+        let archive = TempFile(std::env::temp_dir().join(format!("export-{}.zip", Ulid::new())));
+        let path = archive.0.clone();
+        let size = tokio::task::spawn_blocking(move || -> anyhow::Result<u64> {
+            let mut writer = zip::ZipWriter::new(BufWriter::new(File::create(&path)?));
+            write_json_entry(
+                &mut writer,
+                "figure_records.json",
+                &figure_records
+                    .iter()
+                    .map(FigureRecordExport::from)
+                    .collect::<Vec<_>>(),
+            )?;
+            write_json_entry(
+                &mut writer,
+                "character_configs.json",
+                &character_configs
+                    .iter()
+                    .map(CharacterConfigExport::from)
+                    .collect::<Vec<_>>(),
+            )?;
+            write_json_entry(
+                &mut writer,
+                "reference_figures.json",
+                &reference_figures
+                    .iter()
+                    .map(ReferenceFigureExport::from)
+                    .collect::<Vec<_>>(),
+            )?;
+            write_json_entry(
+                &mut writer,
+                "user_config.json",
+                &UserConfigExport::from(&user_config),
+            )?;
+            write_json_entry(
+                &mut writer,
+                "generate_templates.json",
+                &generate_templates
+                    .iter()
+                    .map(GenerateTemplateExport::from)
+                    .collect::<Vec<_>>(),
+            )?;
+            write_json_entry(
+                &mut writer,
+                "files.json",
+                &files.iter().map(FileExport::from).collect::<Vec<_>>(),
+            )?;
+
+            let file = writer.finish()?.into_inner()?;
+            Ok(file.metadata()?.len())
+        })
+        .await??;
+
+        let mime_type = entities::MimeType::zip();
+        let size =
+            entities::FileSize::from_generated(size).context("exported archive is too large")?;
+        let file = files_repository
+            .create(user_id, now, mime_type.clone(), size)
+            .await
+            .context("create file")?;
+        self.storage
+            .put_file(&file.key, &mime_type, &archive.0)
+            .await
+            .context("upload archive")?;
+        let file = files_repository
+            .verified(now, file)
+            .await
+            .context("verify file")?;
+
+        Ok(file)
+    }
+}
+
+fn write_json_entry<W: Write + Seek>(
+    writer: &mut zip::ZipWriter<W>,
+    name: &str,
+    value: &impl Serialize,
+) -> anyhow::Result<()> {
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    writer.start_file(name, options)?;
+    serde_json::to_writer_pretty(&mut *writer, value)?;
+    Ok(())
+}
+
+// 失敗した場合も含めて、スコープを抜けたら削除する
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("remove temp file error: {}", e);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FigureRecordExport {
+    id: String,
+    character: String,
+    stroke_count: i32,
+    figure: serde_json::Value,
+    created_at: String,
+    disabled: bool,
+    disabled_at: Option<String>,
+}
+
+impl From<&entities::FigureRecord> for FigureRecordExport {
+    fn from(value: &entities::FigureRecord) -> Self {
+        Self {
+            id: Ulid::from(value.id).to_string(),
+            character: String::from(value.character.clone()),
+            stroke_count: i32::from(value.figure.stroke_count()),
+            figure: value.figure.to_json_ast(),
+            created_at: value.created_at.to_rfc3339(),
+            disabled: value.disabled,
+            disabled_at: value
+                .disabled_at
+                .map(|disabled_at| disabled_at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct CharacterConfigExport {
+    character: String,
+    stroke_count: i32,
+    ratio: i32,
+    disabled: bool,
+    updated_at: Option<String>,
+}
+
+impl From<&entities::CharacterConfig> for CharacterConfigExport {
+    fn from(value: &entities::CharacterConfig) -> Self {
+        Self {
+            character: String::from(value.character.clone()),
+            stroke_count: i32::from(value.stroke_count),
+            ratio: i32::from(value.ratio),
+            disabled: value.disabled,
+            updated_at: value.updated_at.map(|updated_at| updated_at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ReferenceFigureExport {
+    character: String,
+    stroke_count: i32,
+    variant: String,
+    figure: serde_json::Value,
+    updated_at: Option<String>,
+}
+
+impl From<&entities::ReferenceFigure> for ReferenceFigureExport {
+    fn from(value: &entities::ReferenceFigure) -> Self {
+        Self {
+            character: String::from(value.character.clone()),
+            stroke_count: i32::from(value.stroke_count),
+            variant: String::from(value.variant.clone()),
+            figure: value.figure.to_json_ast(),
+            updated_at: value.updated_at.map(|updated_at| updated_at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct UserConfigExport {
+    allow_sharing_character_configs: bool,
+    allow_sharing_figure_records: bool,
+    random_level: i32,
+    shared_proportion: i32,
+    updated_at: Option<String>,
+}
+
+impl From<&entities::UserConfig> for UserConfigExport {
+    fn from(value: &entities::UserConfig) -> Self {
+        Self {
+            allow_sharing_character_configs: value.allow_sharing_character_configs,
+            allow_sharing_figure_records: value.allow_sharing_figure_records,
+            random_level: i32::from(value.random_level),
+            shared_proportion: i32::from(value.shared_proportion),
+            updated_at: value.updated_at.map(|updated_at| updated_at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct GenerateTemplateExport {
+    id: String,
+    background_image_file_id: String,
+    font_color: i32,
+    writing_mode: &'static str,
+    margin_block_start: i32,
+    margin_inline_start: i32,
+    line_spacing: i32,
+    letter_spacing: i32,
+    font_size: i32,
+    font_weight: i32,
+    created_at: String,
+    updated_at: String,
+    disabled: bool,
+}
+
+impl From<&entities::GenerateTemplate> for GenerateTemplateExport {
+    fn from(value: &entities::GenerateTemplate) -> Self {
+        Self {
+            id: Ulid::from(value.id).to_string(),
+            background_image_file_id: Ulid::from(value.background_image_file_id).to_string(),
+            font_color: i32::from(value.font_color),
+            writing_mode: match value.writing_mode {
+                entities::WritingMode::Horizontal => "horizontal",
+                entities::WritingMode::Vertical => "vertical",
+            },
+            margin_block_start: i32::from(value.margin_block_start),
+            margin_inline_start: i32::from(value.margin_inline_start),
+            line_spacing: i32::from(value.line_spacing),
+            letter_spacing: i32::from(value.letter_spacing),
+            font_size: i32::from(value.font_size),
+            font_weight: i32::from(value.font_weight),
+            created_at: value.created_at.to_rfc3339(),
+            updated_at: value.updated_at.to_rfc3339(),
+            disabled: value.disabled,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct FileExport {
+    id: String,
+    mime_type: String,
+    size: i32,
+    verified: bool,
+    created_at: String,
+    updated_at: String,
+}
+
+impl From<&entities::File> for FileExport {
+    fn from(value: &entities::File) -> Self {
+        Self {
+            id: Ulid::from(value.id).to_string(),
+            mime_type: value.mime_type.value().to_string(),
+            size: i32::from(value.size),
+            verified: value.verified,
+            created_at: value.created_at.to_rfc3339(),
+            updated_at: value.updated_at.to_rfc3339(),
+        }
+    }
+}
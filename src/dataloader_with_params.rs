@@ -44,9 +44,23 @@ impl<F: BatchFnWithParams> DataloaderWithParams<F> {
     }
 
     pub async fn load(&self, params: F::P, key: F::K) -> io::Result<F::V> {
+        self.loader(params).await.try_load(key).await
+    }
+
+    pub async fn load_many(
+        &self,
+        params: F::P,
+        keys: Vec<F::K>,
+    ) -> io::Result<HashMap<F::K, F::V>> {
+        if keys.is_empty() {
+            return Ok(HashMap::new());
+        }
+        self.loader(params).await.try_load_many(keys).await
+    }
+
+    async fn loader(&self, params: F::P) -> Loader<F::K, F::V, DataloaderWithParamsBatchFn<F>> {
         let mut map = self.1.lock().await;
-        let loader = map
-            .entry(params.clone())
+        map.entry(params.clone())
             .or_insert_with(|| {
                 Loader::new(DataloaderWithParamsBatchFn {
                     params,
@@ -54,8 +68,6 @@ impl<F: BatchFnWithParams> DataloaderWithParams<F> {
                 })
                 .with_yield_count(100)
             })
-            .clone();
-        drop(map);
-        loader.try_load(key).await
+            .clone()
     }
 }
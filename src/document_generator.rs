@@ -0,0 +1,227 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    adapters::{
+        CharacterConfigSeedsRepositoryImpl, CharacterConfigsRepositoryImpl,
+        FigureRecordsRepositoryImpl, FilesRepositoryImpl, StorageImpl, UserConfigsRepositoryImpl,
+    },
+    entities,
+    ports::{
+        CharacterConfigSeedsRepository, CharacterConfigsRepository,
+        FigureRecordsByCharacterConfigIdQuery, FigureRecordsRepository, FilesRepository, Storage,
+        UserConfigsRepository, UserType,
+    },
+    render,
+};
+
+// 1文字の平均を取るのに使う記録の数(自分/共有それぞれ)
+const FIGURE_RECORDS_PER_CHARACTER: i32 = 100;
+
+/*
+GenerateTemplateとテキストから文書の画像を生成してFileとして保存する
+各文字は自分の設定の中で最もratioの大きい画数(設定が無ければシードの画数)の記録を平均した字形で描く
+*/
+#[derive(Debug, Clone)]
+pub struct DocumentGenerator {
+    pool: PgPool,
+    storage: StorageImpl,
+}
+
+impl DocumentGenerator {
+    pub fn new(pool: PgPool, storage: StorageImpl) -> Self {
+        Self { pool, storage }
+    }
+
+    pub async fn generate(
+        &mut self,
+        user_id: entities::UserId,
+        now: DateTime<Utc>,
+        template: &entities::GenerateTemplate,
+        text: &entities::DocumentText,
+        seed: u64,
+    ) -> anyhow::Result<entities::File> {
+        let mut files_repository = FilesRepositoryImpl::new(self.pool.clone());
+
+        let background_image_file = files_repository
+            .get_by_ids(
+                template.user_id.clone(),
+                &[template.background_image_file_id],
+                true,
+            )
+            .await
+            .context("load background image file")?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("background image file not found"))?;
+        let background = self
+            .storage
+            .get(&background_image_file.key)
+            .await
+            .context("download background image")?;
+
+        let figures = self
+            .load_figures(user_id.clone(), text, seed)
+            .await
+            .context("load figures")?;
+
+        let template = template.clone();
+        let text = text.clone();
+        let png = tokio::task::spawn_blocking(move || {
+            render::render_document(&background, &template, text.value(), &figures)
+        })
+        .await??;
+
+        let mime_type = entities::MimeType::try_from("image/png".to_string())?;
+        let size = entities::FileSize::try_from(i32::try_from(png.len())?)
+            .context("generated document is too large")?;
+        let file = files_repository
+            .create(user_id, now, mime_type.clone(), size)
+            .await
+            .context("create file")?;
+        self.storage
+            .put(&file.key, &mime_type, png)
+            .await
+            .context("upload document")?;
+        let file = files_repository
+            .verified(now, file)
+            .await
+            .context("verify file")?;
+
+        Ok(file)
+    }
+
+    async fn load_figures(
+        &mut self,
+        user_id: entities::UserId,
+        text: &entities::DocumentText,
+        seed: u64,
+    ) -> anyhow::Result<HashMap<entities::Character, entities::Figure>> {
+        let mut character_configs_repository =
+            CharacterConfigsRepositoryImpl::new(self.pool.clone());
+        let mut character_config_seeds_repository =
+            CharacterConfigSeedsRepositoryImpl::new(self.pool.clone());
+        let mut user_configs_repository = UserConfigsRepositoryImpl::new(self.pool.clone());
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(self.pool.clone());
+
+        let characters = text
+            .value()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(entities::Character::from)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        let mut stroke_counts =
+            HashMap::<entities::Character, (entities::Ratio, entities::StrokeCount)>::new();
+        for config_seed in character_config_seeds_repository
+            .get_by_characters(&characters)
+            .await?
+        {
+            insert_if_greater(
+                &mut stroke_counts,
+                config_seed.character,
+                config_seed.ratio,
+                config_seed.stroke_count,
+            );
+        }
+        // 自分の設定がある文字はシードより優先する
+        let mut configured = HashMap::new();
+        for config in character_configs_repository
+            .get_by_characters(&characters, user_id.clone())
+            .await?
+        {
+            insert_if_greater(
+                &mut configured,
+                config.character,
+                config.ratio,
+                config.stroke_count,
+            );
+        }
+        stroke_counts.extend(configured);
+
+        let keys = stroke_counts
+            .into_iter()
+            .map(|(character, (_, stroke_count))| (character, stroke_count))
+            .collect::<Vec<_>>();
+
+        let user_config = user_configs_repository.get(user_id.clone()).await?;
+        let limit = entities::Limit::new(entities::LimitKind::First, FIGURE_RECORDS_PER_CHARACTER)?;
+
+        let user_types = [UserType::Myself, UserType::Other];
+        let queries = user_types
+            .iter()
+            .flat_map(|&user_type| {
+                keys.iter().map(move |(character, stroke_count)| {
+                    FigureRecordsByCharacterConfigIdQuery {
+                        character: character.clone(),
+                        stroke_count: *stroke_count,
+                        ids: None,
+                        after_id: None,
+                        before_id: None,
+                        limit,
+                        user_type: Some(user_type),
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut figures_by_user_type = vec![HashMap::<_, Vec<_>>::new(); user_types.len()];
+        for (query, records) in queries.iter().zip(
+            figure_records_repository
+                .get_by_character_config_ids(user_id.clone(), &queries)
+                .await?,
+        ) {
+            let figures = if query.user_type == Some(UserType::Myself) {
+                &mut figures_by_user_type[0]
+            } else {
+                &mut figures_by_user_type[1]
+            };
+            for record in records {
+                figures
+                    .entry(record.character)
+                    .or_default()
+                    .push(record.figure);
+            }
+        }
+
+        let mut result = HashMap::new();
+        for (character, _) in keys {
+            let own = figures_by_user_type[0]
+                .get(&character)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let shared = figures_by_user_type[1]
+                .get(&character)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            if let Some(figure) = entities::average_figure(
+                own,
+                shared,
+                user_config.random_level,
+                user_config.shared_proportion,
+                seed ^ u64::from(char::from(character.clone())),
+            ) {
+                result.insert(character, figure);
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+fn insert_if_greater(
+    map: &mut HashMap<entities::Character, (entities::Ratio, entities::StrokeCount)>,
+    character: entities::Character,
+    ratio: entities::Ratio,
+    stroke_count: entities::StrokeCount,
+) {
+    let entry = map.entry(character).or_insert((ratio, stroke_count));
+    if ratio > entry.0 {
+        *entry = (ratio, stroke_count);
+    }
+}
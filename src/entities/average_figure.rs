@@ -0,0 +1,148 @@
+use super::{Figure, RandomLevel, SharedProportion};
+
+/*
+自分のfigureと共有されたfigureを重み付けして平均を取る
+自分:共有 = (100 - shared_proportion):shared_proportion の割合で重みを配分し、どちらか一方しか無い場合はもう一方に全て配分する
+random_levelが大きいほど各figureの重みをseedから決まる乱数で大きく揺らす(同じseedなら同じ結果になる)
+*/
+pub fn average_figure(
+    own_figures: &[Figure],
+    shared_figures: &[Figure],
+    random_level: RandomLevel,
+    shared_proportion: SharedProportion,
+    seed: u64,
+) -> Option<Figure> {
+    let shared_ratio = match (own_figures.is_empty(), shared_figures.is_empty()) {
+        (true, true) => return None,
+        (true, false) => 1.0,
+        (false, true) => 0.0,
+        (false, false) => f64::from(i32::from(shared_proportion)) / 100.0,
+    };
+    let random_ratio = f64::from(i32::from(random_level)) / 100.0;
+
+    let mut rng = XorShift64::new(seed);
+    let weighted = own_figures
+        .iter()
+        .map(|figure| (figure, (1.0 - shared_ratio) / own_figures.len() as f64))
+        .chain(
+            shared_figures
+                .iter()
+                .map(|figure| (figure, shared_ratio / shared_figures.len() as f64)),
+        )
+        .map(|(figure, weight)| {
+            let jitter = 1.0 + random_ratio * (rng.next_f64() * 2.0 - 1.0);
+            (figure.clone(), weight * jitter)
+        })
+        .collect::<Vec<_>>();
+
+    Figure::average(&weighted)
+}
+
+// 外部crateに依存せず再現性のある乱数が欲しいだけなので簡易的なもの
+struct XorShift64(u64);
+
+impl XorShift64 {
+    fn new(seed: u64) -> Self {
+        // 状態が0だと0しか出なくなる
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        Self(if state == 0 {
+            0x9E37_79B9_7F4A_7C15
+        } else {
+            state
+        })
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn figure(points: &[(f64, f64)], width: f64, height: f64) -> Figure {
+        Figure::from_json_ast(serde_json::json!({
+            "strokes": [{
+                "points": points
+                    .iter()
+                    .map(|(x, y)| serde_json::json!({ "x": x, "y": y, "z": 0.5 }))
+                    .collect::<Vec<_>>(),
+            }],
+            "width": width,
+            "height": height,
+        }))
+        .unwrap()
+    }
+
+    fn level(value: i32) -> RandomLevel {
+        RandomLevel::try_from(value).unwrap()
+    }
+
+    fn proportion(value: i32) -> SharedProportion {
+        SharedProportion::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_average_figure() {
+        let a = figure(&[(0.0, 0.0), (10.0, 0.0)], 10.0, 10.0);
+        // 点数が違っても平均前にリサンプリングされる
+        let b = figure(&[(0.0, 10.0), (5.0, 10.0), (10.0, 10.0)], 10.0, 10.0);
+        let c = figure(&[(0.0, 20.0), (20.0, 20.0)], 20.0, 20.0);
+
+        assert!(average_figure(&[], &[], level(0), proportion(50), 0).is_none());
+
+        // 片方しか無い場合はshared_proportionに関係なくそちらだけで平均する
+        let own_only =
+            average_figure(&[a.clone(), b.clone()], &[], level(0), proportion(100), 0).unwrap();
+        let expected = Figure::average(&[(a.clone(), 0.5), (b.clone(), 0.5)]).unwrap();
+        assert_eq!(own_only.to_json_ast(), expected.to_json_ast());
+        let points = own_only.strokes().next().unwrap();
+        assert_eq!((points[0].x, points[0].y), (0.0, 5.0));
+        assert_eq!(
+            (points[points.len() - 1].x, points[points.len() - 1].y),
+            (10.0, 5.0)
+        );
+
+        let shared_only =
+            average_figure(&[], std::slice::from_ref(&c), level(0), proportion(0), 0).unwrap();
+        let expected = Figure::average(&[(c.clone(), 1.0)]).unwrap();
+        assert_eq!(shared_only.to_json_ast(), expected.to_json_ast());
+
+        // 自分:共有 = 75:25
+        let both = average_figure(
+            std::slice::from_ref(&a),
+            std::slice::from_ref(&c),
+            level(0),
+            proportion(25),
+            0,
+        )
+        .unwrap();
+        let expected = Figure::average(&[(a.clone(), 0.75), (c.clone(), 0.25)]).unwrap();
+        assert_eq!(both.to_json_ast(), expected.to_json_ast());
+        assert_eq!(both.width(), 12.5);
+    }
+
+    #[test]
+    fn test_average_figure_random_level() {
+        let own = [
+            figure(&[(0.0, 0.0), (10.0, 0.0)], 10.0, 10.0),
+            figure(&[(0.0, 10.0), (10.0, 10.0)], 10.0, 10.0),
+        ];
+
+        // random_levelが0ならseedに依存しない
+        let x = average_figure(&own, &[], level(0), proportion(50), 1).unwrap();
+        let y = average_figure(&own, &[], level(0), proportion(50), 2).unwrap();
+        assert_eq!(x.to_json_ast(), y.to_json_ast());
+
+        // 同じseedなら同じ結果になり、seedが違えば揺らぐ
+        let x = average_figure(&own, &[], level(100), proportion(50), 1).unwrap();
+        let y = average_figure(&own, &[], level(100), proportion(50), 1).unwrap();
+        let z = average_figure(&own, &[], level(100), proportion(50), 2).unwrap();
+        assert_eq!(x.to_json_ast(), y.to_json_ast());
+        assert_ne!(x.to_json_ast(), z.to_json_ast());
+    }
+}
@@ -0,0 +1,45 @@
+use thiserror::Error;
+
+// 文書生成で書く文字列
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct DocumentText(String);
+
+const MAX_DOCUMENT_TEXT_LENGTH: usize = 2000;
+
+impl DocumentText {
+    pub fn value(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum DocumentTextTryFromError {
+    #[error(
+        "Text must be less than or equal to {} characters",
+        MAX_DOCUMENT_TEXT_LENGTH
+    )]
+    TooLong,
+    #[error("Text must not be empty")]
+    Empty,
+}
+
+impl TryFrom<String> for DocumentText {
+    type Error = DocumentTextTryFromError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let length = value.chars().count();
+        if length > MAX_DOCUMENT_TEXT_LENGTH {
+            Err(DocumentTextTryFromError::TooLong)
+        } else if length == 0 {
+            Err(DocumentTextTryFromError::Empty)
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl From<DocumentText> for String {
+    fn from(value: DocumentText) -> Self {
+        value.0
+    }
+}
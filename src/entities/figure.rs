@@ -1,4 +1,5 @@
 use super::StrokeCount;
+use thiserror::Error;
 
 mod json_model {
     use serde::{Deserialize, Serialize};
@@ -22,6 +23,33 @@ mod json_model {
     }
 }
 
+pub use json_model::Point as FigurePoint;
+
+// 平均化する前に各ストロークをこの点数にリサンプリングする
+const AVERAGE_RESAMPLE_POINTS: usize = 32;
+
+// JSONにした時の最大サイズ
+const MAX_FIGURE_JSON_BYTES: usize = 512 * 1024;
+
+// キャンバスからはみ出すことを許容する範囲(width/heightに対する割合)
+const CANVAS_MARGIN_RATIO: f64 = 0.5;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum FigureValidationError {
+    #[error("Figure must be smaller than {} bytes", MAX_FIGURE_JSON_BYTES)]
+    TooLarge,
+    #[error("Figure width and height must be positive finite numbers")]
+    InvalidSize,
+    #[error("Stroke {stroke} must have at least 2 points")]
+    TooFewPoints { stroke: usize },
+    #[error("Stroke {stroke} point {point} has a non finite coordinate")]
+    NonFiniteCoordinate { stroke: usize, point: usize },
+    #[error("Stroke {stroke} point {point} is too far outside the canvas")]
+    OutOfCanvas { stroke: usize, point: usize },
+    #[error("Stroke {stroke} point {point} has a pressure outside [0, 1]")]
+    PressureOutOfRange { stroke: usize, point: usize },
+}
+
 #[derive(Clone, Debug)]
 pub struct Figure {
     model: json_model::Figure,
@@ -53,4 +81,487 @@ impl Figure {
     pub fn stroke_count(&self) -> StrokeCount {
         self.stroke_count
     }
+
+    // from_json/from_json_astは保存済みのデータの読み込みにも使うのでJSONの形しか検査しない
+    // ユーザーからの入力はこれで検査すること
+    pub fn validate(&self) -> Result<(), FigureValidationError> {
+        if self.to_json().len() > MAX_FIGURE_JSON_BYTES {
+            return Err(FigureValidationError::TooLarge);
+        }
+
+        let (width, height) = (self.model.width, self.model.height);
+        if !(width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0) {
+            return Err(FigureValidationError::InvalidSize);
+        }
+
+        for (stroke_index, stroke) in self.model.strokes.iter().enumerate() {
+            if stroke.points.len() < 2 {
+                return Err(FigureValidationError::TooFewPoints {
+                    stroke: stroke_index,
+                });
+            }
+
+            for (point_index, point) in stroke.points.iter().enumerate() {
+                if !(point.x.is_finite() && point.y.is_finite() && point.z.is_finite()) {
+                    return Err(FigureValidationError::NonFiniteCoordinate {
+                        stroke: stroke_index,
+                        point: point_index,
+                    });
+                }
+
+                if point.x < -width * CANVAS_MARGIN_RATIO
+                    || point.x > width * (1.0 + CANVAS_MARGIN_RATIO)
+                    || point.y < -height * CANVAS_MARGIN_RATIO
+                    || point.y > height * (1.0 + CANVAS_MARGIN_RATIO)
+                {
+                    return Err(FigureValidationError::OutOfCanvas {
+                        stroke: stroke_index,
+                        point: point_index,
+                    });
+                }
+
+                if !(0.0..=1.0).contains(&point.z) {
+                    return Err(FigureValidationError::PressureOutOfRange {
+                        stroke: stroke_index,
+                        point: point_index,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn width(&self) -> f64 {
+        self.model.width
+    }
+
+    pub fn height(&self) -> f64 {
+        self.model.height
+    }
+
+    pub fn strokes(&self) -> impl Iterator<Item = &[FigurePoint]> {
+        self.model
+            .strokes
+            .iter()
+            .map(|stroke| stroke.points.as_slice())
+    }
+
+    // 各ストロークを弧長で等間隔なn点にリサンプリングする。点が無いストロークはそのまま
+    pub fn resample(&self, n: usize) -> Figure {
+        Figure {
+            model: json_model::Figure {
+                strokes: self
+                    .model
+                    .strokes
+                    .iter()
+                    .map(|stroke| json_model::Stroke {
+                        points: resample_points(&stroke.points, n).unwrap_or_default(),
+                    })
+                    .collect(),
+                width: self.model.width,
+                height: self.model.height,
+            },
+            stroke_count: self.stroke_count,
+        }
+    }
+
+    // width/heightで割って幅と高さが1の座標系に変換する。width/heightが正でなければNone
+    pub fn normalize(&self) -> Option<Figure> {
+        let (width, height) = (self.model.width, self.model.height);
+        if !(width > 0.0 && height > 0.0) {
+            return None;
+        }
+
+        Some(self.map_points(1.0, 1.0, |point| json_model::Point {
+            x: point.x / width,
+            y: point.y / height,
+            z: point.z,
+        }))
+    }
+
+    // 各ストロークの長さ(xy平面上の折れ線の長さ)
+    pub fn stroke_lengths(&self) -> Vec<f64> {
+        self.model
+            .strokes
+            .iter()
+            .map(|stroke| {
+                stroke
+                    .points
+                    .windows(2)
+                    .map(|pair| distance(&pair[0], &pair[1]))
+                    .sum()
+            })
+            .collect()
+    }
+
+    // 各ストロークのバウンディングボックス。点が無いストロークはNone
+    pub fn stroke_bounding_boxes(&self) -> Vec<Option<BoundingBox>> {
+        self.model
+            .strokes
+            .iter()
+            .map(|stroke| {
+                let first = stroke.points.first()?;
+                Some(stroke.points.iter().skip(1).fold(
+                    BoundingBox {
+                        min_x: first.x,
+                        min_y: first.y,
+                        max_x: first.x,
+                        max_y: first.y,
+                    },
+                    |bbox, point| BoundingBox {
+                        min_x: bbox.min_x.min(point.x),
+                        min_y: bbox.min_y.min(point.y),
+                        max_x: bbox.max_x.max(point.x),
+                        max_y: bbox.max_y.max(point.y),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /*
+    重み付き平均を取る
+    正規化してから各ストロークをリサンプリングして平均し、平均のwidth/heightに戻す
+    全てのfigureのstroke_countが一致していない場合、点の無いストロークがある場合、重みの合計が0以下の場合はNone
+    */
+    pub fn average(figures: &[(Figure, f64)]) -> Option<Figure> {
+        let (first, _) = figures.first()?;
+        let stroke_count = first.stroke_count;
+        if figures.iter().any(|(figure, weight)| {
+            figure.stroke_count != stroke_count
+                || weight.is_nan()
+                || *weight < 0.0
+                || figure
+                    .model
+                    .strokes
+                    .iter()
+                    .any(|stroke| stroke.points.is_empty())
+        }) {
+            return None;
+        }
+
+        let total_weight = figures.iter().map(|(_, weight)| weight).sum::<f64>();
+        if total_weight <= 0.0 {
+            return None;
+        }
+
+        let width = figures
+            .iter()
+            .map(|(figure, weight)| figure.model.width * weight)
+            .sum::<f64>()
+            / total_weight;
+        let height = figures
+            .iter()
+            .map(|(figure, weight)| figure.model.height * weight)
+            .sum::<f64>()
+            / total_weight;
+
+        let mut sum = first
+            .resample(AVERAGE_RESAMPLE_POINTS)
+            .map_points(1.0, 1.0, |_| json_model::Point {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+            });
+
+        for (figure, weight) in figures {
+            let ratio = weight / total_weight;
+            let figure = figure.normalize()?.resample(AVERAGE_RESAMPLE_POINTS);
+            for (sum, stroke) in sum.model.strokes.iter_mut().zip(figure.model.strokes) {
+                for (sum, point) in sum.points.iter_mut().zip(stroke.points) {
+                    sum.x += point.x * ratio;
+                    sum.y += point.y * ratio;
+                    sum.z += point.z * ratio;
+                }
+            }
+        }
+
+        Some(sum.map_points(width, height, |point| json_model::Point {
+            x: point.x * width,
+            y: point.y * height,
+            z: point.z,
+        }))
+    }
+
+    fn map_points(
+        &self,
+        width: f64,
+        height: f64,
+        f: impl Fn(&json_model::Point) -> json_model::Point,
+    ) -> Figure {
+        Figure {
+            model: json_model::Figure {
+                strokes: self
+                    .model
+                    .strokes
+                    .iter()
+                    .map(|stroke| json_model::Stroke {
+                        points: stroke.points.iter().map(&f).collect(),
+                    })
+                    .collect(),
+                width,
+                height,
+            },
+            stroke_count: self.stroke_count,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+}
+
+fn distance(a: &json_model::Point, b: &json_model::Point) -> f64 {
+    ((b.x - a.x).powi(2) + (b.y - a.y).powi(2)).sqrt()
+}
+
+// 弧長で等間隔なn点にリサンプリングする。点が無い場合はNone
+fn resample_points(points: &[json_model::Point], n: usize) -> Option<Vec<json_model::Point>> {
+    let first = points.first()?;
+
+    let mut distances = Vec::with_capacity(points.len());
+    let mut total = 0.0;
+    distances.push(total);
+    for pair in points.windows(2) {
+        total += distance(&pair[0], &pair[1]);
+        distances.push(total);
+    }
+
+    if n == 0 {
+        return Some(Vec::new());
+    }
+
+    if total <= 0.0 {
+        return Some(vec![first.clone(); n]);
+    }
+
+    let mut result = Vec::with_capacity(n);
+    let mut segment = 0;
+    for i in 0..n {
+        let target = if n == 1 {
+            0.0
+        } else {
+            total * i as f64 / (n - 1) as f64
+        };
+        while segment + 2 < points.len() && distances[segment + 1] < target {
+            segment += 1;
+        }
+
+        let (a, b) = match points.get(segment + 1) {
+            Some(b) => (&points[segment], b),
+            None => (&points[segment], &points[segment]),
+        };
+        let length = distances.get(segment + 1).copied().unwrap_or(total) - distances[segment];
+        let t = if length > 0.0 {
+            ((target - distances[segment]) / length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        result.push(json_model::Point {
+            x: a.x + (b.x - a.x) * t,
+            y: a.y + (b.y - a.y) * t,
+            z: a.z + (b.z - a.z) * t,
+        });
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn figure(strokes: &[&[(f64, f64)]], width: f64, height: f64) -> Figure {
+        Figure::from_json_ast(serde_json::json!({
+            "strokes": strokes
+                .iter()
+                .map(|points| serde_json::json!({
+                    "points": points
+                        .iter()
+                        .map(|(x, y)| serde_json::json!({ "x": x, "y": y, "z": 0.5 }))
+                        .collect::<Vec<_>>(),
+                }))
+                .collect::<Vec<_>>(),
+            "width": width,
+            "height": height,
+        }))
+        .unwrap()
+    }
+
+    fn points(figure: &Figure, stroke: usize) -> Vec<(f64, f64)> {
+        figure.model.strokes[stroke]
+            .points
+            .iter()
+            .map(|point| (point.x, point.y))
+            .collect()
+    }
+
+    fn assert_points_eq(actual: &[(f64, f64)], expected: &[(f64, f64)]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a.0 - e.0).abs() < 1e-9 && (a.1 - e.1).abs() < 1e-9,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_resample() {
+        let figure = figure(
+            &[&[(0.0, 0.0), (1.0, 0.0), (1.0, 3.0)], &[], &[(2.0, 2.0)]],
+            4.0,
+            4.0,
+        );
+        let resampled = figure.resample(5);
+
+        assert_eq!(resampled.stroke_count(), figure.stroke_count());
+        assert_points_eq(
+            &points(&resampled, 0),
+            &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (1.0, 2.0), (1.0, 3.0)],
+        );
+        assert_points_eq(&points(&resampled, 1), &[]);
+        assert_points_eq(&points(&resampled, 2), &[(2.0, 2.0); 5]);
+        assert!(resampled.model.strokes[0]
+            .points
+            .iter()
+            .all(|point| (point.z - 0.5).abs() < 1e-9));
+    }
+
+    #[test]
+    fn test_normalize() {
+        let figure = figure(&[&[(0.0, 0.0), (2.0, 4.0)]], 2.0, 8.0);
+        let normalized = figure.normalize().unwrap();
+
+        assert_eq!(normalized.width(), 1.0);
+        assert_eq!(normalized.height(), 1.0);
+        assert_points_eq(&points(&normalized, 0), &[(0.0, 0.0), (1.0, 0.5)]);
+
+        assert!(self::figure(&[&[(0.0, 0.0)]], 0.0, 1.0)
+            .normalize()
+            .is_none());
+        assert!(self::figure(&[&[(0.0, 0.0)]], 1.0, -1.0)
+            .normalize()
+            .is_none());
+    }
+
+    #[test]
+    fn test_stroke_lengths() {
+        let figure = figure(
+            &[&[(0.0, 0.0), (3.0, 4.0), (3.0, 5.0)], &[(1.0, 1.0)], &[]],
+            10.0,
+            10.0,
+        );
+
+        assert_eq!(figure.stroke_lengths(), vec![6.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_stroke_bounding_boxes() {
+        let figure = figure(&[&[(1.0, 5.0), (3.0, 2.0), (2.0, 4.0)], &[]], 10.0, 10.0);
+        let bounding_boxes = figure.stroke_bounding_boxes();
+
+        assert_eq!(
+            bounding_boxes,
+            vec![
+                Some(BoundingBox {
+                    min_x: 1.0,
+                    min_y: 2.0,
+                    max_x: 3.0,
+                    max_y: 5.0,
+                }),
+                None,
+            ]
+        );
+        assert_eq!(bounding_boxes[0].unwrap().width(), 2.0);
+        assert_eq!(bounding_boxes[0].unwrap().height(), 3.0);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(
+            figure(
+                &[&[(0.0, 0.0), (10.0, 10.0)], &[(-5.0, 15.0), (5.0, 5.0)]],
+                10.0,
+                10.0
+            )
+            .validate(),
+            Ok(())
+        );
+        assert_eq!(
+            figure(&[&[(0.0, 0.0), (1.0, 1.0)]], 0.0, 10.0).validate(),
+            Err(FigureValidationError::InvalidSize)
+        );
+        assert_eq!(
+            figure(&[&[(0.0, 0.0), (1.0, 1.0)], &[(1.0, 1.0)]], 10.0, 10.0).validate(),
+            Err(FigureValidationError::TooFewPoints { stroke: 1 })
+        );
+        assert_eq!(
+            figure(&[&[(0.0, 0.0), (1.0, 16.0)]], 10.0, 10.0).validate(),
+            Err(FigureValidationError::OutOfCanvas {
+                stroke: 0,
+                point: 1
+            })
+        );
+
+        let mut non_finite = figure(&[&[(0.0, 0.0), (1.0, 1.0)]], 10.0, 10.0);
+        non_finite.model.strokes[0].points[0].x = f64::NAN;
+        assert_eq!(
+            non_finite.validate(),
+            Err(FigureValidationError::NonFiniteCoordinate {
+                stroke: 0,
+                point: 0
+            })
+        );
+
+        let mut pressure = figure(&[&[(0.0, 0.0), (1.0, 1.0)]], 10.0, 10.0);
+        pressure.model.strokes[0].points[1].z = 1.5;
+        assert_eq!(
+            pressure.validate(),
+            Err(FigureValidationError::PressureOutOfRange {
+                stroke: 0,
+                point: 1
+            })
+        );
+
+        let large = figure(&[&vec![(1.0, 1.0); MAX_FIGURE_JSON_BYTES / 16]], 10.0, 10.0);
+        assert_eq!(large.validate(), Err(FigureValidationError::TooLarge));
+    }
+
+    #[test]
+    fn test_average() {
+        let a = figure(&[&[(0.0, 0.0), (10.0, 0.0)]], 10.0, 10.0);
+        let b = figure(&[&[(0.0, 20.0), (20.0, 20.0)]], 20.0, 20.0);
+        let average = Figure::average(&[(a.clone(), 1.0), (b, 3.0)]).unwrap();
+
+        assert_eq!(average.width(), 17.5);
+        assert_eq!(average.height(), 17.5);
+        let points = points(&average, 0);
+        assert_eq!(points.len(), AVERAGE_RESAMPLE_POINTS);
+        assert_points_eq(&points[..1], &[(0.0, 13.125)]);
+        assert_points_eq(&points[AVERAGE_RESAMPLE_POINTS - 1..], &[(17.5, 13.125)]);
+
+        let c = figure(&[&[(0.0, 0.0)], &[(1.0, 1.0)]], 10.0, 10.0);
+        assert!(Figure::average(&[(a.clone(), 1.0), (c, 1.0)]).is_none());
+        assert!(Figure::average(&[(a, 0.0)]).is_none());
+        assert!(Figure::average(&[]).is_none());
+    }
 }
@@ -1,8 +1,11 @@
 use super::{character, UserId, Version};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use derive_more::{From, Into};
 use ulid::Ulid;
 
+// 削除(disabled)してから復元できる期間。過ぎたものはジョブで物理削除する
+pub const FIGURE_RECORD_RETENTION_DAYS: i64 = 30;
+
 #[derive(Clone, Debug, Into, From, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub struct FigureRecordId(Ulid);
 
@@ -14,5 +17,74 @@ pub struct FigureRecord {
     pub figure: super::figure::Figure,
     pub created_at: DateTime<Utc>,
     pub disabled: bool,
+    // disabled=trueになった日時
+    pub disabled_at: Option<DateTime<Utc>>,
     pub version: Version,
 }
+
+impl FigureRecord {
+    pub fn disable(mut self, now: DateTime<Utc>) -> Self {
+        if !self.disabled {
+            self.disabled = true;
+            self.disabled_at = Some(now);
+        }
+        self
+    }
+
+    // 保持期間を過ぎて物理削除されるのを待っているものはNone
+    pub fn restore(mut self, now: DateTime<Utc>) -> Option<Self> {
+        if self.disabled {
+            if self.purge_at().is_some_and(|purge_at| purge_at <= now) {
+                return None;
+            }
+            self.disabled = false;
+            self.disabled_at = None;
+        }
+        Some(self)
+    }
+
+    // 物理削除される日時
+    pub fn purge_at(&self) -> Option<DateTime<Utc>> {
+        self.disabled_at
+            .map(|disabled_at| disabled_at + Duration::days(FIGURE_RECORD_RETENTION_DAYS))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Character, Figure};
+
+    #[test]
+    fn test_disable_and_restore() {
+        let now = Utc::now();
+        let record = FigureRecord {
+            id: FigureRecordId::from(Ulid::new()),
+            user_id: UserId::from("user".to_string()),
+            character: Character::from('あ'),
+            figure: Figure::from_json(r#"{"strokes":[],"width":1,"height":1}"#).unwrap(),
+            created_at: now,
+            disabled: false,
+            disabled_at: None,
+            version: Version::new(),
+        };
+
+        let disabled = record.disable(now);
+        assert!(disabled.disabled);
+        assert_eq!(disabled.disabled_at, Some(now));
+        // 2回目は日時を更新しない
+        let disabled = disabled.disable(now + Duration::days(1));
+        assert_eq!(disabled.disabled_at, Some(now));
+
+        let restored = disabled
+            .clone()
+            .restore(now + Duration::days(FIGURE_RECORD_RETENTION_DAYS - 1))
+            .unwrap();
+        assert!(!restored.disabled);
+        assert_eq!(restored.disabled_at, None);
+
+        assert!(disabled
+            .restore(now + Duration::days(FIGURE_RECORD_RETENTION_DAYS))
+            .is_none());
+    }
+}
@@ -1,4 +1,5 @@
 use super::{FileId, MimeType};
+use crate::entities::{FigureRecordId, ThumbnailSize, UserId};
 use derive_more::Into;
 use ulid::Ulid;
 
@@ -11,6 +12,34 @@ impl FileKey {
         Self(key)
     }
 
+    // figure_recordのfigureは変更されないのでidとサイズだけで一意になる
+    pub fn figure_record_thumbnail(
+        user_id: &UserId,
+        id: FigureRecordId,
+        size: ThumbnailSize,
+    ) -> Self {
+        let key = format!(
+            "{}{}.png",
+            Self::figure_record_thumbnail_prefix(user_id, id),
+            u32::from(size)
+        );
+        Self(key)
+    }
+
+    // あるfigure_recordの全サイズのサムネイルに共通するプレフィックス
+    pub fn figure_record_thumbnail_prefix(user_id: &UserId, id: FigureRecordId) -> String {
+        format!(
+            "{}figure_records/{}/",
+            Self::user_thumbnails_prefix(user_id),
+            Ulid::from(id)
+        )
+    }
+
+    // あるユーザーの全てのサムネイルに共通するプレフィックス
+    pub fn user_thumbnails_prefix(user_id: &UserId) -> String {
+        format!("thumbnails/{}/", String::from(user_id.clone()))
+    }
+
     // for repository
     pub fn from_unchecked(key: String) -> Self {
         Self(key)
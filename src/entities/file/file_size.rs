@@ -15,6 +15,15 @@ pub enum FileSizeTryFromError {
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Into)]
 pub struct FileSize(i32);
 
+impl FileSize {
+    // サーバー側で生成したファイル(エクスポートなど)用。アップロードの上限は適用しない
+    pub fn from_generated(value: u64) -> Result<Self, FileSizeTryFromError> {
+        i32::try_from(value)
+            .map(Self)
+            .map_err(|_| FileSizeTryFromError::TooLarge)
+    }
+}
+
 impl TryFrom<i32> for FileSize {
     type Error = FileSizeTryFromError;
 
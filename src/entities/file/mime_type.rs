@@ -14,6 +14,28 @@ impl MimeType {
     pub fn extension(&self) -> &str {
         &self.extension
     }
+
+    // エクスポートでサーバーが生成するzip。ユーザーはアップロードできない
+    pub fn zip() -> Self {
+        Self {
+            value: "application/zip".to_string(),
+            extension: "zip".to_string(),
+        }
+    }
+
+    // DBに保存されたもの。アップロードできる画像に加えてサーバーが生成する種類も受け付ける
+    pub fn from_stored(value: String) -> Result<Self, MimeTypeTryFromError> {
+        let zip = Self::zip();
+        if value == zip.value {
+            Ok(zip)
+        } else {
+            Self::try_from(value)
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.value.starts_with("image/")
+    }
 }
 
 #[derive(Error, Debug, Clone)]
@@ -22,6 +44,7 @@ pub enum MimeTypeTryFromError {
     Unsupported(String),
 }
 
+// ユーザーがアップロードできる画像のみ
 impl TryFrom<String> for MimeType {
     type Error = MimeTypeTryFromError;
 
@@ -47,3 +70,22 @@ impl TryFrom<String> for MimeType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mime_type_try_from() {
+        assert!(MimeType::try_from("image/png".to_string()).is_ok());
+        assert!(MimeType::try_from("application/zip".to_string()).is_err());
+
+        let zip = MimeType::from_stored("application/zip".to_string()).unwrap();
+        assert_eq!(zip.extension(), "zip");
+        assert!(!zip.is_image());
+        assert!(MimeType::from_stored("image/webp".to_string())
+            .unwrap()
+            .is_image());
+        assert!(MimeType::from_stored("text/plain".to_string()).is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use super::{GenerationJobId, GenerationJobKind, GenerationJobStatus};
+use crate::entities::{FileId, UserId, Version};
+use chrono::{DateTime, Duration, Utc};
+
+// 実行中のまま更新されずにこの時間が経ったジョブはワーカーが落ちたとみなし、リトライで引き継ぐ
+const GENERATION_JOB_RUNNING_TIMEOUT_MINUTES: i64 = 30;
+
+#[derive(Clone, Debug)]
+pub struct GenerationJob {
+    pub id: GenerationJobId,
+    pub user_id: UserId,
+    pub kind: GenerationJobKind,
+    pub status: GenerationJobStatus,
+    // status=Failedの時のみ
+    pub error_message: Option<String>,
+    // status=Succeededの時のみ
+    pub result_file_id: Option<FileId>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: Version,
+}
+
+impl GenerationJob {
+    pub fn new(
+        id: GenerationJobId,
+        user_id: UserId,
+        kind: GenerationJobKind,
+        now: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            kind,
+            status: GenerationJobStatus::Queued,
+            error_message: None,
+            result_file_id: None,
+            created_at: now,
+            updated_at: now,
+            version: Version::none(),
+        }
+    }
+
+    // キュー待ちか、実行中のままタイムアウトしたジョブなら実行を開始できる
+    pub fn startable(&self, now: DateTime<Utc>) -> bool {
+        match self.status {
+            GenerationJobStatus::Queued => true,
+            GenerationJobStatus::Running => {
+                self.updated_at + Duration::minutes(GENERATION_JOB_RUNNING_TIMEOUT_MINUTES) <= now
+            }
+            _ => false,
+        }
+    }
+
+    pub fn running(mut self) -> Self {
+        self.status = GenerationJobStatus::Running;
+        self
+    }
+
+    pub fn succeeded(mut self, result_file_id: FileId) -> Self {
+        self.status = GenerationJobStatus::Succeeded;
+        self.result_file_id = Some(result_file_id);
+        self
+    }
+
+    pub fn failed(mut self, error_message: String) -> Self {
+        self.status = GenerationJobStatus::Failed;
+        self.error_message = Some(error_message);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ulid::Ulid;
+
+    #[test]
+    fn test_startable() {
+        let now = Utc::now();
+        let job = GenerationJob::new(
+            GenerationJobId::from(Ulid::from_datetime(now)),
+            UserId::from("user".to_string()),
+            GenerationJobKind::DataExport,
+            now,
+        );
+        assert!(job.startable(now));
+
+        let job = job.running();
+        assert!(!job.startable(now));
+        assert!(!job.startable(now + Duration::minutes(GENERATION_JOB_RUNNING_TIMEOUT_MINUTES - 1)));
+        assert!(job.startable(now + Duration::minutes(GENERATION_JOB_RUNNING_TIMEOUT_MINUTES)));
+
+        let job = job.failed("error".to_string());
+        assert!(!job.startable(now + Duration::days(1)));
+    }
+}
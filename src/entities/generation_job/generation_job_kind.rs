@@ -0,0 +1,12 @@
+use crate::entities::{DocumentText, GenerateTemplateId};
+
+#[derive(Clone, Debug)]
+pub enum GenerationJobKind {
+    // GenerateTemplateにtextを書いた画像
+    Document {
+        generate_template_id: GenerateTemplateId,
+        text: DocumentText,
+    },
+    // ユーザーの全データのアーカイブ
+    DataExport,
+}
@@ -0,0 +1,40 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum GenerationJobStatusTryFromError {
+    #[error("Invalid generation job status value: {0}")]
+    InvalidValue(i32),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Copy)]
+pub enum GenerationJobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl TryFrom<i32> for GenerationJobStatus {
+    type Error = GenerationJobStatusTryFromError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Queued),
+            1 => Ok(Self::Running),
+            2 => Ok(Self::Succeeded),
+            3 => Ok(Self::Failed),
+            _ => Err(GenerationJobStatusTryFromError::InvalidValue(value)),
+        }
+    }
+}
+
+impl From<GenerationJobStatus> for i32 {
+    fn from(value: GenerationJobStatus) -> Self {
+        match value {
+            GenerationJobStatus::Queued => 0,
+            GenerationJobStatus::Running => 1,
+            GenerationJobStatus::Succeeded => 2,
+            GenerationJobStatus::Failed => 3,
+        }
+    }
+}
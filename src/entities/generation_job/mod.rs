@@ -0,0 +1,9 @@
+mod generation_job;
+mod generation_job_id;
+mod generation_job_kind;
+mod generation_job_status;
+
+pub use generation_job::GenerationJob;
+pub use generation_job_id::GenerationJobId;
+pub use generation_job_kind::GenerationJobKind;
+pub use generation_job_status::{GenerationJobStatus, GenerationJobStatusTryFromError};
@@ -6,7 +6,7 @@ pub enum LimitKind {
     Last,
 }
 
-const MAX_LIMIT: i32 = 100;
+pub const MAX_LIMIT: i32 = 100;
 #[derive(Error, Debug, Clone)]
 
 pub enum CreateLimitError {
@@ -1,31 +1,47 @@
+mod average_figure;
 mod character;
 mod character_config;
 mod character_config_seed;
+mod document_text;
 mod figure;
 mod figure_record;
 mod file;
 mod generate_template;
+mod generation_job;
 mod limit;
+mod persisted_query;
 mod random_level;
 mod ratio;
+mod reference_figure;
 mod shared_proportion;
 mod stroke_count;
+mod stroke_deviation;
+mod thumbnail_size;
 mod user_config;
 mod user_id;
 mod version;
 
+pub use average_figure::average_figure;
 pub use character::Character;
 pub use character_config::CharacterConfig;
 pub use character_config_seed::CharacterConfigSeed;
-pub use figure::Figure;
-pub use figure_record::{FigureRecord, FigureRecordId};
+pub use document_text::{DocumentText, DocumentTextTryFromError};
+pub use figure::{BoundingBox, Figure, FigurePoint, FigureValidationError};
+pub use figure_record::{FigureRecord, FigureRecordId, FIGURE_RECORD_RETENTION_DAYS};
 pub use file::*;
 pub use generate_template::*;
-pub use limit::{Limit, LimitKind};
+pub use generation_job::*;
+pub use limit::{Limit, LimitKind, MAX_LIMIT};
+pub use persisted_query::{PersistedQuery, PersistedQueryHash, PersistedQueryHashTryFromError};
 pub use random_level::RandomLevel;
 pub use ratio::Ratio;
+pub use reference_figure::{
+    ReferenceFigure, ReferenceFigureVariant, ReferenceFigureVariantTryFromError,
+};
 pub use shared_proportion::SharedProportion;
 pub use stroke_count::StrokeCount;
+pub use stroke_deviation::StrokeDeviation;
+pub use thumbnail_size::ThumbnailSize;
 pub use user_config::UserConfig;
 pub use user_id::UserId;
 pub use version::Version;
@@ -0,0 +1,66 @@
+use thiserror::Error;
+
+// クエリ文字列のSHA-256の16進数表現(小文字)
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PersistedQueryHash(String);
+
+const PERSISTED_QUERY_HASH_LENGTH: usize = 64;
+
+impl PersistedQueryHash {
+    pub fn value(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum PersistedQueryHashTryFromError {
+    #[error("Persisted query hash must be a lowercase hex encoded sha256")]
+    Invalid,
+}
+
+impl TryFrom<String> for PersistedQueryHash {
+    type Error = PersistedQueryHashTryFromError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value.len() == PERSISTED_QUERY_HASH_LENGTH
+            && value
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        {
+            Ok(Self(value))
+        } else {
+            Err(PersistedQueryHashTryFromError::Invalid)
+        }
+    }
+}
+
+impl From<PersistedQueryHash> for String {
+    fn from(value: PersistedQueryHash) -> Self {
+        value.0
+    }
+}
+
+// 実行を許可するクエリ
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PersistedQuery {
+    pub hash: PersistedQueryHash,
+    pub query: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persisted_query_hash_try_from() {
+        let hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855".to_string();
+        assert_eq!(
+            PersistedQueryHash::try_from(hash.clone())
+                .map(String::from)
+                .ok(),
+            Some(hash.clone())
+        );
+        assert!(PersistedQueryHash::try_from(hash.to_uppercase()).is_err());
+        assert!(PersistedQueryHash::try_from(hash[1..].to_string()).is_err());
+    }
+}
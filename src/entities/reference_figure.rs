@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use thiserror::Error;
+
+use super::{Character, Figure, StrokeCount, UserId, Version};
+
+// 書き順・筆の向きの手本。同じ文字・画数でも字形の違うものをvariantで区別して複数持てる
+#[derive(Clone, Debug)]
+pub struct ReferenceFigure {
+    pub user_id: UserId,
+    pub character: Character,
+    pub stroke_count: StrokeCount,
+    pub variant: ReferenceFigureVariant,
+    // 画数はstroke_countと一致する
+    pub figure: Figure,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub version: Version,
+}
+
+impl ReferenceFigure {
+    pub fn new(
+        user_id: UserId,
+        character: Character,
+        stroke_count: StrokeCount,
+        variant: ReferenceFigureVariant,
+        figure: Figure,
+    ) -> ReferenceFigure {
+        ReferenceFigure {
+            user_id,
+            character,
+            stroke_count,
+            variant,
+            figure,
+            updated_at: None,
+            version: Version::none(),
+        }
+    }
+
+    pub fn with_figure(mut self, figure: Figure) -> Self {
+        self.figure = figure;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct ReferenceFigureVariant(String);
+
+const MAX_REFERENCE_FIGURE_VARIANT_LENGTH: usize = 32;
+
+impl ReferenceFigureVariant {
+    pub fn value(&self) -> &str {
+        &self.0
+    }
+}
+
+// 字形を指定しない場合
+impl Default for ReferenceFigureVariant {
+    fn default() -> Self {
+        Self("default".to_string())
+    }
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum ReferenceFigureVariantTryFromError {
+    #[error(
+        "Variant must be less than or equal to {} characters",
+        MAX_REFERENCE_FIGURE_VARIANT_LENGTH
+    )]
+    TooLong,
+    #[error("Variant must not be empty")]
+    Empty,
+}
+
+impl TryFrom<String> for ReferenceFigureVariant {
+    type Error = ReferenceFigureVariantTryFromError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let length = value.chars().count();
+        if length > MAX_REFERENCE_FIGURE_VARIANT_LENGTH {
+            Err(ReferenceFigureVariantTryFromError::TooLong)
+        } else if length == 0 {
+            Err(ReferenceFigureVariantTryFromError::Empty)
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl From<ReferenceFigureVariant> for String {
+    fn from(value: ReferenceFigureVariant) -> Self {
+        value.0
+    }
+}
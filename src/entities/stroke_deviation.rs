@@ -0,0 +1,133 @@
+use super::{Figure, FigurePoint};
+
+// 比較する前に各ストロークをこの点数にリサンプリングする
+const DEVIATION_RESAMPLE_POINTS: usize = 32;
+
+/*
+参照figure(書き順・筆の向きの手本)との差
+座標は幅と高さが1になるよう正規化してから比べる
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrokeDeviation {
+    // 同じ番号のストローク同士の対応する点の距離の平均
+    pub distance: f64,
+    // 最も近い参照ストロークが別の番号であるストローク(書き順が違う)
+    pub misordered_strokes: Vec<usize>,
+    // 同じ番号の参照ストロークを逆向きに書いたほうが近いストローク
+    pub reversed_strokes: Vec<usize>,
+}
+
+impl StrokeDeviation {
+    // 画数が違う、点の無いストロークがあるなど比較できなければNone
+    pub fn compute(figure: &Figure, reference: &Figure) -> Option<StrokeDeviation> {
+        if figure.stroke_count() != reference.stroke_count() {
+            return None;
+        }
+
+        let figure = figure.normalize()?.resample(DEVIATION_RESAMPLE_POINTS);
+        let reference = reference.normalize()?.resample(DEVIATION_RESAMPLE_POINTS);
+        let strokes = figure.strokes().collect::<Vec<_>>();
+        let reference_strokes = reference.strokes().collect::<Vec<_>>();
+        if strokes
+            .iter()
+            .chain(reference_strokes.iter())
+            .any(|points| points.is_empty())
+        {
+            return None;
+        }
+
+        let mut distance = 0.0;
+        let mut misordered_strokes = Vec::new();
+        let mut reversed_strokes = Vec::new();
+        for (i, points) in strokes.iter().enumerate() {
+            let forward = mean_distance(points.iter(), reference_strokes[i].iter());
+            let backward = mean_distance(points.iter().rev(), reference_strokes[i].iter());
+            distance += forward;
+            if backward < forward {
+                reversed_strokes.push(i);
+            }
+
+            let nearest = reference_strokes
+                .iter()
+                .map(|reference_points| {
+                    f64::min(
+                        mean_distance(points.iter(), reference_points.iter()),
+                        mean_distance(points.iter().rev(), reference_points.iter()),
+                    )
+                })
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(j, _)| j);
+            if nearest != Some(i) {
+                misordered_strokes.push(i);
+            }
+        }
+
+        Some(StrokeDeviation {
+            distance: distance / strokes.len().max(1) as f64,
+            misordered_strokes,
+            reversed_strokes,
+        })
+    }
+}
+
+fn mean_distance<'a>(
+    a: impl Iterator<Item = &'a FigurePoint>,
+    b: impl Iterator<Item = &'a FigurePoint>,
+) -> f64 {
+    let (sum, count) = a.zip(b).fold((0.0, 0), |(sum, count), (a, b)| {
+        (
+            sum + ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt(),
+            count + 1,
+        )
+    });
+    sum / f64::from(count.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn figure(strokes: &[&[(f64, f64)]]) -> Figure {
+        let strokes = strokes
+            .iter()
+            .map(|points| {
+                let points = points
+                    .iter()
+                    .map(|(x, y)| format!(r#"{{"x":{},"y":{},"z":0.5}}"#, x, y))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                format!(r#"{{"points":[{}]}}"#, points)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        Figure::from_json(&format!(
+            r#"{{"strokes":[{}],"width":100,"height":100}}"#,
+            strokes
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_compute() {
+        let reference = figure(&[&[(10.0, 20.0), (90.0, 20.0)], &[(50.0, 0.0), (50.0, 100.0)]]);
+
+        let same = StrokeDeviation::compute(&reference, &reference).unwrap();
+        assert!(same.distance < 1e-9);
+        assert!(same.misordered_strokes.is_empty());
+        assert!(same.reversed_strokes.is_empty());
+
+        let reversed = figure(&[&[(90.0, 20.0), (10.0, 20.0)], &[(50.0, 0.0), (50.0, 100.0)]]);
+        let deviation = StrokeDeviation::compute(&reversed, &reference).unwrap();
+        assert!(deviation.distance > 0.1);
+        assert!(deviation.misordered_strokes.is_empty());
+        assert_eq!(deviation.reversed_strokes, vec![0]);
+
+        let misordered = figure(&[&[(50.0, 0.0), (50.0, 100.0)], &[(10.0, 20.0), (90.0, 20.0)]]);
+        let deviation = StrokeDeviation::compute(&misordered, &reference).unwrap();
+        assert_eq!(deviation.misordered_strokes, vec![0, 1]);
+
+        let other_stroke_count = figure(&[&[(10.0, 20.0), (90.0, 20.0)]]);
+        assert!(StrokeDeviation::compute(&other_stroke_count, &reference).is_none());
+    }
+}
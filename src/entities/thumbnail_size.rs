@@ -0,0 +1,34 @@
+use derive_more::Into;
+use thiserror::Error;
+
+// サムネイルの一辺のピクセル数
+#[derive(Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Into, Copy)]
+pub struct ThumbnailSize(u32);
+
+const MAX_THUMBNAIL_SIZE: i32 = 512;
+const MIN_THUMBNAIL_SIZE: i32 = 1;
+
+#[derive(Error, Debug, Clone)]
+pub enum ThumbnailSizeTryFromError {
+    #[error("Thumbnail size must be less than or equal to {}", MAX_THUMBNAIL_SIZE)]
+    TooLarge,
+    #[error(
+        "Thumbnail size must be greater than or equal to {}",
+        MIN_THUMBNAIL_SIZE
+    )]
+    TooSmall,
+}
+
+impl TryFrom<i32> for ThumbnailSize {
+    type Error = ThumbnailSizeTryFromError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        if value > MAX_THUMBNAIL_SIZE {
+            Err(ThumbnailSizeTryFromError::TooLarge)
+        } else if value < MIN_THUMBNAIL_SIZE {
+            Err(ThumbnailSizeTryFromError::TooSmall)
+        } else {
+            Ok(Self(value as u32))
+        }
+    }
+}
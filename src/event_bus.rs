@@ -0,0 +1,397 @@
+use std::str::FromStr;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use ulid::Ulid;
+
+use crate::entities;
+
+/*
+サブスクリプション用のイベント
+ワーカーとWebサーバーが別プロセスでも届くようにPostgresのLISTEN/NOTIFYで配送する
+NOTIFYのペイロードには上限(8000バイト)があるのでIDのみ送り、受け取った側で読み込む
+*/
+
+const CHANNEL: &str = "average_character_cloud_events";
+const BROADCAST_CAPACITY: usize = 1024;
+const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    // 一度に作成したものをまとめて1回で送る。createFigureRecordsの上限(100件)でもペイロードの上限に収まる
+    FigureRecordsCreated {
+        user_id: entities::UserId,
+        figure_records: Vec<(entities::FigureRecordId, entities::Character)>,
+    },
+    CharacterConfigSeedsUpdated {
+        updated_at: DateTime<Utc>,
+    },
+    GenerationJobUpdated {
+        user_id: entities::UserId,
+        generation_job_id: entities::GenerationJobId,
+    },
+    // ログアウトやアカウント削除でセッションが破棄された時。そのユーザーのWebSocket接続を切る
+    UserSessionEnded {
+        user_id: entities::UserId,
+    },
+}
+
+impl Event {
+    // user_idのFigureRecordsCreatedのうち、characterが指定されていればそれに一致するもの
+    pub fn created_figure_record_ids(
+        &self,
+        user_id: &entities::UserId,
+        character: Option<&entities::Character>,
+    ) -> Vec<entities::FigureRecordId> {
+        match self {
+            Event::FigureRecordsCreated {
+                user_id: event_user_id,
+                figure_records,
+            } if event_user_id == user_id => figure_records
+                .iter()
+                .filter(|(_, event_character)| {
+                    character.is_none_or(|character| character == event_character)
+                })
+                .map(|(figure_record_id, _)| *figure_record_id)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn seeds_updated_at(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Event::CharacterConfigSeedsUpdated { updated_at } => Some(*updated_at),
+            _ => None,
+        }
+    }
+
+    // user_idのGenerationJobUpdatedのうち、idが指定されていればそれに一致するもの
+    pub fn updated_generation_job_id(
+        &self,
+        user_id: &entities::UserId,
+        id: Option<entities::GenerationJobId>,
+    ) -> Option<entities::GenerationJobId> {
+        match self {
+            Event::GenerationJobUpdated {
+                user_id: event_user_id,
+                generation_job_id,
+            } if event_user_id == user_id && id.is_none_or(|id| id == *generation_job_id) => {
+                Some(*generation_job_id)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn ends_session_of(&self, user_id: &entities::UserId) -> bool {
+        matches!(self, Event::UserSessionEnded { user_id: event_user_id } if event_user_id == user_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum EventPayload {
+    FigureRecordsCreated {
+        user_id: String,
+        // (id, character)
+        figure_records: Vec<(String, String)>,
+    },
+    CharacterConfigSeedsUpdated {
+        updated_at: DateTime<Utc>,
+    },
+    GenerationJobUpdated {
+        user_id: String,
+        generation_job_id: String,
+    },
+    UserSessionEnded {
+        user_id: String,
+    },
+}
+
+impl From<&Event> for EventPayload {
+    fn from(value: &Event) -> Self {
+        match value {
+            Event::FigureRecordsCreated {
+                user_id,
+                figure_records,
+            } => EventPayload::FigureRecordsCreated {
+                user_id: String::from(user_id.clone()),
+                figure_records: figure_records
+                    .iter()
+                    .map(|(figure_record_id, character)| {
+                        (
+                            Ulid::from(*figure_record_id).to_string(),
+                            String::from(character.clone()),
+                        )
+                    })
+                    .collect(),
+            },
+            Event::CharacterConfigSeedsUpdated { updated_at } => {
+                EventPayload::CharacterConfigSeedsUpdated {
+                    updated_at: *updated_at,
+                }
+            }
+            Event::GenerationJobUpdated {
+                user_id,
+                generation_job_id,
+            } => EventPayload::GenerationJobUpdated {
+                user_id: String::from(user_id.clone()),
+                generation_job_id: Ulid::from(*generation_job_id).to_string(),
+            },
+            Event::UserSessionEnded { user_id } => EventPayload::UserSessionEnded {
+                user_id: String::from(user_id.clone()),
+            },
+        }
+    }
+}
+
+impl TryFrom<EventPayload> for Event {
+    type Error = anyhow::Error;
+
+    fn try_from(value: EventPayload) -> Result<Self, Self::Error> {
+        Ok(match value {
+            EventPayload::FigureRecordsCreated {
+                user_id,
+                figure_records,
+            } => Event::FigureRecordsCreated {
+                user_id: entities::UserId::from(user_id),
+                figure_records: figure_records
+                    .into_iter()
+                    .map(|(figure_record_id, character)| {
+                        anyhow::Ok((
+                            entities::FigureRecordId::from(
+                                Ulid::from_str(&figure_record_id).context("ulid decode error")?,
+                            ),
+                            entities::Character::try_from(character.as_str())?,
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            },
+            EventPayload::CharacterConfigSeedsUpdated { updated_at } => {
+                Event::CharacterConfigSeedsUpdated { updated_at }
+            }
+            EventPayload::GenerationJobUpdated {
+                user_id,
+                generation_job_id,
+            } => Event::GenerationJobUpdated {
+                user_id: entities::UserId::from(user_id),
+                generation_job_id: entities::GenerationJobId::from(
+                    Ulid::from_str(&generation_job_id).context("ulid decode error")?,
+                ),
+            },
+            EventPayload::UserSessionEnded { user_id } => Event::UserSessionEnded {
+                user_id: entities::UserId::from(user_id),
+            },
+        })
+    }
+}
+
+pub async fn publish(pool: &PgPool, event: &Event) -> anyhow::Result<()> {
+    let payload = serde_json::to_string(&EventPayload::from(event))?;
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(CHANNEL)
+        .bind(payload)
+        .execute(pool)
+        .await
+        .context("notify event")?;
+    Ok(())
+}
+
+// イベントの配信はサブスクリプションのおまけなので、失敗しても元の処理は失敗させない
+pub async fn publish_or_log(pool: &PgPool, event: &Event) {
+    if let Err(e) = publish(pool, event).await {
+        tracing::warn!("publish event error: {:?}", e);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    // LISTENを開始し、受け取ったイベントをプロセス内の購読者に配る
+    pub async fn listen(pool: &PgPool) -> anyhow::Result<Self> {
+        let mut listener = PgListener::connect_with(pool)
+            .await
+            .context("connect listener")?;
+        listener.listen(CHANNEL).await.context("listen events")?;
+
+        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+        let event_sender = sender.clone();
+        tokio::spawn(async move {
+            loop {
+                // 接続が切れた場合はPgListenerが再接続する
+                let notification = match listener.recv().await {
+                    Ok(notification) => notification,
+                    Err(e) => {
+                        tracing::error!("receive event error: {:?}", e);
+                        tokio::time::sleep(RETRY_INTERVAL).await;
+                        continue;
+                    }
+                };
+                let event = serde_json::from_str::<EventPayload>(notification.payload())
+                    .context("parse event")
+                    .and_then(Event::try_from);
+                match event {
+                    // 購読者がいない場合のエラーは無視する
+                    Ok(event) => {
+                        let _ = event_sender.send(event);
+                    }
+                    Err(e) => tracing::warn!("invalid event: {:?}", e),
+                }
+            }
+        });
+
+        Ok(Self { sender })
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::FigureRecordsCreated {
+                user_id: entities::UserId::from("user".to_string()),
+                figure_records: vec![
+                    (
+                        entities::FigureRecordId::from(Ulid::new()),
+                        entities::Character::from('あ'),
+                    ),
+                    (
+                        entities::FigureRecordId::from(Ulid::new()),
+                        entities::Character::from('い'),
+                    ),
+                ],
+            },
+            Event::CharacterConfigSeedsUpdated {
+                updated_at: DateTime::<Utc>::from_timestamp_micros(1_700_000_000_123_456).unwrap(),
+            },
+            Event::GenerationJobUpdated {
+                user_id: entities::UserId::from("user".to_string()),
+                generation_job_id: entities::GenerationJobId::from(Ulid::new()),
+            },
+            Event::UserSessionEnded {
+                user_id: entities::UserId::from("user".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_event_payload() {
+        for event in events() {
+            let payload = serde_json::to_string(&EventPayload::from(&event)).unwrap();
+            let decoded = serde_json::from_str::<EventPayload>(&payload)
+                .map_err(anyhow::Error::from)
+                .and_then(Event::try_from)
+                .unwrap();
+            assert_eq!(decoded, event);
+        }
+
+        assert!(serde_json::from_str::<EventPayload>(
+            r#"{"type":"figure_records_created","user_id":"user","figure_records":[["invalid","あ"]]}"#
+        )
+        .map_err(anyhow::Error::from)
+        .and_then(Event::try_from)
+        .is_err());
+    }
+
+    #[test]
+    fn test_event_filters() {
+        let user_id = entities::UserId::from("user".to_string());
+        let other_user_id = entities::UserId::from("other_user".to_string());
+        let figure_record_id = entities::FigureRecordId::from(Ulid::new());
+        let generation_job_id = entities::GenerationJobId::from(Ulid::new());
+
+        let other_figure_record_id = entities::FigureRecordId::from(Ulid::new());
+        let created = Event::FigureRecordsCreated {
+            user_id: user_id.clone(),
+            figure_records: vec![
+                (figure_record_id, entities::Character::from('あ')),
+                (other_figure_record_id, entities::Character::from('い')),
+            ],
+        };
+        assert_eq!(
+            created.created_figure_record_ids(&user_id, None),
+            vec![figure_record_id, other_figure_record_id]
+        );
+        assert_eq!(
+            created.created_figure_record_ids(&user_id, Some(&entities::Character::from('あ'))),
+            vec![figure_record_id]
+        );
+        assert_eq!(
+            created.created_figure_record_ids(&user_id, Some(&entities::Character::from('う'))),
+            vec![]
+        );
+        assert_eq!(
+            created.created_figure_record_ids(&other_user_id, None),
+            vec![]
+        );
+        assert_eq!(created.seeds_updated_at(), None);
+
+        let updated = Event::GenerationJobUpdated {
+            user_id: user_id.clone(),
+            generation_job_id,
+        };
+        assert_eq!(
+            updated.updated_generation_job_id(&user_id, None),
+            Some(generation_job_id)
+        );
+        assert_eq!(
+            updated.updated_generation_job_id(&user_id, Some(generation_job_id)),
+            Some(generation_job_id)
+        );
+        assert_eq!(
+            updated.updated_generation_job_id(
+                &user_id,
+                Some(entities::GenerationJobId::from(Ulid::new()))
+            ),
+            None
+        );
+        assert_eq!(
+            updated.updated_generation_job_id(&other_user_id, None),
+            None
+        );
+        assert_eq!(updated.created_figure_record_ids(&user_id, None), vec![]);
+
+        let seeds_updated_at = Utc::now();
+        assert_eq!(
+            Event::CharacterConfigSeedsUpdated {
+                updated_at: seeds_updated_at
+            }
+            .seeds_updated_at(),
+            Some(seeds_updated_at)
+        );
+
+        let ended = Event::UserSessionEnded {
+            user_id: user_id.clone(),
+        };
+        assert!(ended.ends_session_of(&user_id));
+        assert!(!ended.ends_session_of(&other_user_id));
+        assert!(!updated.ends_session_of(&user_id));
+    }
+
+    #[sqlx::test]
+    async fn test_event_bus(pool: PgPool) {
+        let event_bus = EventBus::listen(&pool).await.unwrap();
+        let mut receiver = event_bus.subscribe();
+
+        for event in events() {
+            publish(&pool, &event).await.unwrap();
+            let received = tokio::time::timeout(std::time::Duration::from_secs(5), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(received, event);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::io::BufRead;
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{adapters::FigureRecordsRepositoryImpl, entities, ports::FigureRecordsRepository};
+
+// JSONLの1行
+#[derive(Debug, Deserialize)]
+struct FigureLine {
+    character: String,
+    figure: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    // (行番号(1始まり), エラー)
+    pub errors: Vec<(usize, String)>,
+}
+
+pub fn parse_line(line: &str) -> Result<(entities::Character, entities::Figure), String> {
+    let line = serde_json::from_str::<FigureLine>(line).map_err(|e| e.to_string())?;
+    let character =
+        entities::Character::try_from(line.character.as_str()).map_err(|e| e.to_string())?;
+    let figure = entities::Figure::from_json_ast(line.figure)
+        .ok_or_else(|| "figure must be valid json".to_string())?;
+    figure.validate().map_err(|e| e.to_string())?;
+
+    Ok((character, figure))
+}
+
+/*
+1行に1つ {"character": "あ", "figure": {...}} のJSONLを読み込みfigure_recordsを作成する
+batch_size行ごとに1つのトランザクションで作成し、失敗したバッチは全ての行をエラーとして報告する
+空行は無視する
+*/
+pub async fn import_figures(
+    pool: PgPool,
+    user_id: entities::UserId,
+    now: DateTime<Utc>,
+    reader: impl BufRead,
+    batch_size: usize,
+) -> anyhow::Result<ImportReport> {
+    let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool);
+    let mut report = ImportReport::default();
+    let mut batch = Vec::new();
+    // 全て同じnowで作成するので、バッチをまたいでもIDが行の順になるよう同じgeneratorを使う
+    let mut generator = ulid::Generator::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.context("read line")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match parse_line(&line) {
+            Ok(figure) => batch.push((line_number, figure)),
+            Err(e) => report.errors.push((line_number, e)),
+        }
+
+        if batch.len() >= batch_size {
+            insert_batch(
+                &mut figure_records_repository,
+                &user_id,
+                now,
+                &mut generator,
+                std::mem::take(&mut batch),
+                &mut report,
+            )
+            .await;
+        }
+    }
+    if !batch.is_empty() {
+        insert_batch(
+            &mut figure_records_repository,
+            &user_id,
+            now,
+            &mut generator,
+            batch,
+            &mut report,
+        )
+        .await;
+    }
+
+    report.errors.sort_by_key(|(line_number, _)| *line_number);
+    Ok(report)
+}
+
+async fn insert_batch(
+    figure_records_repository: &mut FigureRecordsRepositoryImpl<PgPool>,
+    user_id: &entities::UserId,
+    now: DateTime<Utc>,
+    generator: &mut ulid::Generator,
+    batch: Vec<(usize, (entities::Character, entities::Figure))>,
+    report: &mut ImportReport,
+) {
+    let (line_numbers, figures): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+    match figure_records_repository
+        .create_many(user_id.clone(), now, generator, figures)
+        .await
+    {
+        Ok(records) => report.imported += records.len(),
+        Err(e) => report.errors.extend(
+            line_numbers
+                .into_iter()
+                .map(|line_number| (line_number, format!("batch insert failed: {:#}", e))),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let (character, figure) = parse_line(
+            r#"{"character":"あ","figure":{"strokes":[{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]}],"width":1,"height":1}}"#,
+        )
+        .unwrap();
+        assert_eq!(character, entities::Character::from('あ'));
+        assert_eq!(i32::from(figure.stroke_count()), 1);
+
+        assert!(parse_line("{").is_err());
+        assert!(
+            parse_line(r#"{"character":"あい","figure":{"strokes":[],"width":1,"height":1}}"#)
+                .is_err()
+        );
+        assert!(parse_line(
+            r#"{"character":"あ","figure":{"strokes":[{"points":[{"x":0,"y":0,"z":1}]}],"width":1,"height":1}}"#
+        )
+        .is_err());
+    }
+}
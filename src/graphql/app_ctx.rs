@@ -1,8 +1,12 @@
+use std::sync::atomic::AtomicBool;
+
 use chrono::{DateTime, Utc};
 
 use sqlx::PgPool;
 
-use crate::{app_config::AppConfig, entities};
+use crate::{
+    app_config::AppConfig, entities, event_bus::EventBus, faktory::FaktoryConnectionManager,
+};
 
 pub use super::loaders::Loaders;
 
@@ -13,6 +17,10 @@ pub struct AppCtx {
     pub loaders: Loaders,
     pub config: AppConfig,
     pub s3_client: aws_sdk_s3::Client,
+    pub faktory_pool: r2d2::Pool<FaktoryConnectionManager>,
+    pub event_bus: EventBus,
+    // リゾルバからはSessionを触れないので、実行後にハンドラがこれを見てセッションを破棄する
+    pub clear_session: AtomicBool,
 }
 
 impl juniper::Context for AppCtx {}
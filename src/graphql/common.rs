@@ -1,33 +1,57 @@
 use std::str::FromStr;
 
-use juniper::GraphQLObject;
+use chrono::{DateTime, Utc};
 use juniper::ID;
+use juniper::{GraphQLEnum, GraphQLObject};
 
 use thiserror::Error;
 use ulid::Ulid;
 
-use crate::entities;
+use crate::{entities, ports};
 use anyhow::anyhow;
 
+/*
+クライアントに見せるエラー
+codeで分岐できるようにし、入力値の問題であればfieldに引数からのパスを入れる
+*/
 #[derive(Debug, Error)]
 #[error("{source}")]
 pub struct GraphqlUserError {
     #[source]
     pub source: anyhow::Error,
+    pub code: GraphqlErrorCode,
+    pub field: Option<Vec<String>>,
 }
 
-impl From<anyhow::Error> for GraphqlUserError {
-    fn from(source: anyhow::Error) -> Self {
-        Self { source }
-    }
-}
-
-impl From<&str> for GraphqlUserError {
-    fn from(source: &str) -> Self {
+impl GraphqlUserError {
+    pub fn new(code: GraphqlErrorCode, message: &str) -> Self {
         Self {
-            source: anyhow!("{}", source),
+            source: anyhow!("{}", message),
+            code,
+            field: None,
         }
     }
+
+    pub fn unauthenticated() -> Self {
+        Self::new(GraphqlErrorCode::Unauthenticated, "Authentication required")
+    }
+
+    pub fn not_found(message: &str) -> Self {
+        Self::new(GraphqlErrorCode::NotFound, message)
+    }
+
+    pub fn validation(field: &[&str], message: &str) -> Self {
+        Self::new(GraphqlErrorCode::Validation, message).with_field(field)
+    }
+
+    pub fn quota_exceeded(message: &str) -> Self {
+        Self::new(GraphqlErrorCode::QuotaExceeded, message)
+    }
+
+    pub fn with_field(mut self, field: &[&str]) -> Self {
+        self.field = Some(field.iter().map(|field| field.to_string()).collect());
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -35,14 +59,44 @@ pub struct ApiError(pub anyhow::Error);
 
 impl<S: juniper::ScalarValue> juniper::IntoFieldError<S> for ApiError {
     fn into_field_error(self) -> juniper::FieldError<S> {
-        match self.0.downcast_ref::<GraphqlUserError>() {
-            Some(err) => err.source.to_string().into(),
-            None => {
-                tracing::error!("{:?}", self.0);
-                "Internal error".into()
-            }
+        if let Some(err) = self.0.downcast_ref::<GraphqlUserError>() {
+            return juniper::FieldError::new(
+                &err.source,
+                error_extensions(err.code, err.field.as_deref()),
+            );
+        }
+        if let Some(err) = self.0.downcast_ref::<ports::ConflictError>() {
+            return juniper::FieldError::new(
+                err,
+                error_extensions(GraphqlErrorCode::Conflict, None),
+            );
         }
+        tracing::error!("{:?}", self.0);
+        juniper::FieldError::new(
+            "Internal error",
+            error_extensions(GraphqlErrorCode::Internal, None),
+        )
+    }
+}
+
+fn error_extensions<S: juniper::ScalarValue>(
+    code: GraphqlErrorCode,
+    field: Option<&[String]>,
+) -> juniper::Value<S> {
+    let mut extensions = juniper::Object::with_capacity(2);
+    extensions.add_field("code", juniper::Value::scalar(code.as_str().to_string()));
+    if let Some(field) = field {
+        extensions.add_field(
+            "field",
+            juniper::Value::list(
+                field
+                    .iter()
+                    .map(|field| juniper::Value::scalar(field.clone()))
+                    .collect(),
+            ),
+        );
     }
+    juniper::Value::object(extensions)
 }
 
 impl<T: Into<anyhow::Error>> From<T> for ApiError {
@@ -58,27 +112,28 @@ pub fn encode_limit(
     match (first, last) {
         (Some(first), None) => {
             if first < 0 {
-                Err(GraphqlUserError::from(
+                Err(GraphqlUserError::validation(
+                    &["first"],
                     "first must be greater than or equal to 0",
                 ))
             } else {
                 entities::Limit::new(entities::LimitKind::First, first)
-                    .map_err(anyhow::Error::from)
-                    .map_err(GraphqlUserError::from)
+                    .map_err(|e| GraphqlUserError::validation(&["first"], &e.to_string()))
             }
         }
         (None, Some(last)) => {
             if last < 0 {
-                Err(GraphqlUserError::from(
+                Err(GraphqlUserError::validation(
+                    &["last"],
                     "last must be greater than or equal to 0",
                 ))
             } else {
                 entities::Limit::new(entities::LimitKind::Last, last)
-                    .map_err(anyhow::Error::from)
-                    .map_err(GraphqlUserError::from)
+                    .map_err(|e| GraphqlUserError::validation(&["last"], &e.to_string()))
             }
         }
-        _ => Err(GraphqlUserError::from(
+        _ => Err(GraphqlUserError::validation(
+            &["first"],
             "Must provide either first or last, not both",
         )),
     }
@@ -93,6 +148,7 @@ pub enum NodeId {
     CharacterConfigSeed(entities::Character, entities::StrokeCount),
     File(entities::FileId),
     GenerateTemplate(entities::GenerateTemplateId),
+    GenerationJob(entities::GenerationJobId),
 }
 
 impl NodeId {
@@ -129,6 +185,9 @@ impl NodeId {
                 "GenerateTemplate:{}",
                 Ulid::from(*id)
             ))),
+            NodeId::GenerationJob(id) => {
+                ID::new(base64::encode(format!("GenerationJob:{}", Ulid::from(*id))))
+            }
         }
     }
 
@@ -172,11 +231,119 @@ impl NodeId {
             "GenerateTemplate" => Ulid::from_str(id)
                 .ok()
                 .map(|id| NodeId::GenerateTemplate(entities::GenerateTemplateId::from(id))),
+            "GenerationJob" => Ulid::from_str(id)
+                .ok()
+                .map(|id| NodeId::GenerationJob(entities::GenerationJobId::from(id))),
             _ => None,
         })
     }
 }
 
+/*
+QueryRoot.characterConfigsのカーソル
+並び順ごとにsort_keyが異なるので並び順も含め、違う並び順のカーソルは無効にする
+*/
+pub fn encode_character_configs_cursor(
+    order: ports::CharacterConfigsOrder,
+    cursor: &ports::CharacterConfigsCursor,
+) -> String {
+    base64::encode(format!(
+        "CharacterConfigsCursor:{}:{}:{}:{}:{}",
+        character_configs_order_by_name(order.by),
+        i32::from(order.descending),
+        cursor.sort_key,
+        base64::encode(String::from(cursor.character.clone())),
+        i32::from(cursor.stroke_count),
+    ))
+}
+
+pub fn decode_character_configs_cursor(
+    order: ports::CharacterConfigsOrder,
+    cursor: &str,
+) -> Option<ports::CharacterConfigsCursor> {
+    // 並び順を指定しない場合は以前のカーソル(CharacterConfigのID)も受け付ける
+    if order == ports::CharacterConfigsOrder::default() {
+        if let Some(NodeId::CharacterConfig(_, character, stroke_count)) =
+            NodeId::from_id(&ID::new(cursor))
+        {
+            return Some(ports::CharacterConfigsCursor {
+                sort_key: 0,
+                character,
+                stroke_count,
+            });
+        }
+    }
+
+    let buf = base64::decode(cursor).ok()?;
+    let s = String::from_utf8(buf).ok()?;
+    let (kind, s) = s.split_once(':')?;
+    if kind != "CharacterConfigsCursor" {
+        return None;
+    }
+    // sort_keyは負になり得るので区切りは'-'ではなく':'
+    let mut parts = s.split(':');
+    let (order_by, descending, sort_key, character, stroke_count) = (
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+        parts.next()?,
+    );
+    if parts.next().is_some()
+        || order_by != character_configs_order_by_name(order.by)
+        || descending != i32::from(order.descending).to_string()
+    {
+        return None;
+    }
+    let sort_key = i64::from_str(sort_key).ok()?;
+    let character = base64::decode(character).ok()?;
+    let character = String::from_utf8(character).ok()?;
+    let character = entities::Character::try_from(character.as_str()).ok()?;
+    let stroke_count = i32::from_str(stroke_count).ok()?;
+    let stroke_count = entities::StrokeCount::try_from(stroke_count).ok()?;
+    Some(ports::CharacterConfigsCursor {
+        sort_key,
+        character,
+        stroke_count,
+    })
+}
+
+fn character_configs_order_by_name(order_by: ports::CharacterConfigsOrderBy) -> &'static str {
+    match order_by {
+        ports::CharacterConfigsOrderBy::Character => "character",
+        ports::CharacterConfigsOrderBy::UpdatedAt => "updatedAt",
+        ports::CharacterConfigsOrderBy::FigureRecordCount => "figureRecordCount",
+        ports::CharacterConfigsOrderBy::Ratio => "ratio",
+    }
+}
+
+// QueryRoot.trashedFigureRecordsのカーソル
+pub fn encode_trashed_figure_records_cursor(cursor: &ports::TrashedFigureRecordsCursor) -> String {
+    base64::encode(format!(
+        "TrashedFigureRecordsCursor:{}:{}",
+        cursor.disabled_at.timestamp_micros(),
+        Ulid::from(cursor.id),
+    ))
+}
+
+pub fn decode_trashed_figure_records_cursor(
+    cursor: &str,
+) -> Option<ports::TrashedFigureRecordsCursor> {
+    let buf = base64::decode(cursor).ok()?;
+    let s = String::from_utf8(buf).ok()?;
+    let (kind, s) = s.split_once(':')?;
+    if kind != "TrashedFigureRecordsCursor" {
+        return None;
+    }
+    let (disabled_at, id) = s.split_once(':')?;
+    let disabled_at = DateTime::<Utc>::from_timestamp_micros(i64::from_str(disabled_at).ok()?)?;
+    let id = Ulid::from_str(id).ok()?;
+    Some(ports::TrashedFigureRecordsCursor {
+        disabled_at,
+        id: entities::FigureRecordId::from(id),
+    })
+}
+
 #[derive(GraphQLObject, Clone, Debug)]
 pub struct PageInfo {
     pub has_next_page: bool,
@@ -185,8 +352,293 @@ pub struct PageInfo {
     pub end_cursor: Option<String>,
 }
 
+// クライアントが分岐に使うので値を変えないこと
+#[derive(GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+#[graphql(name = "ErrorCode")]
+pub enum GraphqlErrorCode {
+    Unauthenticated,
+    NotFound,
+    Validation,
+    // versionが一致しない。最新の値を取得し直して再度更新する
+    Conflict,
+    QuotaExceeded,
+    Internal,
+    // 以下は実行前にリクエストを弾いた時のもの
+    // Apolloのクライアントはクエリ文字列付きで再送する
+    PersistedQueryNotFound,
+    PersistedQueryNotAllowed,
+    QueryTooDeep,
+    QueryTooManyAliases,
+    QueryTooComplex,
+    // 1つのWebSocket接続で同時に購読できる数を超えた
+    TooManySubscriptions,
+}
+
+impl GraphqlErrorCode {
+    // extensions.codeの値。GraphQLのenumの値と同じ
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GraphqlErrorCode::Unauthenticated => "UNAUTHENTICATED",
+            GraphqlErrorCode::NotFound => "NOT_FOUND",
+            GraphqlErrorCode::Validation => "VALIDATION",
+            GraphqlErrorCode::Conflict => "CONFLICT",
+            GraphqlErrorCode::QuotaExceeded => "QUOTA_EXCEEDED",
+            GraphqlErrorCode::Internal => "INTERNAL",
+            GraphqlErrorCode::PersistedQueryNotFound => "PERSISTED_QUERY_NOT_FOUND",
+            GraphqlErrorCode::PersistedQueryNotAllowed => "PERSISTED_QUERY_NOT_ALLOWED",
+            GraphqlErrorCode::QueryTooDeep => "QUERY_TOO_DEEP",
+            GraphqlErrorCode::QueryTooManyAliases => "QUERY_TOO_MANY_ALIASES",
+            GraphqlErrorCode::QueryTooComplex => "QUERY_TOO_COMPLEX",
+            GraphqlErrorCode::TooManySubscriptions => "TOO_MANY_SUBSCRIPTIONS",
+        }
+    }
+}
+
 #[derive(GraphQLObject, Clone, Debug)]
 #[graphql(name = "Error")]
 pub struct GraphqlErrorType {
     pub message: String,
+    pub code: GraphqlErrorCode,
+    // 入力値の問題の場合、引数からのパス(例: ["input", "ratio"])
+    pub field: Option<Vec<String>>,
+}
+
+impl GraphqlErrorType {
+    pub fn validation(field: &[&str], message: &str) -> Self {
+        Self::from(GraphqlUserError::validation(field, message))
+    }
+}
+
+impl From<GraphqlUserError> for GraphqlErrorType {
+    fn from(e: GraphqlUserError) -> Self {
+        Self {
+            message: e.source.to_string(),
+            code: e.code,
+            field: e.field,
+        }
+    }
+}
+
+impl From<ports::ConflictError> for GraphqlErrorType {
+    fn from(e: ports::ConflictError) -> Self {
+        Self {
+            message: e.to_string(),
+            code: GraphqlErrorCode::Conflict,
+            field: None,
+        }
+    }
+}
+
+// 楽観ロックの競合はクライアントが解決できるようにpayloadのerrorsで返し、それ以外はエラーにする
+pub fn catch_conflict<T>(
+    result: anyhow::Result<T>,
+) -> Result<Result<T, GraphqlErrorType>, ApiError> {
+    match result {
+        Ok(value) => Ok(Ok(value)),
+        Err(e) => match e.downcast_ref::<ports::ConflictError>() {
+            Some(conflict) => Ok(Err(GraphqlErrorType::from(*conflict))),
+            None => Err(ApiError::from(e)),
+        },
+    }
+}
+
+// 更新系のinputのexpectedVersion。指定された場合は保存時にこのversionと照合する
+pub fn decode_expected_version(
+    expected_version: Option<i32>,
+) -> Result<Option<entities::Version>, GraphqlUserError> {
+    expected_version
+        .map(entities::Version::try_from)
+        .transpose()
+        .map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "expectedVersion"],
+                "expected_version must be a non negative integer",
+            )
+        })
+}
+
+// 実行前に弾いたリクエストのエラー。resolverのエラーと同じ形にする
+pub fn request_error(
+    code: GraphqlErrorCode,
+    message: &str,
+    limit: Option<u64>,
+) -> serde_json::Value {
+    let mut extensions = serde_json::json!({ "code": code.as_str() });
+    if let Some(limit) = limit {
+        extensions["limit"] = serde_json::Value::from(limit);
+    }
+    serde_json::json!({
+        "message": message,
+        "extensions": extensions,
+    })
+}
+
+// 問い合わせ時にログと突き合わせられるよう、全てのエラーのextensionsにリクエストIDを入れる
+// errorsはエラーの配列か、1つのエラー(graphql-wsのerrorのpayload)
+pub fn add_request_id(errors: &mut serde_json::Value, request_id: &str) {
+    let errors = match errors {
+        serde_json::Value::Array(errors) => errors.iter_mut().collect::<Vec<_>>(),
+        error => vec![error],
+    };
+    for error in errors.into_iter().filter_map(|error| error.as_object_mut()) {
+        let extensions = error
+            .entry("extensions")
+            .or_insert_with(|| serde_json::json!({}));
+        if let Some(extensions) = extensions.as_object_mut() {
+            extensions.insert(
+                "requestId".to_string(),
+                serde_json::Value::String(request_id.to_string()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juniper::{graphql_value, DefaultScalarValue, IntoFieldError};
+
+    use super::*;
+
+    #[test]
+    fn test_error_extensions() {
+        let error: juniper::FieldError<DefaultScalarValue> =
+            ApiError::from(GraphqlUserError::validation(&["input", "ratio"], "invalid"))
+                .into_field_error();
+        assert_eq!(error.message(), "invalid");
+        assert_eq!(
+            error.extensions(),
+            &graphql_value!({ "code": "VALIDATION", "field": ["input", "ratio"] })
+        );
+
+        let error: juniper::FieldError<DefaultScalarValue> =
+            ApiError::from(ports::ConflictError).into_field_error();
+        assert_eq!(error.extensions(), &graphql_value!({ "code": "CONFLICT" }));
+
+        // 想定外のエラーは内容を隠す
+        let error: juniper::FieldError<DefaultScalarValue> =
+            ApiError::from(anyhow!("connection refused")).into_field_error();
+        assert_eq!(error.message(), "Internal error");
+        assert_eq!(error.extensions(), &graphql_value!({ "code": "INTERNAL" }));
+    }
+
+    #[test]
+    fn test_request_error() {
+        let mut res = serde_json::json!({
+            "errors": [
+                request_error(GraphqlErrorCode::QueryTooDeep, "too deep", Some(10)),
+                request_error(GraphqlErrorCode::PersistedQueryNotFound, "PersistedQueryNotFound", None),
+            ],
+        });
+        add_request_id(&mut res["errors"], "request");
+        assert_eq!(
+            res,
+            serde_json::json!({
+                "errors": [
+                    {
+                        "message": "too deep",
+                        "extensions": { "code": "QUERY_TOO_DEEP", "limit": 10, "requestId": "request" },
+                    },
+                    {
+                        "message": "PersistedQueryNotFound",
+                        "extensions": { "code": "PERSISTED_QUERY_NOT_FOUND", "requestId": "request" },
+                    },
+                ],
+            })
+        );
+
+        // extensionsの無いエラーや、配列でない1つのエラーにも入れる
+        let mut error = serde_json::json!({ "message": "error" });
+        add_request_id(&mut error, "request");
+        assert_eq!(
+            error,
+            serde_json::json!({ "message": "error", "extensions": { "requestId": "request" } })
+        );
+    }
+
+    #[test]
+    fn test_character_configs_cursor() {
+        let cursor = ports::CharacterConfigsCursor {
+            sort_key: -1_700_000_000_000_000,
+            character: entities::Character::from('𠮷'),
+            stroke_count: entities::StrokeCount::try_from(6).unwrap(),
+        };
+        let orders = [
+            ports::CharacterConfigsOrderBy::Character,
+            ports::CharacterConfigsOrderBy::UpdatedAt,
+            ports::CharacterConfigsOrderBy::FigureRecordCount,
+            ports::CharacterConfigsOrderBy::Ratio,
+        ]
+        .into_iter()
+        .flat_map(|by| {
+            [false, true]
+                .into_iter()
+                .map(move |descending| ports::CharacterConfigsOrder { by, descending })
+        })
+        .collect::<Vec<_>>();
+
+        for &order in &orders {
+            let encoded = encode_character_configs_cursor(order, &cursor);
+            assert_eq!(
+                decode_character_configs_cursor(order, &encoded),
+                Some(cursor.clone())
+            );
+            // 違う並び順のカーソルは無効
+            for &other in &orders {
+                if other != order {
+                    assert_eq!(decode_character_configs_cursor(other, &encoded), None);
+                }
+            }
+        }
+
+        // 並び順を指定しない場合のみCharacterConfigのIDも受け付ける
+        let node_id = NodeId::CharacterConfig(
+            entities::UserId::from("user".to_string()),
+            cursor.character.clone(),
+            cursor.stroke_count,
+        )
+        .to_id()
+        .to_string();
+        assert_eq!(
+            decode_character_configs_cursor(ports::CharacterConfigsOrder::default(), &node_id),
+            Some(ports::CharacterConfigsCursor {
+                sort_key: 0,
+                ..cursor.clone()
+            })
+        );
+        assert_eq!(
+            decode_character_configs_cursor(
+                ports::CharacterConfigsOrder {
+                    by: ports::CharacterConfigsOrderBy::Ratio,
+                    descending: false,
+                },
+                &node_id
+            ),
+            None
+        );
+        assert_eq!(
+            decode_character_configs_cursor(ports::CharacterConfigsOrder::default(), "invalid"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_trashed_figure_records_cursor() {
+        let cursor = ports::TrashedFigureRecordsCursor {
+            disabled_at: DateTime::<Utc>::from_timestamp_micros(1_700_000_000_123_456).unwrap(),
+            id: entities::FigureRecordId::from(Ulid::new()),
+        };
+        assert_eq!(
+            decode_trashed_figure_records_cursor(&encode_trashed_figure_records_cursor(&cursor)),
+            Some(cursor.clone())
+        );
+
+        // FigureRecordのIDは受け付けない
+        assert_eq!(
+            decode_trashed_figure_records_cursor(
+                &NodeId::FigureRecord(cursor.id).to_id().to_string()
+            ),
+            None
+        );
+        assert_eq!(decode_trashed_figure_records_cursor("invalid"), None);
+    }
 }
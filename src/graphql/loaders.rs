@@ -3,8 +3,10 @@ use sqlx::PgPool;
 use crate::loaders::{
     CharacterConfigByCharacterLoader, CharacterConfigByIdLoader, CharacterConfigLoader,
     CharacterConfigSeedByCharacterLoader, CharacterConfigSeedByIdLoader,
-    CharacterConfigSeedsLoader, FigureRecordByIdLoader, FigureRecordsByCharacterConfigIdLoader,
-    FileByIdLoader, GenerateTemplateByIdLoader, GenerateTemplatesLoader,
+    CharacterConfigSeedsLoader, FigureRecordByIdLoader, FigureRecordStatsByCharacterLoader,
+    FigureRecordsByCharacterConfigIdLoader, FigureRecordsLoader, FileByIdLoader,
+    GenerateTemplateByIdLoader, GenerateTemplatesLoader, GenerationJobByIdLoader,
+    ReferenceFiguresByCharacterConfigIdLoader, TrashedFigureRecordsLoader,
 };
 use crate::{adapters, DataloaderWithParams};
 
@@ -18,11 +20,22 @@ pub struct Loaders {
     pub character_config_loader: DataloaderWithParams<
         CharacterConfigLoader<adapters::CharacterConfigsRepositoryImpl<PgPool>>,
     >,
+    pub reference_figures_by_character_config_id_loader: DataloaderWithParams<
+        ReferenceFiguresByCharacterConfigIdLoader<adapters::ReferenceFiguresRepositoryImpl<PgPool>>,
+    >,
     pub figure_record_by_id_loader:
         DataloaderWithParams<FigureRecordByIdLoader<adapters::FigureRecordsRepositoryImpl<PgPool>>>,
     pub figure_records_by_character_config_id_loader: DataloaderWithParams<
         FigureRecordsByCharacterConfigIdLoader<adapters::FigureRecordsRepositoryImpl<PgPool>>,
     >,
+    pub figure_records_loader:
+        DataloaderWithParams<FigureRecordsLoader<adapters::FigureRecordsRepositoryImpl<PgPool>>>,
+    pub trashed_figure_records_loader: DataloaderWithParams<
+        TrashedFigureRecordsLoader<adapters::FigureRecordsRepositoryImpl<PgPool>>,
+    >,
+    pub figure_record_stats_by_character_loader: DataloaderWithParams<
+        FigureRecordStatsByCharacterLoader<adapters::FigureRecordsRepositoryImpl<PgPool>>,
+    >,
     pub character_config_seed_by_character_loader: DataloaderWithParams<
         CharacterConfigSeedByCharacterLoader<adapters::CharacterConfigSeedsRepositoryImpl<PgPool>>,
     >,
@@ -40,6 +53,9 @@ pub struct Loaders {
     pub generate_templates_loader: DataloaderWithParams<
         GenerateTemplatesLoader<adapters::GenerateTemplatesRepositoryImpl<PgPool>>,
     >,
+    pub generation_job_by_id_loader: DataloaderWithParams<
+        GenerationJobByIdLoader<adapters::GenerationJobsRepositoryImpl<PgPool>>,
+    >,
 }
 
 impl Loaders {
@@ -62,6 +78,13 @@ impl Loaders {
                     pool.clone(),
                 ),
             }),
+            reference_figures_by_character_config_id_loader: DataloaderWithParams::new(
+                ReferenceFiguresByCharacterConfigIdLoader {
+                    reference_figures_repository: adapters::ReferenceFiguresRepositoryImpl::new(
+                        pool.clone(),
+                    ),
+                },
+            ),
             figure_record_by_id_loader: DataloaderWithParams::new(FigureRecordByIdLoader {
                 figure_records_repository: adapters::FigureRecordsRepositoryImpl::new(pool.clone()),
             }),
@@ -72,6 +95,19 @@ impl Loaders {
                     ),
                 },
             ),
+            figure_records_loader: DataloaderWithParams::new(FigureRecordsLoader {
+                figure_records_repository: adapters::FigureRecordsRepositoryImpl::new(pool.clone()),
+            }),
+            trashed_figure_records_loader: DataloaderWithParams::new(TrashedFigureRecordsLoader {
+                figure_records_repository: adapters::FigureRecordsRepositoryImpl::new(pool.clone()),
+            }),
+            figure_record_stats_by_character_loader: DataloaderWithParams::new(
+                FigureRecordStatsByCharacterLoader {
+                    figure_records_repository: adapters::FigureRecordsRepositoryImpl::new(
+                        pool.clone(),
+                    ),
+                },
+            ),
             character_config_seed_by_character_loader: DataloaderWithParams::new(
                 CharacterConfigSeedByCharacterLoader {
                     character_config_seeds_repository:
@@ -101,6 +137,11 @@ impl Loaders {
                     pool.clone(),
                 ),
             }),
+            generation_job_by_id_loader: DataloaderWithParams::new(GenerationJobByIdLoader {
+                generation_jobs_repository: adapters::GenerationJobsRepositoryImpl::new(
+                    pool.clone(),
+                ),
+            }),
         }
     }
 }
@@ -1,18 +1,24 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
+use std::pin::Pin;
+use std::sync::atomic::Ordering;
 
 use chrono::{DateTime, Utc};
 use derive_more::From;
-use juniper::FieldResult;
-use juniper::{
-    graphql_interface, EmptySubscription, GraphQLInputObject, GraphQLObject, RootNode, ID,
-};
+use futures::{Stream, StreamExt};
+use juniper::{graphql_interface, GraphQLInputObject, GraphQLObject, IntoFieldError, RootNode, ID};
+use juniper::{FieldError, FieldResult};
+use tokio_stream::wrappers::BroadcastStream;
 use ulid::Ulid;
 
+use crate::account_deleter::AccountDeleter;
 use crate::adapters::{
     CharacterConfigsRepositoryImpl, FigureRecordsRepositoryImpl, FilesRepositoryImpl,
-    GenerateTemplatesRepositoryImpl, StorageImpl, UserConfigsRepositoryImpl,
+    GenerateTemplatesRepositoryImpl, GenerationJobsRepositoryImpl, ReferenceFiguresRepositoryImpl,
+    StorageImpl, UserConfigsRepositoryImpl,
 };
-use crate::{entities, ports};
+use crate::event_bus::{self, Event};
+use crate::job::Job;
+use crate::{entities, jobs, ports, render};
 
 use crate::graphql::scalars::{FigureScalar, UlidScalar};
 use anyhow::Context;
@@ -25,7 +31,8 @@ mod loaders;
 use self::scalars::CharacterValueScalar;
 use crate::ports::{
     CharacterConfigsRepository, FigureRecordsRepository, FilesRepository,
-    GenerateTemplatesRepository, Storage, UserConfigsRepository,
+    GenerateTemplatesRepository, GenerationJobsRepository, ReferenceFiguresRepository, Storage,
+    UserConfigsRepository,
 };
 
 mod scalars;
@@ -33,8 +40,10 @@ use crate::loaders::{
     CharacterConfigByCharacterLoaderParams, CharacterConfigByIdLoaderParams,
     CharacterConfigLoaderParams, CharacterConfigSeedByCharacterLoaderParams,
     CharacterConfigSeedByIdLoaderParams, CharacterConfigSeedsLoaderParams,
-    FigureRecordByIdLoaderParams, FigureRecordsByCharacterConfigIdLoaderParams,
-    FileByIdLoaderParams, GenerateTemplateByIdLoaderParams, GenerateTemplatesLoaderParams,
+    FigureRecordByIdLoaderParams, FigureRecordStatsByCharacterLoaderParams,
+    FigureRecordsByCharacterConfigIdLoaderParams, FigureRecordsLoaderParams, FileByIdLoaderParams,
+    GenerateTemplateByIdLoaderParams, GenerateTemplatesLoaderParams, GenerationJobByIdLoaderParams,
+    ReferenceFiguresByCharacterConfigIdLoaderParams, TrashedFigureRecordsLoaderParams,
 };
 
 /*
@@ -43,7 +52,7 @@ use crate::loaders::{
  *   https://relay.dev/graphql/connections.htm
 */
 
-#[graphql_interface(for = [FigureRecord, CharacterConfig, Character, UserConfig, CharacterConfigSeed, File, GenerateTemplate], context = AppCtx)]
+#[graphql_interface(for = [FigureRecord, CharacterConfig, Character, UserConfig, CharacterConfigSeed, File, GenerateTemplate, GenerationJob], context = AppCtx)]
 trait Node {
     #[graphql(name = "id")]
     fn node_id(&self) -> ID;
@@ -70,9 +79,50 @@ impl FigureRecord {
         FigureScalar(self.0.figure.clone())
     }
 
+    fn svg(&self) -> String {
+        render::figure_to_svg(&self.0.figure)
+    }
+
+    // サムネイルの生成はURLにアクセスされた時に行うのでここではS3にアクセスしない
+    fn thumbnail_url(
+        &self,
+        ctx: &AppCtx,
+        #[graphql(default = 128)] size: i32,
+    ) -> Result<String, ApiError> {
+        let size = entities::ThumbnailSize::try_from(size).map_err(|_| {
+            GraphqlUserError::validation(&["size"], "size must be a valid thumbnail size")
+        })?;
+
+        Ok(format!("{}/{}", ctx.config.origin, {
+            let mut path = ctx.config.mount_base.clone();
+            path.push("figure_records".to_string());
+            path.push(Ulid::from(self.0.id).to_string());
+            path.push("thumbnails".to_string());
+            path.push(format!("{}.png", u32::from(size)));
+            path.join("/")
+        }))
+    }
+
     fn created_at(&self) -> DateTime<Utc> {
         self.0.created_at
     }
+
+    fn version(&self) -> i32 {
+        i32::from(self.0.version)
+    }
+
+    fn disabled(&self) -> bool {
+        self.0.disabled
+    }
+
+    fn disabled_at(&self) -> Option<DateTime<Utc>> {
+        self.0.disabled_at
+    }
+
+    // この日時を過ぎると復元できなくなり物理削除される
+    fn purge_at(&self) -> Option<DateTime<Utc>> {
+        self.0.purge_at()
+    }
 }
 
 #[graphql_interface]
@@ -106,9 +156,79 @@ struct CreateFigureRecordInput {
 #[graphql(context = AppCtx)]
 struct CreateFigureRecordPayload {
     figure_record: Option<FigureRecord>,
+    // 文字設定に手本(referenceFigures)がある場合のみ。最も近い字形のもの
+    reference_deviation: Option<StrokeDeviation>,
     errors: Option<Vec<GraphqlErrorType>>,
 }
 
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct CreateFigureRecordsPayload {
+    // inputsと同じ順
+    results: Vec<CreateFigureRecordPayload>,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+struct StrokeDeviation {
+    // 比較した手本の字形
+    variant: String,
+    distance: f64,
+    misordered_strokes: Vec<i32>,
+    reversed_strokes: Vec<i32>,
+}
+
+impl StrokeDeviation {
+    // 字形ごとの手本のうちdistanceが最小のものとの差
+    fn nearest(
+        figure: &entities::Figure,
+        reference_figures: &[entities::ReferenceFigure],
+    ) -> Option<Self> {
+        reference_figures
+            .iter()
+            .filter_map(|reference_figure| {
+                entities::StrokeDeviation::compute(figure, &reference_figure.figure)
+                    .map(|deviation| (reference_figure, deviation))
+            })
+            .min_by(|(_, a), (_, b)| a.distance.total_cmp(&b.distance))
+            .map(|(reference_figure, deviation)| Self {
+                variant: String::from(reference_figure.variant.clone()),
+                distance: deviation.distance,
+                misordered_strokes: deviation
+                    .misordered_strokes
+                    .into_iter()
+                    .map(|i| i as i32)
+                    .collect(),
+                reversed_strokes: deviation
+                    .reversed_strokes
+                    .into_iter()
+                    .map(|i| i as i32)
+                    .collect(),
+            })
+    }
+}
+
+#[derive(Clone, Debug, From)]
+struct ReferenceFigure(entities::ReferenceFigure);
+
+#[juniper::graphql_object(Context = AppCtx)]
+impl ReferenceFigure {
+    fn variant(&self) -> String {
+        String::from(self.0.variant.clone())
+    }
+
+    fn figure(&self) -> FigureScalar {
+        FigureScalar(self.0.figure.clone())
+    }
+
+    fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.0.updated_at
+    }
+
+    fn version(&self) -> i32 {
+        i32::from(self.0.version)
+    }
+}
+
 #[derive(Clone, Debug, From)]
 struct File(entities::File);
 
@@ -142,12 +262,16 @@ impl File {
         self.0.updated_at
     }
 
+    fn version(&self) -> i32 {
+        i32::from(self.0.version)
+    }
+
     async fn upload_url(&self, ctx: &AppCtx) -> Result<String, ApiError> {
         let mut storage = StorageImpl::new(ctx.config.clone(), ctx.s3_client.clone());
         let url = storage
             .generate_upload_url(&self.0)
             .await
-            .map_err(|_| GraphqlUserError::from("Failed to generate upload URL"))?;
+            .context("generate upload URL")?;
         Ok(url)
     }
 
@@ -156,7 +280,7 @@ impl File {
         let url = storage
             .generate_download_url(&self.0)
             .await
-            .map_err(|_| GraphqlUserError::from("Failed to generate download URL"))?;
+            .context("generate download URL")?;
         Ok(url)
     }
 }
@@ -210,7 +334,7 @@ impl GenerateTemplate {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let file = ctx
             .loaders
@@ -224,10 +348,7 @@ impl GenerateTemplate {
             )
             .await
             .context("load file")??
-            .ok_or_else(|| {
-                // TODO: internal error
-                GraphqlUserError::from("Background image file not found or not verified")
-            })?;
+            .ok_or_else(|| anyhow::anyhow!("Background image file not found or not verified"))?;
 
         Ok(File::from(file))
     }
@@ -271,6 +392,14 @@ impl GenerateTemplate {
     fn updated_at(&self) -> DateTime<Utc> {
         self.0.updated_at
     }
+
+    fn version(&self) -> i32 {
+        i32::from(self.0.version)
+    }
+
+    fn disabled(&self) -> bool {
+        self.0.disabled
+    }
 }
 
 #[graphql_interface]
@@ -350,6 +479,7 @@ struct UpdateGenerateTemplateInput {
     letter_spacing: Option<i32>,
     font_size: Option<i32>,
     font_weight: Option<i32>,
+    expected_version: Option<i32>,
 }
 
 #[derive(GraphQLObject, Clone, Debug)]
@@ -371,6 +501,160 @@ struct DeleteGenerateTemplatePayload {
     errors: Option<Vec<GraphqlErrorType>>,
 }
 
+#[derive(GraphQLInputObject, Clone, Debug)]
+struct GenerateDocumentInput {
+    generate_template_id: UlidScalar,
+    text: String,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct GenerateDocumentPayload {
+    generation_job: Option<GenerationJob>,
+    errors: Option<Vec<GraphqlErrorType>>,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct DeleteMyAccountPayload {
+    errors: Option<Vec<GraphqlErrorType>>,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct ExportMyDataPayload {
+    generation_job: Option<GenerationJob>,
+    errors: Option<Vec<GraphqlErrorType>>,
+}
+
+#[derive(Clone, Debug, From)]
+struct GenerationJob(entities::GenerationJob);
+
+#[juniper::graphql_object(Context = AppCtx, impl = NodeValue)]
+impl GenerationJob {
+    fn id(&self) -> ID {
+        self.node_id()
+    }
+
+    fn generation_job_id(&self) -> UlidScalar {
+        UlidScalar(Ulid::from(self.0.id))
+    }
+
+    fn kind(&self) -> GenerationJobKind {
+        GenerationJobKind::from(&self.0.kind)
+    }
+
+    // 文書生成以外のジョブ、または生成後にテンプレートが削除された場合はnull
+    async fn generate_template(&self, ctx: &AppCtx) -> Result<Option<GenerateTemplate>, ApiError> {
+        let entities::GenerationJobKind::Document {
+            generate_template_id,
+            ..
+        } = self.0.kind
+        else {
+            return Ok(None);
+        };
+
+        let generate_template = ctx
+            .loaders
+            .generate_template_by_id_loader
+            .load(
+                GenerateTemplateByIdLoaderParams {
+                    user_id: self.0.user_id.clone(),
+                },
+                generate_template_id,
+            )
+            .await
+            .context("load generate_template")??;
+
+        Ok(generate_template.map(GenerateTemplate::from))
+    }
+
+    fn text(&self) -> Option<String> {
+        match &self.0.kind {
+            entities::GenerationJobKind::Document { text, .. } => Some(text.value().to_string()),
+            entities::GenerationJobKind::DataExport => None,
+        }
+    }
+
+    fn status(&self) -> GenerationJobStatus {
+        GenerationJobStatus::from(self.0.status)
+    }
+
+    fn error_message(&self) -> Option<String> {
+        self.0.error_message.clone()
+    }
+
+    async fn file(&self, ctx: &AppCtx) -> Result<Option<File>, ApiError> {
+        let Some(result_file_id) = self.0.result_file_id else {
+            return Ok(None);
+        };
+
+        let file = ctx
+            .loaders
+            .file_by_id_loader
+            .load(
+                FileByIdLoaderParams {
+                    user_id: self.0.user_id.clone(),
+                    verified_only: true,
+                },
+                result_file_id,
+            )
+            .await
+            .context("load file")??;
+
+        Ok(file.map(File::from))
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+}
+
+#[graphql_interface]
+impl Node for GenerationJob {
+    fn node_id(&self) -> ID {
+        NodeId::GenerationJob(self.0.id).to_id()
+    }
+}
+
+#[derive(juniper::GraphQLEnum, Clone, Debug)]
+enum GenerationJobKind {
+    Document,
+    DataExport,
+}
+
+impl From<&entities::GenerationJobKind> for GenerationJobKind {
+    fn from(value: &entities::GenerationJobKind) -> Self {
+        match value {
+            entities::GenerationJobKind::Document { .. } => GenerationJobKind::Document,
+            entities::GenerationJobKind::DataExport => GenerationJobKind::DataExport,
+        }
+    }
+}
+
+#[derive(juniper::GraphQLEnum, Clone, Debug)]
+enum GenerationJobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl From<entities::GenerationJobStatus> for GenerationJobStatus {
+    fn from(value: entities::GenerationJobStatus) -> Self {
+        match value {
+            entities::GenerationJobStatus::Queued => GenerationJobStatus::Queued,
+            entities::GenerationJobStatus::Running => GenerationJobStatus::Running,
+            entities::GenerationJobStatus::Succeeded => GenerationJobStatus::Succeeded,
+            entities::GenerationJobStatus::Failed => GenerationJobStatus::Failed,
+        }
+    }
+}
+
 #[derive(Clone, Debug, From)]
 struct CharacterConfig(entities::CharacterConfig);
 
@@ -386,6 +670,32 @@ impl Node for CharacterConfig {
     }
 }
 
+impl CharacterConfig {
+    async fn load_figure_record_stats(
+        &self,
+        ctx: &AppCtx,
+    ) -> Result<Option<ports::FigureRecordStats>, ApiError> {
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let stats = ctx
+            .loaders
+            .figure_record_stats_by_character_loader
+            .load(
+                FigureRecordStatsByCharacterLoaderParams { user_id },
+                self.0.character.clone(),
+            )
+            .await
+            .context("load figure_record_stats")??;
+
+        Ok(stats
+            .into_iter()
+            .find(|stats| stats.stroke_count == self.0.stroke_count))
+    }
+}
+
 #[juniper::graphql_object(Context = AppCtx, impl = NodeValue)]
 impl CharacterConfig {
     fn id(&self) -> ID {
@@ -408,10 +718,64 @@ impl CharacterConfig {
         self.0.updated_at
     }
 
+    fn version(&self) -> i32 {
+        i32::from(self.0.version)
+    }
+
     fn disabled(&self) -> bool {
         self.0.disabled
     }
 
+    // 書き順・筆の向きの手本。字形(variant)ごとにvariant順
+    async fn reference_figures(&self, ctx: &AppCtx) -> Result<Vec<ReferenceFigure>, ApiError> {
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let reference_figures = ctx
+            .loaders
+            .reference_figures_by_character_config_id_loader
+            .load(
+                ReferenceFiguresByCharacterConfigIdLoaderParams { user_id },
+                (self.0.character.clone(), self.0.stroke_count),
+            )
+            .await
+            .context("load reference_figures")??;
+
+        Ok(reference_figures
+            .into_iter()
+            .map(ReferenceFigure::from)
+            .collect())
+    }
+
+    // disabledでないfigureRecordsの数。userType省略時は自分のものと共有されたものの合計
+    async fn figure_record_count(
+        &self,
+        ctx: &AppCtx,
+        user_type: Option<UserType>,
+    ) -> Result<i32, ApiError> {
+        let count = self
+            .load_figure_record_stats(ctx)
+            .await?
+            .map(|stats| match user_type {
+                Some(UserType::Myself) => stats.my_count,
+                Some(UserType::Other) => stats.other_count,
+                None => stats.my_count + stats.other_count,
+            })
+            .unwrap_or(0);
+
+        Ok(i32::try_from(count).context("into i32")?)
+    }
+
+    // 自分が最後に記録した日時
+    async fn last_recorded_at(&self, ctx: &AppCtx) -> Result<Option<DateTime<Utc>>, ApiError> {
+        Ok(self
+            .load_figure_record_stats(ctx)
+            .await?
+            .and_then(|stats| stats.last_recorded_at))
+    }
+
     async fn figure_records(
         &self,
         ctx: &AppCtx,
@@ -422,11 +786,10 @@ impl CharacterConfig {
         before: Option<String>,
         user_type: Option<UserType>,
     ) -> Result<FigureRecordConnection, ApiError> {
-        // TODO: N+1
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let ids = ids.map(|ids| {
             ids.into_iter()
@@ -439,7 +802,11 @@ impl CharacterConfig {
         let after_id = after
             .map(|after| -> anyhow::Result<entities::FigureRecordId> {
                 let Some(NodeId::FigureRecord(id)) = NodeId::from_id(&ID::new(after)) else {
-                    return Err(GraphqlUserError::from("after must be a valid cursor").into());
+                    return Err(GraphqlUserError::validation(
+                        &["after"],
+                        "after must be a valid cursor",
+                    )
+                    .into());
                 };
 
                 Ok(id)
@@ -449,7 +816,11 @@ impl CharacterConfig {
         let before_id = before
             .map(|before| -> anyhow::Result<entities::FigureRecordId> {
                 let Some(NodeId::FigureRecord(id)) = NodeId::from_id(&ID::new(before)) else {
-                    return Err(GraphqlUserError::from("before must be a valid cursor").into());
+                    return Err(GraphqlUserError::validation(
+                        &["before"],
+                        "before must be a valid cursor",
+                    )
+                    .into());
                 };
                 Ok(id)
             })
@@ -459,18 +830,16 @@ impl CharacterConfig {
             .loaders
             .figure_records_by_character_config_id_loader
             .load(
-                FigureRecordsByCharacterConfigIdLoaderParams {
-                    user_id,
+                FigureRecordsByCharacterConfigIdLoaderParams { user_id },
+                ports::FigureRecordsByCharacterConfigIdQuery {
+                    character: self.0.character.clone(),
+                    stroke_count: self.0.stroke_count,
                     ids,
                     after_id,
                     before_id,
                     limit: limit.clone(),
-                    user_type: user_type.map(|user_type| match user_type {
-                        UserType::Myself => ports::UserType::Myself,
-                        UserType::Other => ports::UserType::Other,
-                    }),
+                    user_type: user_type.map(ports::UserType::from),
                 },
-                (self.0.character.clone(), self.0.stroke_count),
             )
             .await
             .context("load character_config")??;
@@ -562,6 +931,8 @@ struct UpdateCharacterConfigInput {
     stroke_count: i32,
     ratio: Option<i32>,
     disabled: Option<bool>,
+    // 未作成の文字設定は0
+    expected_version: Option<i32>,
 }
 
 #[derive(GraphQLObject, Clone, Debug)]
@@ -571,10 +942,39 @@ struct UpdateCharacterConfigPayload {
     errors: Option<Vec<GraphqlErrorType>>,
 }
 
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct UpdateCharacterConfigsPayload {
+    // inputsと同じ順
+    results: Vec<UpdateCharacterConfigPayload>,
+}
+
+#[derive(GraphQLInputObject, Clone, Debug)]
+struct SetCharacterConfigReferenceFigureInput {
+    character: CharacterValueScalar,
+    stroke_count: i32,
+    // 同じ画数で字形の違う手本を区別する名前。省略時は"default"
+    variant: Option<String>,
+    // nullでこのvariantの手本を外す
+    reference_figure: Option<FigureScalar>,
+    // このvariantの手本のversion
+    expected_version: Option<i32>,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct SetCharacterConfigReferenceFigurePayload {
+    character_config: Option<CharacterConfig>,
+    // 外した場合はnull
+    reference_figure: Option<ReferenceFigure>,
+    errors: Option<Vec<GraphqlErrorType>>,
+}
+
 #[derive(GraphQLInputObject, Clone, Debug)]
 struct UpdateFigureRecordInput {
     id: UlidScalar,
     disabled: Option<bool>,
+    expected_version: Option<i32>,
 }
 
 #[derive(GraphQLObject, Clone, Debug)]
@@ -584,55 +984,191 @@ struct UpdateFigureRecordPayload {
     errors: Option<Vec<GraphqlErrorType>>,
 }
 
-#[derive(GraphQLInputObject, Clone, Debug)]
-struct UpdateUserConfigInput {
-    allow_sharing_character_configs: Option<bool>,
-    allow_sharing_figure_records: Option<bool>,
-    random_level: Option<i32>,
-    shared_proportion: Option<i32>,
+#[derive(GraphQLInputObject, Clone, Debug, Default)]
+struct FigureRecordsFilter {
+    characters: Option<Vec<CharacterValueScalar>>,
+    min_stroke_count: Option<i32>,
+    max_stroke_count: Option<i32>,
+    // createdAfter <= createdAt < createdBefore
+    created_after: Option<DateTime<Utc>>,
+    created_before: Option<DateTime<Utc>>,
+    user_type: Option<UserType>,
+    // 省略時はEXCLUDE
+    disabled: Option<DisabledFilter>,
+}
+
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug)]
+enum DisabledFilter {
+    // disabledでないもののみ
+    Exclude,
+    // disabledなもの(ゴミ箱)のみ
+    Only,
+    Include,
 }
 
 #[derive(GraphQLObject, Clone, Debug)]
 #[graphql(context = AppCtx)]
-struct UpdateUserConfigPayload {
-    user_config: Option<UserConfig>,
-    errors: Option<Vec<GraphqlErrorType>>,
+struct Coverage {
+    // 空白と重複を除いた文字の数
+    character_count: i32,
+    covered_count: i32,
+    shared_count: i32,
+    missing_count: i32,
+    characters: Vec<CharacterCoverage>,
 }
 
-#[derive(Clone, Debug, From)]
-struct Character(entities::Character);
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct CharacterCoverage {
+    character: Character,
+    // 全ての画数の合計
+    my_record_count: i32,
+    shared_record_count: i32,
+    status: CoverageStatus,
+}
 
-#[graphql_interface]
-impl Node for Character {
-    fn node_id(&self) -> ID {
-        NodeId::Character(self.0.clone()).to_id()
-    }
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum CoverageStatus {
+    // 自分の記録だけでminRecordCount件以上ある
+    Covered,
+    // 共有された記録を合わせるとminRecordCount件以上ある
+    Shared,
+    Missing,
 }
 
-#[derive(juniper::GraphQLEnum)]
-enum UserType {
-    Myself,
-    Other,
+#[derive(GraphQLInputObject, Clone, Debug, Default)]
+struct CharacterConfigsFilter {
+    characters: Option<Vec<CharacterValueScalar>>,
+    min_stroke_count: Option<i32>,
+    max_stroke_count: Option<i32>,
+    // 省略時はfalse
+    include_disabled: Option<bool>,
+    // trueなら自分のfigureRecordsがまだ1件もないもののみ
+    without_figure_records: Option<bool>,
 }
 
-#[juniper::graphql_object(Context = AppCtx, impl = NodeValue)]
-impl Character {
-    fn id(&self) -> ID {
-        self.node_id()
-    }
+#[derive(GraphQLInputObject, Clone, Debug)]
+struct CharacterConfigsOrder {
+    by: CharacterConfigsOrderBy,
+    // 省略時はASC
+    direction: Option<OrderDirection>,
+}
 
-    fn value(&self) -> CharacterValueScalar {
-        CharacterValueScalar(self.0.clone())
+impl From<CharacterConfigsOrder> for ports::CharacterConfigsOrder {
+    fn from(value: CharacterConfigsOrder) -> Self {
+        ports::CharacterConfigsOrder {
+            by: match value.by {
+                CharacterConfigsOrderBy::Character => ports::CharacterConfigsOrderBy::Character,
+                CharacterConfigsOrderBy::UpdatedAt => ports::CharacterConfigsOrderBy::UpdatedAt,
+                CharacterConfigsOrderBy::FigureRecordCount => {
+                    ports::CharacterConfigsOrderBy::FigureRecordCount
+                }
+                CharacterConfigsOrderBy::Ratio => ports::CharacterConfigsOrderBy::Ratio,
+            },
+            descending: value.direction == Some(OrderDirection::Desc),
+        }
     }
+}
 
-    async fn character_configs(&self, ctx: &mut AppCtx) -> Result<Vec<CharacterConfig>, ApiError> {
-        // 通常は1～3程度になるはずだし、どんなに多くてもStrokeCountの最大値である1000を超えることはないのでページネーションはしない
-        let user_id = ctx
-            .user_id
-            .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+// 同じ値の間はcharacter, strokeCountの順に並ぶ
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug)]
+enum CharacterConfigsOrderBy {
+    Character,
+    UpdatedAt,
+    // 自分のdisabledでないfigureRecordsの数
+    FigureRecordCount,
+    Ratio,
+}
 
-        let character_configs = ctx
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OrderDirection {
+    Asc,
+    Desc,
+}
+
+#[derive(GraphQLInputObject, Clone, Debug)]
+struct DeleteFigureRecordInput {
+    figure_record_id: UlidScalar,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct DeleteFigureRecordPayload {
+    figure_record: Option<FigureRecord>,
+    errors: Option<Vec<GraphqlErrorType>>,
+}
+
+#[derive(GraphQLInputObject, Clone, Debug)]
+struct RestoreFigureRecordInput {
+    figure_record_id: UlidScalar,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct RestoreFigureRecordPayload {
+    figure_record: Option<FigureRecord>,
+    errors: Option<Vec<GraphqlErrorType>>,
+}
+
+#[derive(GraphQLInputObject, Clone, Debug)]
+struct UpdateUserConfigInput {
+    allow_sharing_character_configs: Option<bool>,
+    allow_sharing_figure_records: Option<bool>,
+    random_level: Option<i32>,
+    shared_proportion: Option<i32>,
+    expected_version: Option<i32>,
+}
+
+#[derive(GraphQLObject, Clone, Debug)]
+#[graphql(context = AppCtx)]
+struct UpdateUserConfigPayload {
+    user_config: Option<UserConfig>,
+    errors: Option<Vec<GraphqlErrorType>>,
+}
+
+#[derive(Clone, Debug, From)]
+struct Character(entities::Character);
+
+#[graphql_interface]
+impl Node for Character {
+    fn node_id(&self) -> ID {
+        NodeId::Character(self.0.clone()).to_id()
+    }
+}
+
+#[derive(juniper::GraphQLEnum, Clone, Copy, Debug)]
+enum UserType {
+    Myself,
+    Other,
+}
+
+impl From<UserType> for ports::UserType {
+    fn from(value: UserType) -> Self {
+        match value {
+            UserType::Myself => ports::UserType::Myself,
+            UserType::Other => ports::UserType::Other,
+        }
+    }
+}
+
+#[juniper::graphql_object(Context = AppCtx, impl = NodeValue)]
+impl Character {
+    fn id(&self) -> ID {
+        self.node_id()
+    }
+
+    fn value(&self) -> CharacterValueScalar {
+        CharacterValueScalar(self.0.clone())
+    }
+
+    async fn character_configs(&self, ctx: &mut AppCtx) -> Result<Vec<CharacterConfig>, ApiError> {
+        // 通常は1～3程度になるはずだし、どんなに多くてもStrokeCountの最大値である1000を超えることはないのでページネーションはしない
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let character_configs = ctx
             .loaders
             .character_config_by_character_loader
             .load(
@@ -656,10 +1192,14 @@ impl Character {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
-        let stroke_count = entities::StrokeCount::try_from(stroke_count)
-            .map_err(|_| GraphqlUserError::from("stroke_count must be an non negative integer"))?;
+        let stroke_count = entities::StrokeCount::try_from(stroke_count).map_err(|_| {
+            GraphqlUserError::validation(
+                &["strokeCount"],
+                "stroke_count must be an non negative integer",
+            )
+        })?;
 
         let character_config = ctx
             .loaders
@@ -700,8 +1240,12 @@ impl Character {
         ctx: &mut AppCtx,
         stroke_count: i32,
     ) -> Result<Option<CharacterConfigSeed>, ApiError> {
-        let stroke_count = entities::StrokeCount::try_from(stroke_count)
-            .map_err(|_| GraphqlUserError::from("stroke_count must be an non negative integer"))?;
+        let stroke_count = entities::StrokeCount::try_from(stroke_count).map_err(|_| {
+            GraphqlUserError::validation(
+                &["strokeCount"],
+                "stroke_count must be an non negative integer",
+            )
+        })?;
 
         let character_config_seed = ctx
             .loaders
@@ -715,6 +1259,95 @@ impl Character {
 
         Ok(character_config_seed.map(CharacterConfigSeed::from))
     }
+
+    /// 自分と他人それぞれ最新100件までのfigure_recordを重み付けして平均したfigure
+    async fn average_figure(
+        &self,
+        ctx: &AppCtx,
+        stroke_count: i32,
+        random_level: Option<i32>,
+        shared_proportion: Option<i32>,
+        #[graphql(default = 0)] seed: i32,
+    ) -> Result<Option<FigureScalar>, ApiError> {
+        let mut user_config_repository = UserConfigsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let stroke_count = entities::StrokeCount::try_from(stroke_count).map_err(|_| {
+            GraphqlUserError::validation(
+                &["strokeCount"],
+                "stroke_count must be an non negative integer",
+            )
+        })?;
+
+        let user_config = user_config_repository
+            .get(user_id.clone())
+            .await
+            .context("load user_config")?;
+
+        let random_level = random_level
+            .map(entities::RandomLevel::try_from)
+            .transpose()
+            .map_err(|_| GraphqlUserError::validation(&["randomLevel"], "random_level is invalid"))?
+            .unwrap_or(user_config.random_level);
+
+        let shared_proportion = shared_proportion
+            .map(entities::SharedProportion::try_from)
+            .transpose()
+            .map_err(|_| {
+                GraphqlUserError::validation(&["sharedProportion"], "shared_proportion is invalid")
+            })?
+            .unwrap_or(user_config.shared_proportion);
+
+        let limit = entities::Limit::new(
+            entities::LimitKind::First,
+            AVERAGE_FIGURE_MAX_RECORDS_PER_USER_TYPE,
+        )?;
+
+        let mut figures_by_user_type = Vec::new();
+        for user_type in [ports::UserType::Myself, ports::UserType::Other] {
+            let result = ctx
+                .loaders
+                .figure_records_by_character_config_id_loader
+                .load(
+                    FigureRecordsByCharacterConfigIdLoaderParams {
+                        user_id: user_id.clone(),
+                    },
+                    ports::FigureRecordsByCharacterConfigIdQuery {
+                        character: self.0.clone(),
+                        stroke_count,
+                        ids: None,
+                        after_id: None,
+                        before_id: None,
+                        limit,
+                        user_type: Some(user_type),
+                    },
+                )
+                .await
+                .context("load figure_records")??;
+
+            figures_by_user_type.push(
+                result
+                    .values
+                    .into_iter()
+                    .map(|record| record.figure)
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        let figure = entities::average_figure(
+            &figures_by_user_type[0],
+            &figures_by_user_type[1],
+            random_level,
+            shared_proportion,
+            seed as u32 as u64,
+        );
+
+        Ok(figure.map(FigureScalar))
+    }
 }
 
 #[derive(Clone, Debug, From)]
@@ -752,6 +1385,10 @@ impl UserConfig {
     fn updated_at(&self) -> Option<DateTime<Utc>> {
         self.0.updated_at
     }
+
+    fn version(&self) -> i32 {
+        i32::from(self.0.version)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -784,7 +1421,7 @@ impl QueryRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         Ok(UserConfig(user_config_repository.get(user_id).await?))
     }
@@ -793,7 +1430,7 @@ impl QueryRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let Some(id) = NodeId::from_id(&id) else {
             return Ok(None);
@@ -804,7 +1441,13 @@ impl QueryRoot {
                 let figure_record = ctx
                     .loaders
                     .figure_record_by_id_loader
-                    .load(FigureRecordByIdLoaderParams { user_id }, id)
+                    .load(
+                        FigureRecordByIdLoaderParams {
+                            user_id,
+                            include_disabled: false,
+                        },
+                        id,
+                    )
                     .await
                     .context("load FigureRecord")??;
                 Ok(figure_record
@@ -876,7 +1519,35 @@ impl QueryRoot {
                     .map(GenerateTemplate::from)
                     .map(NodeValue::GenerateTemplate))
             }
+            NodeId::GenerationJob(id) => {
+                let generation_job = ctx
+                    .loaders
+                    .generation_job_by_id_loader
+                    .load(GenerationJobByIdLoaderParams { user_id }, id)
+                    .await
+                    .context("load generation_job")??;
+                Ok(generation_job
+                    .map(GenerationJob::from)
+                    .map(NodeValue::GenerationJob))
+            }
+        }
+    }
+
+    // 存在しない・不正なIDはnullにし、idsと同じ順で返す
+    async fn nodes(ctx: &AppCtx, ids: Vec<ID>) -> Result<Vec<Option<NodeValue>>, ApiError> {
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        if ids.len() > MAX_NODE_IDS {
+            return Err(GraphqlUserError::quota_exceeded(
+                format!("ids must be at most {}", MAX_NODE_IDS).as_str(),
+            )
+            .into());
         }
+
+        load_nodes(&ctx.loaders, &ctx.pool, user_id, &ids).await
     }
 
     async fn characters(values: Vec<CharacterValueScalar>) -> FieldResult<Vec<Character>> {
@@ -901,35 +1572,65 @@ impl QueryRoot {
         after: Option<String>,
         last: Option<i32>,
         before: Option<String>,
+        filter: Option<CharacterConfigsFilter>,
+        order: Option<CharacterConfigsOrder>,
     ) -> Result<CharacterConfigConnection, ApiError> {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let limit = encode_limit(first, last)?;
+        let filter = filter.unwrap_or_default();
+        let order = order
+            .map(ports::CharacterConfigsOrder::from)
+            .unwrap_or_default();
+
+        if filter
+            .characters
+            .as_ref()
+            .is_some_and(|characters| characters.len() > MAX_FILTER_CHARACTERS)
+        {
+            return Err(GraphqlUserError::quota_exceeded(
+                format!("characters must be at most {}", MAX_FILTER_CHARACTERS).as_str(),
+            )
+            .into());
+        }
 
-        let after_id = after
-            .map(|after| -> anyhow::Result<_> {
-                let Some(NodeId::CharacterConfig(_, character, stroke_count)) =
-                    NodeId::from_id(&ID::new(after))
-                else {
-                    return Err(GraphqlUserError::from("after must be a valid cursor").into());
-                };
+        let min_stroke_count = filter
+            .min_stroke_count
+            .map(entities::StrokeCount::try_from)
+            .transpose()
+            .map_err(|_| {
+                GraphqlUserError::validation(
+                    &["filter", "minStrokeCount"],
+                    "minStrokeCount must be a valid stroke count",
+                )
+            })?;
+        let max_stroke_count = filter
+            .max_stroke_count
+            .map(entities::StrokeCount::try_from)
+            .transpose()
+            .map_err(|_| {
+                GraphqlUserError::validation(
+                    &["filter", "maxStrokeCount"],
+                    "maxStrokeCount must be a valid stroke count",
+                )
+            })?;
 
-                Ok((character, stroke_count))
+        let after = after
+            .map(|after| {
+                decode_character_configs_cursor(order, &after).ok_or_else(|| {
+                    GraphqlUserError::validation(&["after"], "after must be a valid cursor")
+                })
             })
             .transpose()?;
 
-        let before_id = before
-            .map(|before| -> anyhow::Result<_> {
-                let Some(NodeId::CharacterConfig(_, character, stroke_count)) =
-                    NodeId::from_id(&ID::new(before))
-                else {
-                    return Err(GraphqlUserError::from("before must be a valid cursor").into());
-                };
-
-                Ok((character, stroke_count))
+        let before = before
+            .map(|before| {
+                decode_character_configs_cursor(order, &before).ok_or_else(|| {
+                    GraphqlUserError::validation(&["before"], "before must be a valid cursor")
+                })
             })
             .transpose()?;
 
@@ -939,8 +1640,21 @@ impl QueryRoot {
             .load(
                 CharacterConfigLoaderParams {
                     user_id,
-                    after_id,
-                    before_id,
+                    filter: ports::CharacterConfigsQueryFilter {
+                        characters: filter.characters.map(|characters| {
+                            characters
+                                .into_iter()
+                                .map(|character| character.0)
+                                .collect()
+                        }),
+                        min_stroke_count,
+                        max_stroke_count,
+                        include_disabled: filter.include_disabled.unwrap_or(false),
+                        without_figure_records: filter.without_figure_records.unwrap_or(false),
+                    },
+                    order,
+                    after,
+                    before,
                     limit: limit.clone(),
                 },
                 (),
@@ -948,26 +1662,23 @@ impl QueryRoot {
             .await
             .context("load character_config")??;
 
-        let records = result
+        let edges = result
             .values
             .into_iter()
-            .map(CharacterConfig::from)
+            .map(|(character_config, cursor)| CharacterConfigEdge {
+                cursor: encode_character_configs_cursor(order, &cursor),
+                node: CharacterConfig::from(character_config),
+            })
             .collect::<Vec<_>>();
 
         Ok(CharacterConfigConnection {
             page_info: PageInfo {
                 has_next_page: result.has_next && limit.kind() == entities::LimitKind::First,
                 has_previous_page: result.has_next && limit.kind() == entities::LimitKind::Last,
-                start_cursor: records.first().map(|record| record.node_id().to_string()),
-                end_cursor: records.last().map(|record| record.node_id().to_string()),
+                start_cursor: edges.first().map(|edge| edge.cursor.clone()),
+                end_cursor: edges.last().map(|edge| edge.cursor.clone()),
             },
-            edges: records
-                .into_iter()
-                .map(|character| CharacterConfigEdge {
-                    cursor: character.node_id().to_string(),
-                    node: character,
-                })
-                .collect(),
+            edges,
         })
     }
 
@@ -982,7 +1693,7 @@ impl QueryRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let limit = encode_limit(first, last)?;
 
@@ -991,7 +1702,11 @@ impl QueryRoot {
                 let Some(NodeId::CharacterConfigSeed(character, stroke_count)) =
                     NodeId::from_id(&ID::new(after))
                 else {
-                    return Err(GraphqlUserError::from("after must be a valid cursor").into());
+                    return Err(GraphqlUserError::validation(
+                        &["after"],
+                        "after must be a valid cursor",
+                    )
+                    .into());
                 };
 
                 Ok((character, stroke_count))
@@ -1003,7 +1718,11 @@ impl QueryRoot {
                 let Some(NodeId::CharacterConfigSeed(character, stroke_count)) =
                     NodeId::from_id(&ID::new(before))
                 else {
-                    return Err(GraphqlUserError::from("before must be a valid cursor").into());
+                    return Err(GraphqlUserError::validation(
+                        &["before"],
+                        "before must be a valid cursor",
+                    )
+                    .into());
                 };
 
                 Ok((character, stroke_count))
@@ -1059,14 +1778,18 @@ impl QueryRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let limit = encode_limit(first, last)?;
 
         let after_id = after
             .map(|after| -> anyhow::Result<_> {
                 let Some(NodeId::GenerateTemplate(id)) = NodeId::from_id(&ID::new(after)) else {
-                    return Err(GraphqlUserError::from("after must be a valid cursor").into());
+                    return Err(GraphqlUserError::validation(
+                        &["after"],
+                        "after must be a valid cursor",
+                    )
+                    .into());
                 };
 
                 Ok(id)
@@ -1076,7 +1799,11 @@ impl QueryRoot {
         let before_id = before
             .map(|before| -> anyhow::Result<_> {
                 let Some(NodeId::GenerateTemplate(id)) = NodeId::from_id(&ID::new(before)) else {
-                    return Err(GraphqlUserError::from("before must be a valid cursor").into());
+                    return Err(GraphqlUserError::validation(
+                        &["before"],
+                        "before must be a valid cursor",
+                    )
+                    .into());
                 };
 
                 Ok(id)
@@ -1089,6 +1816,7 @@ impl QueryRoot {
             .load(
                 GenerateTemplatesLoaderParams {
                     user_id,
+                    disabled: false,
                     after_id,
                     before_id,
                     limit: limit.clone(),
@@ -1120,53 +1848,448 @@ impl QueryRoot {
                 .collect(),
         })
     }
-}
-#[derive(Clone, Debug)]
-pub struct MutationRoot;
 
-#[juniper::graphql_object(Context = AppCtx, name = "Mutation")]
-impl MutationRoot {
-    async fn create_figure_record(
+    // disabledにした(削除した)テンプレート
+    async fn trashed_generate_templates(
         ctx: &AppCtx,
-        input: CreateFigureRecordInput,
-    ) -> Result<CreateFigureRecordPayload, ApiError> {
-        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(ctx.pool.clone());
-
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<GenerateTemplateConnection, ApiError> {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
-        let record = figure_records_repository
-            .create(user_id, ctx.now, input.character.0, input.figure.0)
-            .await?;
+        let limit = encode_limit(first, last)?;
 
-        Ok(CreateFigureRecordPayload {
-            figure_record: Some(FigureRecord::from(record)),
-            errors: None,
+        let after_id = after
+            .map(|after| -> anyhow::Result<_> {
+                let Some(NodeId::GenerateTemplate(id)) = NodeId::from_id(&ID::new(after)) else {
+                    return Err(GraphqlUserError::validation(
+                        &["after"],
+                        "after must be a valid cursor",
+                    )
+                    .into());
+                };
+
+                Ok(id)
+            })
+            .transpose()?;
+
+        let before_id = before
+            .map(|before| -> anyhow::Result<_> {
+                let Some(NodeId::GenerateTemplate(id)) = NodeId::from_id(&ID::new(before)) else {
+                    return Err(GraphqlUserError::validation(
+                        &["before"],
+                        "before must be a valid cursor",
+                    )
+                    .into());
+                };
+
+                Ok(id)
+            })
+            .transpose()?;
+
+        let result = ctx
+            .loaders
+            .generate_templates_loader
+            .load(
+                GenerateTemplatesLoaderParams {
+                    user_id,
+                    disabled: true,
+                    after_id,
+                    before_id,
+                    limit: limit.clone(),
+                },
+                (),
+            )
+            .await
+            .context("load generate_template")??;
+
+        let records = result
+            .values
+            .into_iter()
+            .map(GenerateTemplate::from)
+            .collect::<Vec<_>>();
+
+        Ok(GenerateTemplateConnection {
+            page_info: PageInfo {
+                has_next_page: result.has_next && limit.kind() == entities::LimitKind::First,
+                has_previous_page: result.has_next && limit.kind() == entities::LimitKind::Last,
+                start_cursor: records.first().map(|record| record.node_id().to_string()),
+                end_cursor: records.last().map(|record| record.node_id().to_string()),
+            },
+            edges: records
+                .into_iter()
+                .map(|generate_template| GenerateTemplateEdge {
+                    cursor: generate_template.node_id().to_string(),
+                    node: generate_template,
+                })
+                .collect(),
         })
     }
 
-    async fn update_character_config(
+    // 文書を生成する前にまだ書く必要のある文字を調べるためのもの
+    async fn coverage(
         ctx: &AppCtx,
-        input: UpdateCharacterConfigInput,
-    ) -> Result<UpdateCharacterConfigPayload, ApiError> {
-        let mut character_configs_repository =
-            CharacterConfigsRepositoryImpl::new(ctx.pool.clone());
-
+        text: Option<String>,
+        characters: Option<Vec<CharacterValueScalar>>,
+        #[graphql(default = 1)] min_record_count: i32,
+    ) -> Result<Coverage, ApiError> {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
-        let character = input.character.0;
+        if min_record_count < 1 {
+            return Err(GraphqlUserError::validation(
+                &["minRecordCount"],
+                "minRecordCount must be positive",
+            )
+            .into());
+        }
+        if text.is_none() && characters.is_none() {
+            return Err(
+                GraphqlUserError::validation(&["text"], "text or characters is required").into(),
+            );
+        }
+        if characters
+            .as_ref()
+            .is_some_and(|characters| characters.len() > MAX_FILTER_CHARACTERS)
+        {
+            return Err(GraphqlUserError::quota_exceeded(
+                format!("characters must be at most {}", MAX_FILTER_CHARACTERS).as_str(),
+            )
+            .into());
+        }
+        let text = text
+            .map(entities::DocumentText::try_from)
+            .transpose()
+            .map_err(|_| {
+                GraphqlUserError::validation(&["text"], "text must be 1 to 2000 characters")
+            })?;
 
-        let stroke_count = entities::StrokeCount::try_from(input.stroke_count)
-            .map_err(|_| GraphqlUserError::from("stroke_count must be an non negative integer"))?;
+        let characters = text
+            .iter()
+            .flat_map(|text| {
+                text.value()
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(entities::Character::from)
+                    .collect::<Vec<_>>()
+            })
+            .chain(
+                characters
+                    .into_iter()
+                    .flatten()
+                    .map(|character| character.0),
+            )
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
 
-        let mut character_config = character_configs_repository
-            .get_by_ids(user_id.clone(), &[(character.clone(), stroke_count)])
-            .await
+        let stats_map = ctx
+            .loaders
+            .figure_record_stats_by_character_loader
+            .load_many(
+                FigureRecordStatsByCharacterLoaderParams { user_id },
+                characters.clone(),
+            )
+            .await
+            .context("load figure_record_stats")?;
+
+        let min_record_count = i64::from(min_record_count);
+        let characters = characters
+            .into_iter()
+            .map(|character| -> Result<_, ApiError> {
+                let stats = match stats_map.get(&character) {
+                    Some(stats) => stats.clone()?,
+                    None => Vec::new(),
+                };
+                let my_record_count = stats.iter().map(|stats| stats.my_count).sum::<i64>();
+                let shared_record_count = stats.iter().map(|stats| stats.other_count).sum::<i64>();
+                let status = if my_record_count >= min_record_count {
+                    CoverageStatus::Covered
+                } else if my_record_count + shared_record_count >= min_record_count {
+                    CoverageStatus::Shared
+                } else {
+                    CoverageStatus::Missing
+                };
+
+                Ok(CharacterCoverage {
+                    character: Character::from(character),
+                    my_record_count: i32::try_from(my_record_count).context("into i32")?,
+                    shared_record_count: i32::try_from(shared_record_count).context("into i32")?,
+                    status,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let count_by_status = |status| {
+            characters
+                .iter()
+                .filter(|character| character.status == status)
+                .count()
+        };
+        Ok(Coverage {
+            character_count: i32::try_from(characters.len()).context("into i32")?,
+            covered_count: i32::try_from(count_by_status(CoverageStatus::Covered))
+                .context("into i32")?,
+            shared_count: i32::try_from(count_by_status(CoverageStatus::Shared))
+                .context("into i32")?,
+            missing_count: i32::try_from(count_by_status(CoverageStatus::Missing))
+                .context("into i32")?,
+            characters,
+        })
+    }
+
+    // 自分と共有されたfigure_recordsを作成順に返す
+    async fn figure_records(
+        ctx: &AppCtx,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+        filter: Option<FigureRecordsFilter>,
+    ) -> Result<FigureRecordConnection, ApiError> {
+        let filter = filter.unwrap_or_default();
+
+        if filter
+            .characters
+            .as_ref()
+            .is_some_and(|characters| characters.len() > MAX_FILTER_CHARACTERS)
+        {
+            return Err(GraphqlUserError::quota_exceeded(
+                format!("characters must be at most {}", MAX_FILTER_CHARACTERS).as_str(),
+            )
+            .into());
+        }
+
+        let min_stroke_count = filter
+            .min_stroke_count
+            .map(entities::StrokeCount::try_from)
+            .transpose()
+            .map_err(|_| {
+                GraphqlUserError::validation(
+                    &["filter", "minStrokeCount"],
+                    "minStrokeCount must be a valid stroke count",
+                )
+            })?;
+        let max_stroke_count = filter
+            .max_stroke_count
+            .map(entities::StrokeCount::try_from)
+            .transpose()
+            .map_err(|_| {
+                GraphqlUserError::validation(
+                    &["filter", "maxStrokeCount"],
+                    "maxStrokeCount must be a valid stroke count",
+                )
+            })?;
+
+        query_figure_records(
+            ctx,
+            ports::FigureRecordsQueryFilter {
+                characters: filter.characters.map(|characters| {
+                    characters
+                        .into_iter()
+                        .map(|character| character.0)
+                        .collect()
+                }),
+                min_stroke_count,
+                max_stroke_count,
+                created_after: filter.created_after,
+                created_before: filter.created_before,
+                user_type: filter.user_type.map(ports::UserType::from),
+                disabled: match filter.disabled.unwrap_or(DisabledFilter::Exclude) {
+                    DisabledFilter::Exclude => Some(false),
+                    DisabledFilter::Only => Some(true),
+                    DisabledFilter::Include => None,
+                },
+            },
+            first,
+            after,
+            last,
+            before,
+        )
+        .await
+    }
+
+    // disabledにした(削除した)自分のfigure_records。削除した日時の新しい順
+    async fn trashed_figure_records(
+        ctx: &AppCtx,
+        first: Option<i32>,
+        after: Option<String>,
+        last: Option<i32>,
+        before: Option<String>,
+    ) -> Result<FigureRecordConnection, ApiError> {
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let limit = encode_limit(first, last)?;
+
+        let after = after
+            .map(|after| {
+                decode_trashed_figure_records_cursor(&after).ok_or_else(|| {
+                    GraphqlUserError::validation(&["after"], "after must be a valid cursor")
+                })
+            })
+            .transpose()?;
+
+        let before = before
+            .map(|before| {
+                decode_trashed_figure_records_cursor(&before).ok_or_else(|| {
+                    GraphqlUserError::validation(&["before"], "before must be a valid cursor")
+                })
+            })
+            .transpose()?;
+
+        let result = ctx
+            .loaders
+            .trashed_figure_records_loader
+            .load(
+                TrashedFigureRecordsLoaderParams {
+                    user_id,
+                    after,
+                    before,
+                    limit: limit.clone(),
+                },
+                (),
+            )
+            .await
+            .context("load trashed figure_records")??;
+
+        let edges = result
+            .values
+            .into_iter()
+            .map(|(figure_record, cursor)| FigureRecordEdge {
+                cursor: encode_trashed_figure_records_cursor(&cursor),
+                node: FigureRecord::from(figure_record),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(FigureRecordConnection {
+            page_info: PageInfo {
+                has_next_page: result.has_next && limit.kind() == entities::LimitKind::First,
+                has_previous_page: result.has_next && limit.kind() == entities::LimitKind::Last,
+                start_cursor: edges.first().map(|edge| edge.cursor.clone()),
+                end_cursor: edges.last().map(|edge| edge.cursor.clone()),
+            },
+            edges,
+        })
+    }
+}
+#[derive(Clone, Debug)]
+pub struct MutationRoot;
+
+#[juniper::graphql_object(Context = AppCtx, name = "Mutation")]
+impl MutationRoot {
+    async fn create_figure_record(
+        ctx: &AppCtx,
+        input: CreateFigureRecordInput,
+    ) -> Result<CreateFigureRecordPayload, ApiError> {
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        if let Err(e) = input.figure.0.validate() {
+            return Ok(CreateFigureRecordPayload {
+                figure_record: None,
+                reference_deviation: None,
+                errors: Some(vec![GraphqlErrorType::validation(
+                    &["input", "figure"],
+                    &e.to_string(),
+                )]),
+            });
+        }
+
+        let key = (input.character.0.clone(), input.figure.0.stroke_count());
+        let reference_figures = ctx
+            .loaders
+            .reference_figures_by_character_config_id_loader
+            .load(
+                ReferenceFiguresByCharacterConfigIdLoaderParams {
+                    user_id: user_id.clone(),
+                },
+                key,
+            )
+            .await
+            .context("load reference_figures")??;
+        let reference_deviation = StrokeDeviation::nearest(&input.figure.0, &reference_figures);
+
+        let record = figure_records_repository
+            .create(user_id, ctx.now, input.character.0, input.figure.0)
+            .await?;
+        event_bus::publish_or_log(
+            &ctx.pool,
+            &Event::FigureRecordsCreated {
+                user_id: record.user_id.clone(),
+                figure_records: vec![(record.id, record.character.clone())],
+            },
+        )
+        .await;
+
+        Ok(CreateFigureRecordPayload {
+            figure_record: Some(FigureRecord::from(record)),
+            reference_deviation,
+            errors: None,
+        })
+    }
+
+    // 不正なfigureはそれぞれのerrorsで返し、残りを1つのトランザクションで作成する
+    async fn create_figure_records(
+        ctx: &AppCtx,
+        inputs: Vec<CreateFigureRecordInput>,
+    ) -> Result<CreateFigureRecordsPayload, ApiError> {
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        if inputs.len() > MAX_CREATE_FIGURE_RECORDS {
+            return Err(GraphqlUserError::quota_exceeded(
+                format!("inputs must be at most {}", MAX_CREATE_FIGURE_RECORDS).as_str(),
+            )
+            .with_field(&["inputs"])
+            .into());
+        }
+
+        let results =
+            create_many_figure_records(&ctx.loaders, &ctx.pool, user_id, ctx.now, inputs).await?;
+
+        Ok(CreateFigureRecordsPayload { results })
+    }
+
+    async fn update_character_config(
+        ctx: &AppCtx,
+        input: UpdateCharacterConfigInput,
+    ) -> Result<UpdateCharacterConfigPayload, ApiError> {
+        let mut character_configs_repository =
+            CharacterConfigsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let character = input.character.0;
+
+        let stroke_count = entities::StrokeCount::try_from(input.stroke_count).map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "strokeCount"],
+                "stroke_count must be an non negative integer",
+            )
+        })?;
+
+        let mut character_config = character_configs_repository
+            .get_by_ids(user_id.clone(), &[(character.clone(), stroke_count)])
+            .await
             .context("get character_config")?
             .remove(&(character, stroke_count))
             .ok_or_else(|| anyhow::anyhow!("character_config not found"))?;
@@ -1174,9 +2297,10 @@ impl MutationRoot {
         let Ok(ratio) = input.ratio.map(entities::Ratio::try_from).transpose() else {
             return Ok(UpdateCharacterConfigPayload {
                 character_config: None,
-                errors: Some(vec![GraphqlErrorType {
-                    message: "ratio must be an non negative integer".to_string(),
-                }]),
+                errors: Some(vec![GraphqlErrorType::validation(
+                    &["input", "ratio"],
+                    "ratio must be an non negative integer",
+                )]),
             });
         };
 
@@ -1188,9 +2312,23 @@ impl MutationRoot {
             character_config = character_config.with_disabled(disabled);
         }
 
-        let character_config = character_configs_repository
-            .save(ctx.now, character_config)
-            .await?;
+        if let Some(expected_version) = decode_expected_version(input.expected_version)? {
+            character_config.version = expected_version;
+        }
+
+        let character_config = match catch_conflict(
+            character_configs_repository
+                .save(ctx.now, character_config)
+                .await,
+        )? {
+            Ok(character_config) => character_config,
+            Err(error) => {
+                return Ok(UpdateCharacterConfigPayload {
+                    character_config: None,
+                    errors: Some(vec![error]),
+                })
+            }
+        };
 
         Ok(UpdateCharacterConfigPayload {
             character_config: Some(CharacterConfig::from(character_config)),
@@ -1198,6 +2336,250 @@ impl MutationRoot {
         })
     }
 
+    // 1つのトランザクションで保存し、不正な入力やversionの競合はそれぞれのerrorsで返す
+    // 競合の検出はupdateCharacterConfigと同じく各inputのexpectedVersionで行う
+    async fn update_character_configs(
+        ctx: &AppCtx,
+        inputs: Vec<UpdateCharacterConfigInput>,
+    ) -> Result<UpdateCharacterConfigsPayload, ApiError> {
+        let mut character_configs_repository =
+            CharacterConfigsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        if inputs.len() > MAX_UPDATE_CHARACTER_CONFIGS {
+            return Err(GraphqlUserError::quota_exceeded(
+                format!("inputs must be at most {}", MAX_UPDATE_CHARACTER_CONFIGS).as_str(),
+            )
+            .with_field(&["inputs"])
+            .into());
+        }
+
+        let mut keys = HashSet::new();
+        let validated = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let i = i.to_string();
+                let stroke_count =
+                    entities::StrokeCount::try_from(input.stroke_count).map_err(|_| {
+                        GraphqlErrorType::validation(
+                            &["inputs", &i, "strokeCount"],
+                            "stroke_count must be an non negative integer",
+                        )
+                    })?;
+                let ratio = input
+                    .ratio
+                    .map(entities::Ratio::try_from)
+                    .transpose()
+                    .map_err(|_| {
+                        GraphqlErrorType::validation(
+                            &["inputs", &i, "ratio"],
+                            "ratio must be an non negative integer",
+                        )
+                    })?;
+                let expected_version =
+                    decode_expected_version(input.expected_version).map_err(|e| {
+                        GraphqlErrorType::from(e.with_field(&["inputs", &i, "expectedVersion"]))
+                    })?;
+                let key = (input.character.0, stroke_count);
+                if !keys.insert(key.clone()) {
+                    return Err(GraphqlErrorType::validation(
+                        &["inputs", &i],
+                        "character and stroke_count must be unique",
+                    ));
+                }
+                Ok((key, ratio, input.disabled, expected_version))
+            })
+            .collect::<Vec<_>>();
+
+        let mut character_configs = character_configs_repository
+            .get_by_ids(
+                user_id.clone(),
+                &validated
+                    .iter()
+                    .flatten()
+                    .map(|(key, _, _, _)| key.clone())
+                    .collect::<Vec<_>>(),
+            )
+            .await
+            .context("get character_configs")?;
+
+        let updates = validated
+            .iter()
+            .flatten()
+            .map(|(key, ratio, disabled, expected_version)| {
+                let mut character_config = character_configs
+                    .remove(key)
+                    .ok_or_else(|| anyhow::anyhow!("character_config not found"))?;
+                if let Some(ratio) = ratio {
+                    character_config = character_config.with_ratio(*ratio);
+                }
+                if let Some(disabled) = disabled {
+                    character_config = character_config.with_disabled(*disabled);
+                }
+                if let Some(expected_version) = expected_version {
+                    character_config.version = *expected_version;
+                }
+                Ok(character_config)
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let mut saved = character_configs_repository
+            .save_many(ctx.now, updates)
+            .await?
+            .into_iter();
+
+        let results = validated
+            .into_iter()
+            .map(|validated| -> Result<_, ApiError> {
+                let result = match validated {
+                    Ok(_) => saved
+                        .next()
+                        .context("save_many returned fewer results than inputs")?
+                        .map_err(GraphqlErrorType::from),
+                    Err(error) => Err(error),
+                };
+                Ok(match result {
+                    Ok(character_config) => UpdateCharacterConfigPayload {
+                        character_config: Some(CharacterConfig::from(character_config)),
+                        errors: None,
+                    },
+                    Err(error) => UpdateCharacterConfigPayload {
+                        character_config: None,
+                        errors: Some(vec![error]),
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(UpdateCharacterConfigsPayload { results })
+    }
+
+    async fn set_character_config_reference_figure(
+        ctx: &AppCtx,
+        input: SetCharacterConfigReferenceFigureInput,
+    ) -> Result<SetCharacterConfigReferenceFigurePayload, ApiError> {
+        let mut reference_figures_repository =
+            ReferenceFiguresRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let character = input.character.0;
+
+        let stroke_count = entities::StrokeCount::try_from(input.stroke_count).map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "strokeCount"],
+                "stroke_count must be an non negative integer",
+            )
+        })?;
+
+        let variant = input
+            .variant
+            .map(entities::ReferenceFigureVariant::try_from)
+            .transpose()
+            .map_err(|e| GraphqlUserError::validation(&["input", "variant"], &e.to_string()))?
+            .unwrap_or_default();
+
+        let reference_figure = input.reference_figure.map(|figure| figure.0);
+        if let Some(reference_figure) = &reference_figure {
+            let error = if let Err(e) = reference_figure.validate() {
+                Some(e.to_string())
+            } else if reference_figure.stroke_count() != stroke_count {
+                Some("reference_figure must have the same stroke count".to_string())
+            } else {
+                None
+            };
+
+            if let Some(message) = error {
+                return Ok(SetCharacterConfigReferenceFigurePayload {
+                    character_config: None,
+                    reference_figure: None,
+                    errors: Some(vec![GraphqlErrorType::validation(
+                        &["input", "referenceFigure"],
+                        &message,
+                    )]),
+                });
+            }
+        }
+
+        let expected_version = decode_expected_version(input.expected_version)?;
+
+        let key = (character.clone(), stroke_count);
+        let current = reference_figures_repository
+            .get_by_character_config_ids(user_id.clone(), &[key.clone()])
+            .await
+            .context("get reference_figures")?
+            .remove(&key)
+            .unwrap_or_default()
+            .into_iter()
+            .find(|reference_figure| reference_figure.variant == variant);
+
+        let result = match (reference_figure, current) {
+            (Some(figure), current) => {
+                let mut reference_figure = match current {
+                    Some(current) => current.with_figure(figure),
+                    None => entities::ReferenceFigure::new(
+                        user_id.clone(),
+                        character,
+                        stroke_count,
+                        variant,
+                        figure,
+                    ),
+                };
+                if let Some(expected_version) = expected_version {
+                    reference_figure.version = expected_version;
+                }
+
+                catch_conflict(
+                    reference_figures_repository
+                        .save(ctx.now, reference_figure)
+                        .await,
+                )?
+                .map(Some)
+            }
+            (None, Some(mut current)) => {
+                if let Some(expected_version) = expected_version {
+                    current.version = expected_version;
+                }
+
+                catch_conflict(reference_figures_repository.delete(current).await)?.map(|_| None)
+            }
+            // 既に手本がない
+            (None, None) => Ok(None),
+        };
+
+        let reference_figure = match result {
+            Ok(reference_figure) => reference_figure,
+            Err(error) => {
+                return Ok(SetCharacterConfigReferenceFigurePayload {
+                    character_config: None,
+                    reference_figure: None,
+                    errors: Some(vec![error]),
+                })
+            }
+        };
+
+        let character_config = ctx
+            .loaders
+            .character_config_by_id_loader
+            .load(CharacterConfigByIdLoaderParams { user_id }, key)
+            .await
+            .context("load character_config")??;
+
+        Ok(SetCharacterConfigReferenceFigurePayload {
+            character_config: Some(CharacterConfig::from(character_config)),
+            reference_figure: reference_figure.map(ReferenceFigure::from),
+            errors: None,
+        })
+    }
+
     async fn update_figure_record(
         ctx: &AppCtx,
         input: UpdateFigureRecordInput,
@@ -1207,26 +2589,56 @@ impl MutationRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let id = entities::FigureRecordId::from(input.id.0);
 
-        let figure_record = ctx
+        let mut figure_record = ctx
             .loaders
             .figure_record_by_id_loader
             .load(
                 FigureRecordByIdLoaderParams {
                     user_id: user_id.clone(),
+                    include_disabled: true,
                 },
                 id,
             )
             .await
             .context("load figure_record")??
-            .ok_or_else(|| GraphqlUserError::from("Not found"))?;
+            .filter(|figure_record| figure_record.user_id == user_id)
+            .ok_or_else(|| GraphqlUserError::not_found("Not found"))?;
 
-        let figure_record = figure_records_repository
-            .update(figure_record, input.disabled)
-            .await?;
+        if let Some(expected_version) = decode_expected_version(input.expected_version)? {
+            figure_record.version = expected_version;
+        }
+
+        let figure_record = match input.disabled {
+            Some(true) => figure_record.disable(ctx.now),
+            Some(false) => {
+                let Some(figure_record) = figure_record.restore(ctx.now) else {
+                    return Ok(UpdateFigureRecordPayload {
+                        figure_record: None,
+                        errors: Some(vec![GraphqlErrorType::from(GraphqlUserError::validation(
+                            &["input", "disabled"],
+                            "Retention period has expired",
+                        ))]),
+                    });
+                };
+                figure_record
+            }
+            None => figure_record,
+        };
+
+        let figure_record =
+            match catch_conflict(figure_records_repository.update(figure_record).await)? {
+                Ok(figure_record) => figure_record,
+                Err(error) => {
+                    return Ok(UpdateFigureRecordPayload {
+                        figure_record: None,
+                        errors: Some(vec![error]),
+                    })
+                }
+            };
 
         Ok(UpdateFigureRecordPayload {
             figure_record: Some(FigureRecord::from(figure_record)),
@@ -1234,6 +2646,118 @@ impl MutationRoot {
         })
     }
 
+    // 復元できるようにdisabledにするだけで、保持期間を過ぎるとジョブで物理削除される
+    async fn delete_figure_record(
+        ctx: &AppCtx,
+        input: DeleteFigureRecordInput,
+    ) -> Result<DeleteFigureRecordPayload, ApiError> {
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let id = entities::FigureRecordId::from(input.figure_record_id.0);
+        let figure_record = ctx
+            .loaders
+            .figure_record_by_id_loader
+            .load(
+                FigureRecordByIdLoaderParams {
+                    user_id: user_id.clone(),
+                    include_disabled: true,
+                },
+                id,
+            )
+            .await
+            .context("load figure_record")??
+            .filter(|figure_record| figure_record.user_id == user_id)
+            .ok_or_else(|| GraphqlUserError::not_found("Not found"))?;
+
+        let figure_record = if figure_record.disabled {
+            figure_record
+        } else {
+            match catch_conflict(
+                figure_records_repository
+                    .update(figure_record.disable(ctx.now))
+                    .await,
+            )? {
+                Ok(figure_record) => figure_record,
+                Err(error) => {
+                    return Ok(DeleteFigureRecordPayload {
+                        figure_record: None,
+                        errors: Some(vec![error]),
+                    })
+                }
+            }
+        };
+
+        Ok(DeleteFigureRecordPayload {
+            figure_record: Some(FigureRecord::from(figure_record)),
+            errors: None,
+        })
+    }
+
+    async fn restore_figure_record(
+        ctx: &AppCtx,
+        input: RestoreFigureRecordInput,
+    ) -> Result<RestoreFigureRecordPayload, ApiError> {
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let id = entities::FigureRecordId::from(input.figure_record_id.0);
+        let figure_record = ctx
+            .loaders
+            .figure_record_by_id_loader
+            .load(
+                FigureRecordByIdLoaderParams {
+                    user_id: user_id.clone(),
+                    include_disabled: true,
+                },
+                id,
+            )
+            .await
+            .context("load figure_record")??
+            .filter(|figure_record| figure_record.user_id == user_id)
+            .ok_or_else(|| GraphqlUserError::not_found("Not found"))?;
+
+        if !figure_record.disabled {
+            return Ok(RestoreFigureRecordPayload {
+                figure_record: Some(FigureRecord::from(figure_record)),
+                errors: None,
+            });
+        }
+
+        let Some(figure_record) = figure_record.restore(ctx.now) else {
+            return Ok(RestoreFigureRecordPayload {
+                figure_record: None,
+                errors: Some(vec![GraphqlErrorType::from(GraphqlUserError::validation(
+                    &["input", "figureRecordId"],
+                    "Retention period has expired",
+                ))]),
+            });
+        };
+        let figure_record =
+            match catch_conflict(figure_records_repository.update(figure_record).await)? {
+                Ok(figure_record) => figure_record,
+                Err(error) => {
+                    return Ok(RestoreFigureRecordPayload {
+                        figure_record: None,
+                        errors: Some(vec![error]),
+                    })
+                }
+            };
+
+        Ok(RestoreFigureRecordPayload {
+            figure_record: Some(FigureRecord::from(figure_record)),
+            errors: None,
+        })
+    }
+
     async fn update_user_config(
         ctx: &AppCtx,
         input: UpdateUserConfigInput,
@@ -1243,7 +2767,7 @@ impl MutationRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let mut user_config = user_config_repository
             .get(user_id.clone())
@@ -1261,18 +2785,37 @@ impl MutationRoot {
         }
 
         if let Some(random_level) = input.random_level {
-            let random_level = entities::RandomLevel::try_from(random_level)
-                .map_err(|_| GraphqlUserError::from("random_level is invalid"))?;
+            let random_level = entities::RandomLevel::try_from(random_level).map_err(|_| {
+                GraphqlUserError::validation(&["input", "randomLevel"], "random_level is invalid")
+            })?;
             user_config = user_config.with_random_level(random_level);
         }
 
         if let Some(shared_proportion) = input.shared_proportion {
             let shared_proportion = entities::SharedProportion::try_from(shared_proportion)
-                .map_err(|_| GraphqlUserError::from("shared_proportion is invalid"))?;
+                .map_err(|_| {
+                    GraphqlUserError::validation(
+                        &["input", "sharedProportion"],
+                        "shared_proportion is invalid",
+                    )
+                })?;
             user_config = user_config.with_shared_proportion(shared_proportion);
         }
 
-        let user_config = user_config_repository.save(ctx.now, user_config).await?;
+        if let Some(expected_version) = decode_expected_version(input.expected_version)? {
+            user_config.version = expected_version;
+        }
+
+        let user_config =
+            match catch_conflict(user_config_repository.save(ctx.now, user_config).await)? {
+                Ok(user_config) => user_config,
+                Err(error) => {
+                    return Ok(UpdateUserConfigPayload {
+                        user_config: None,
+                        errors: Some(vec![error]),
+                    })
+                }
+            };
 
         Ok(UpdateUserConfigPayload {
             user_config: Some(UserConfig::from(user_config)),
@@ -1289,13 +2832,14 @@ impl MutationRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
-        let mime_type = entities::MimeType::try_from(input.mime_type.clone())
-            .map_err(|_| GraphqlUserError::from("mime_type is invalid"))?;
+        let mime_type = entities::MimeType::try_from(input.mime_type.clone()).map_err(|_| {
+            GraphqlUserError::validation(&["input", "mimeType"], "mime_type is invalid")
+        })?;
 
         let size = entities::FileSize::try_from(input.size)
-            .map_err(|_| GraphqlUserError::from("size is invalid"))?;
+            .map_err(|_| GraphqlUserError::validation(&["input", "size"], "size is invalid"))?;
 
         let file = files_repository
             .create(user_id, ctx.now, mime_type, size)
@@ -1317,7 +2861,7 @@ impl MutationRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let file = ctx
             .loaders
@@ -1331,7 +2875,9 @@ impl MutationRoot {
             )
             .await
             .context("load file")??;
-        let file = file.ok_or_else(|| GraphqlUserError::from("File not found"))?;
+        let file = file.ok_or_else(|| {
+            GraphqlUserError::not_found("File not found").with_field(&["input", "id"])
+        })?;
 
         // TODO: ここでやることではない
         if file.verified {
@@ -1343,10 +2889,20 @@ impl MutationRoot {
 
         storage.verify(&file).await?;
 
-        let file = files_repository
-            .verified(ctx.now, file)
-            .await
-            .context("verify file")?;
+        let file = match catch_conflict(
+            files_repository
+                .verified(ctx.now, file)
+                .await
+                .context("verify file"),
+        )? {
+            Ok(file) => file,
+            Err(error) => {
+                return Ok(VerifyFilePayload {
+                    file: None,
+                    errors: Some(vec![error]),
+                })
+            }
+        };
 
         Ok(VerifyFilePayload {
             file: Some(File::from(file)),
@@ -1364,7 +2920,7 @@ impl MutationRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let id = entities::GenerateTemplateId::from(Ulid::from_datetime(ctx.now));
         let background_image_file = ctx
@@ -1379,25 +2935,62 @@ impl MutationRoot {
             )
             .await
             .context("load background image file")??
+            // エクスポートのzipなども検証済みのFileなので画像に限る
+            .filter(|file| file.mime_type.is_image())
             .ok_or_else(|| {
-                GraphqlUserError::from("background_image_file_id must be a valid file id")
+                GraphqlUserError::not_found("background_image_file_id must be a valid file id")
+                    .with_field(&["input", "backgroundImageFileId"])
+            })?;
+        let font_color = entities::Color::try_from(input.font_color).map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "fontColor"],
+                "font_color must be a valid hex color",
+            )
+        })?;
+        let writing_mode = entities::WritingMode::try_from(input.writing_mode).map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "writingMode"],
+                "writing_mode must be a valid writing mode",
+            )
+        })?;
+        let margin_block_start =
+            entities::Margin::try_from(input.margin_block_start).map_err(|_| {
+                GraphqlUserError::validation(
+                    &["input", "marginBlockStart"],
+                    "margin_block_start must be a valid margin",
+                )
             })?;
-        let font_color = entities::Color::try_from(input.font_color)
-            .map_err(|_| GraphqlUserError::from("font_color must be a valid hex color"))?;
-        let writing_mode = entities::WritingMode::try_from(input.writing_mode)
-            .map_err(|_| GraphqlUserError::from("writing_mode must be a valid writing mode"))?;
-        let margin_block_start = entities::Margin::try_from(input.margin_block_start)
-            .map_err(|_| GraphqlUserError::from("margin_block_start must be a valid margin"))?;
-        let margin_inline_start = entities::Margin::try_from(input.margin_inline_start)
-            .map_err(|_| GraphqlUserError::from("margin_inline_start must be a valid margin"))?;
-        let line_spacing = entities::Spacing::try_from(input.line_spacing)
-            .map_err(|_| GraphqlUserError::from("line_spacing must be a valid spacing"))?;
-        let letter_spacing = entities::Spacing::try_from(input.letter_spacing)
-            .map_err(|_| GraphqlUserError::from("letter_spacing must be a valid spacing"))?;
-        let font_size = entities::FontSize::try_from(input.font_size)
-            .map_err(|_| GraphqlUserError::from("font_size must be a valid font size"))?;
-        let font_weight = entities::FontWeight::try_from(input.font_weight)
-            .map_err(|_| GraphqlUserError::from("font_weight must be a valid font weight"))?;
+        let margin_inline_start =
+            entities::Margin::try_from(input.margin_inline_start).map_err(|_| {
+                GraphqlUserError::validation(
+                    &["input", "marginInlineStart"],
+                    "margin_inline_start must be a valid margin",
+                )
+            })?;
+        let line_spacing = entities::Spacing::try_from(input.line_spacing).map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "lineSpacing"],
+                "line_spacing must be a valid spacing",
+            )
+        })?;
+        let letter_spacing = entities::Spacing::try_from(input.letter_spacing).map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "letterSpacing"],
+                "letter_spacing must be a valid spacing",
+            )
+        })?;
+        let font_size = entities::FontSize::try_from(input.font_size).map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "fontSize"],
+                "font_size must be a valid font size",
+            )
+        })?;
+        let font_weight = entities::FontWeight::try_from(input.font_weight).map_err(|_| {
+            GraphqlUserError::validation(
+                &["input", "fontWeight"],
+                "font_weight must be a valid font weight",
+            )
+        })?;
 
         let generate_template = entities::GenerateTemplate {
             id,
@@ -1437,7 +3030,7 @@ impl MutationRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
         let id = entities::GenerateTemplateId::from(input.generate_template_id.0);
         let mut generate_template = ctx
             .loaders
@@ -1445,13 +3038,23 @@ impl MutationRoot {
             .load(GenerateTemplateByIdLoaderParams { user_id }, id)
             .await
             .context("load generate_template")??
-            .ok_or_else(|| GraphqlUserError::from("id must be a valid generate template id"))?;
+            .ok_or_else(|| {
+                GraphqlUserError::not_found("id must be a valid generate template id")
+                    .with_field(&["input", "generateTemplateId"])
+            })?;
 
         generate_template.disabled = true;
-        generate_templates_repository
-            .update(ctx.now, generate_template)
-            .await
-            .context("update generate_template")?;
+        if let Err(error) = catch_conflict(
+            generate_templates_repository
+                .update(ctx.now, generate_template)
+                .await
+                .context("update generate_template"),
+        )? {
+            return Ok(DeleteGenerateTemplatePayload {
+                id: NodeId::GenerateTemplate(id).to_id(),
+                errors: Some(vec![error]),
+            });
+        }
 
         Ok(DeleteGenerateTemplatePayload {
             id: NodeId::GenerateTemplate(id).to_id(),
@@ -1469,7 +3072,7 @@ impl MutationRoot {
         let user_id = ctx
             .user_id
             .clone()
-            .ok_or_else(|| GraphqlUserError::from("Authentication required"))?;
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
 
         let id = entities::GenerateTemplateId::from(input.generate_template_id.0);
 
@@ -1484,7 +3087,10 @@ impl MutationRoot {
             )
             .await
             .context("load generate_template")??
-            .ok_or_else(|| GraphqlUserError::from("id must be a valid generate template id"))?;
+            .ok_or_else(|| {
+                GraphqlUserError::not_found("id must be a valid generate template id")
+                    .with_field(&["input", "generateTemplateId"])
+            })?;
 
         if let Some(background_image_file_id) = input.background_image_file_id {
             let background_image_file = ctx
@@ -1499,15 +3105,21 @@ impl MutationRoot {
                 )
                 .await
                 .context("load background image file")??
+                .filter(|file| file.mime_type.is_image())
                 .ok_or_else(|| {
-                    GraphqlUserError::from("background_image_file_id must be a valid file id")
+                    GraphqlUserError::not_found("background_image_file_id must be a valid file id")
+                        .with_field(&["input", "backgroundImageFileId"])
                 })?;
             generate_template.background_image_file_id = background_image_file.id;
         }
 
         if let Some(font_color) = input.font_color {
-            generate_template.font_color = entities::Color::try_from(font_color)
-                .map_err(|_| GraphqlUserError::from("font_color must be a valid hex color"))?;
+            generate_template.font_color = entities::Color::try_from(font_color).map_err(|_| {
+                GraphqlUserError::validation(
+                    &["input", "fontColor"],
+                    "font_color must be a valid hex color",
+                )
+            })?;
         }
 
         if let Some(writing_mode) = input.writing_mode {
@@ -1517,55 +3129,918 @@ impl MutationRoot {
         if let Some(margin_block_start) = input.margin_block_start {
             generate_template.margin_block_start = entities::Margin::try_from(margin_block_start)
                 .map_err(|_| {
-                GraphqlUserError::from("margin_block_start must be a valid margin")
+                GraphqlUserError::validation(
+                    &["input", "marginBlockStart"],
+                    "margin_block_start must be a valid margin",
+                )
             })?;
         }
 
         if let Some(margin_inline_start) = input.margin_inline_start {
             generate_template.margin_inline_start = entities::Margin::try_from(margin_inline_start)
                 .map_err(|_| {
-                    GraphqlUserError::from("margin_inline_start must be a valid margin")
+                    GraphqlUserError::validation(
+                        &["input", "marginInlineStart"],
+                        "margin_inline_start must be a valid margin",
+                    )
                 })?;
         }
 
         if let Some(line_spacing) = input.line_spacing {
-            generate_template.line_spacing = entities::Spacing::try_from(line_spacing)
-                .map_err(|_| GraphqlUserError::from("line_spacing must be a valid spacing"))?;
+            generate_template.line_spacing =
+                entities::Spacing::try_from(line_spacing).map_err(|_| {
+                    GraphqlUserError::validation(
+                        &["input", "lineSpacing"],
+                        "line_spacing must be a valid spacing",
+                    )
+                })?;
         }
 
         if let Some(letter_spacing) = input.letter_spacing {
             generate_template.letter_spacing = entities::Spacing::try_from(letter_spacing)
-                .map_err(|_| GraphqlUserError::from("letter_spacing must be a valid spacing"))?;
+                .map_err(|_| {
+                    GraphqlUserError::validation(
+                        &["input", "letterSpacing"],
+                        "letter_spacing must be a valid spacing",
+                    )
+                })?;
         }
 
         if let Some(font_size) = input.font_size {
-            generate_template.font_size = entities::FontSize::try_from(font_size)
-                .map_err(|_| GraphqlUserError::from("font_size must be a valid font size"))?;
+            generate_template.font_size =
+                entities::FontSize::try_from(font_size).map_err(|_| {
+                    GraphqlUserError::validation(
+                        &["input", "fontSize"],
+                        "font_size must be a valid font size",
+                    )
+                })?;
         }
 
         if let Some(font_weight) = input.font_weight {
-            generate_template.font_weight = entities::FontWeight::try_from(font_weight)
-                .map_err(|_| GraphqlUserError::from("font_weight must be a valid font weight"))?;
+            generate_template.font_weight =
+                entities::FontWeight::try_from(font_weight).map_err(|_| {
+                    GraphqlUserError::validation(
+                        &["input", "fontWeight"],
+                        "font_weight must be a valid font weight",
+                    )
+                })?;
         }
 
-        let generate_template = generate_templates_repository
-            .update(ctx.now, generate_template)
-            .await
-            .context("update generate_template")?;
+        if let Some(expected_version) = decode_expected_version(input.expected_version)? {
+            generate_template.version = expected_version;
+        }
+
+        let generate_template = match catch_conflict(
+            generate_templates_repository
+                .update(ctx.now, generate_template)
+                .await
+                .context("update generate_template"),
+        )? {
+            Ok(generate_template) => generate_template,
+            Err(error) => {
+                return Ok(UpdateGenerateTemplatePayload {
+                    generate_template: None,
+                    errors: Some(vec![error]),
+                })
+            }
+        };
 
         Ok(UpdateGenerateTemplatePayload {
             generate_template: Some(GenerateTemplate::from(generate_template)),
             errors: None,
         })
     }
+
+    async fn generate_document(
+        ctx: &AppCtx,
+        input: GenerateDocumentInput,
+    ) -> Result<GenerateDocumentPayload, ApiError> {
+        let mut generation_jobs_repository = GenerationJobsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let text = entities::DocumentText::try_from(input.text).map_err(|_| {
+            GraphqlUserError::validation(&["input", "text"], "text must be 1 to 2000 characters")
+        })?;
+
+        let id = entities::GenerateTemplateId::from(input.generate_template_id.0);
+        let generate_template = ctx
+            .loaders
+            .generate_template_by_id_loader
+            .load(
+                GenerateTemplateByIdLoaderParams {
+                    user_id: user_id.clone(),
+                },
+                id,
+            )
+            .await
+            .context("load generate_template")??
+            .ok_or_else(|| {
+                GraphqlUserError::not_found(
+                    "generate_template_id must be a valid generate template id",
+                )
+                .with_field(&["input", "generateTemplateId"])
+            })?;
+
+        let generation_job = generation_jobs_repository
+            .create(entities::GenerationJob::new(
+                entities::GenerationJobId::from(Ulid::from_datetime(ctx.now)),
+                user_id.clone(),
+                entities::GenerationJobKind::Document {
+                    generate_template_id: generate_template.id,
+                    text,
+                },
+                ctx.now,
+            ))
+            .await
+            .context("create generation_job")?;
+
+        // 生成は重いのでワーカーで行う
+        let generation_job = enqueue_generation_job(
+            ctx,
+            &mut generation_jobs_repository,
+            generation_job,
+            jobs::GenerateDocument {
+                user_id: String::from(user_id),
+                generation_job_id: Ulid::from(generation_job.id).to_string(),
+            },
+        )
+        .await?;
+
+        Ok(GenerateDocumentPayload {
+            generation_job: Some(GenerationJob::from(generation_job)),
+            errors: None,
+        })
+    }
+
+    async fn export_my_data(ctx: &AppCtx) -> Result<ExportMyDataPayload, ApiError> {
+        let mut generation_jobs_repository = GenerationJobsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let generation_job = generation_jobs_repository
+            .create(entities::GenerationJob::new(
+                entities::GenerationJobId::from(Ulid::from_datetime(ctx.now)),
+                user_id.clone(),
+                entities::GenerationJobKind::DataExport,
+                ctx.now,
+            ))
+            .await
+            .context("create generation_job")?;
+
+        let generation_job = enqueue_generation_job(
+            ctx,
+            &mut generation_jobs_repository,
+            generation_job,
+            jobs::ExportUserData {
+                user_id: String::from(user_id),
+                generation_job_id: Ulid::from(generation_job.id).to_string(),
+            },
+        )
+        .await?;
+
+        Ok(ExportMyDataPayload {
+            generation_job: Some(GenerationJob::from(generation_job)),
+            errors: None,
+        })
+    }
+
+    async fn delete_my_account(ctx: &AppCtx) -> Result<DeleteMyAccountPayload, ApiError> {
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+        let mut account_deleter = AccountDeleter::new(ctx.pool.clone(), ctx.faktory_pool.clone());
+        account_deleter
+            .delete(user_id.clone())
+            .await
+            .context("delete account")?;
+        ctx.clear_session.store(true, Ordering::SeqCst);
+        event_bus::publish_or_log(&ctx.pool, &Event::UserSessionEnded { user_id }).await;
+
+        Ok(DeleteMyAccountPayload { errors: None })
+    }
 }
 
-pub type Schema = RootNode<'static, QueryRoot, MutationRoot, EmptySubscription<AppCtx>>;
+type GraphqlStream<T> = Pin<Box<dyn Stream<Item = Result<T, FieldError>> + Send>>;
+
+#[derive(GraphQLObject, Clone, Debug)]
+struct CharacterConfigSeedsUpdatedEvent {
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SubscriptionRoot;
+
+#[juniper::graphql_subscription(Context = AppCtx, name = "Subscription")]
+impl SubscriptionRoot {
+    // 自分のfigureRecordが作成された時(別の端末で書いたものをプレビューに反映するためのもの)
+    async fn figure_record_created(
+        ctx: &AppCtx,
+        character: Option<CharacterValueScalar>,
+    ) -> Result<GraphqlStream<FigureRecord>, ApiError> {
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+        let character = character.map(|character| character.0);
+        let pool = ctx.pool.clone();
+
+        // まとめて作成されたものは作成順に1件ずつ流す
+        let stream = event_stream(ctx)
+            .filter_map(move |event| {
+                let user_id = user_id.clone();
+                let character = character.clone();
+                let pool = pool.clone();
+                async move {
+                    let figure_record_ids =
+                        event.created_figure_record_ids(&user_id, character.as_ref());
+                    if figure_record_ids.is_empty() {
+                        return None;
+                    }
+
+                    let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool);
+                    Some(
+                        match figure_records_repository
+                            .get_by_ids(user_id, &figure_record_ids)
+                            .await
+                        {
+                            Ok(mut figure_records) => {
+                                figure_records.sort_by_key(|figure_record| figure_record.id);
+                                figure_records
+                                    .into_iter()
+                                    .map(|figure_record| Ok(FigureRecord::from(figure_record)))
+                                    .collect::<Vec<_>>()
+                            }
+                            Err(e) => vec![Err(ApiError::from(e).into_field_error())],
+                        },
+                    )
+                }
+            })
+            .flat_map(futures::stream::iter);
+
+        Ok(Box::pin(stream))
+    }
+
+    async fn character_config_seeds_updated(
+        ctx: &AppCtx,
+    ) -> Result<GraphqlStream<CharacterConfigSeedsUpdatedEvent>, ApiError> {
+        let stream = event_stream(ctx).filter_map(|event| async move {
+            let updated_at = event.seeds_updated_at()?;
+            Some(Ok(CharacterConfigSeedsUpdatedEvent { updated_at }))
+        });
+
+        Ok(Box::pin(stream))
+    }
+
+    // 自分のgenerationJobの状態が変わった時。id省略時は全て
+    async fn generation_job_updated(
+        ctx: &AppCtx,
+        id: Option<UlidScalar>,
+    ) -> Result<GraphqlStream<GenerationJob>, ApiError> {
+        let user_id = ctx
+            .user_id
+            .clone()
+            .ok_or_else(GraphqlUserError::unauthenticated)?;
+        let id = id.map(|id| entities::GenerationJobId::from(id.0));
+        let pool = ctx.pool.clone();
+
+        let stream = event_stream(ctx).filter_map(move |event| {
+            let user_id = user_id.clone();
+            let pool = pool.clone();
+            async move {
+                let generation_job_id = event.updated_generation_job_id(&user_id, id)?;
+
+                let mut generation_jobs_repository = GenerationJobsRepositoryImpl::new(pool);
+                match generation_jobs_repository
+                    .get_by_ids(user_id, &[generation_job_id])
+                    .await
+                {
+                    Ok(generation_jobs) => generation_jobs
+                        .into_iter()
+                        .next()
+                        .map(|generation_job| Ok(GenerationJob::from(generation_job))),
+                    Err(e) => Some(Err(ApiError::from(e).into_field_error())),
+                }
+            }
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+// MutationRoot.createFigureRecordsの実体。不正な入力はその要素のerrorsにし、結果はinputsと同じ順で返す
+async fn create_many_figure_records(
+    loaders: &Loaders,
+    pool: &sqlx::PgPool,
+    user_id: entities::UserId,
+    now: DateTime<Utc>,
+    inputs: Vec<CreateFigureRecordInput>,
+) -> Result<Vec<CreateFigureRecordPayload>, ApiError> {
+    let validated = inputs
+        .into_iter()
+        .enumerate()
+        .map(|(i, input)| {
+            input
+                .figure
+                .0
+                .validate()
+                .map(|_| (input.character.0, input.figure.0))
+                .map_err(|e| {
+                    GraphqlErrorType::validation(
+                        &["inputs", &i.to_string(), "figure"],
+                        &e.to_string(),
+                    )
+                })
+        })
+        .collect::<Vec<_>>();
+
+    let keys = validated
+        .iter()
+        .flatten()
+        .map(|(character, figure)| (character.clone(), figure.stroke_count()))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let reference_figures = loaders
+        .reference_figures_by_character_config_id_loader
+        .load_many(
+            ReferenceFiguresByCharacterConfigIdLoaderParams {
+                user_id: user_id.clone(),
+            },
+            keys,
+        )
+        .await
+        .context("load reference_figures")?;
+
+    let mut reference_deviations = Vec::new();
+    for (character, figure) in validated.iter().flatten() {
+        let reference_deviation = match reference_figures
+            .get(&(character.clone(), figure.stroke_count()))
+            .cloned()
+            .transpose()?
+        {
+            Some(reference_figures) => StrokeDeviation::nearest(figure, &reference_figures),
+            None => None,
+        };
+        reference_deviations.push(reference_deviation);
+    }
+
+    let figures = validated.iter().flatten().cloned().collect::<Vec<_>>();
+    let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool.clone());
+    let records = figure_records_repository
+        .create_many(user_id.clone(), now, &mut ulid::Generator::new(), figures)
+        .await?;
+    if !records.is_empty() {
+        event_bus::publish_or_log(
+            pool,
+            &Event::FigureRecordsCreated {
+                user_id,
+                figure_records: records
+                    .iter()
+                    .map(|record| (record.id, record.character.clone()))
+                    .collect(),
+            },
+        )
+        .await;
+    }
+
+    let mut created = records.into_iter().zip(reference_deviations);
+    let results = validated
+        .into_iter()
+        .map(|validated| -> Result<_, ApiError> {
+            match validated {
+                Ok(_) => {
+                    let (record, reference_deviation) = created
+                        .next()
+                        .context("create_many returned fewer records than inputs")?;
+                    Ok(CreateFigureRecordPayload {
+                        figure_record: Some(FigureRecord::from(record)),
+                        reference_deviation,
+                        errors: None,
+                    })
+                }
+                Err(error) => Ok(CreateFigureRecordPayload {
+                    figure_record: None,
+                    reference_deviation: None,
+                    errors: Some(vec![error]),
+                }),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(results)
+}
+
+// QueryRoot.nodesの実体。種類ごとにまとめて読み込み、idsと同じ順で返す
+async fn load_nodes(
+    loaders: &Loaders,
+    pool: &sqlx::PgPool,
+    user_id: entities::UserId,
+    ids: &[ID],
+) -> Result<Vec<Option<NodeValue>>, ApiError> {
+    let ids = ids.iter().map(NodeId::from_id).collect::<Vec<_>>();
+
+    let mut figure_record_ids = HashSet::new();
+    let mut character_config_ids = HashSet::new();
+    let mut character_config_seed_ids = HashSet::new();
+    let mut file_ids = HashSet::new();
+    let mut generate_template_ids = HashSet::new();
+    let mut generation_job_ids = HashSet::new();
+    let mut has_user_config = false;
+    for id in ids.iter().flatten() {
+        match id {
+            NodeId::FigureRecord(id) => {
+                figure_record_ids.insert(*id);
+            }
+            NodeId::CharacterConfig(_, character, stroke_count) => {
+                character_config_ids.insert((character.clone(), *stroke_count));
+            }
+            NodeId::Character(_) => {}
+            NodeId::UserConfig(_) => {
+                has_user_config = true;
+            }
+            NodeId::CharacterConfigSeed(character, stroke_count) => {
+                character_config_seed_ids.insert((character.clone(), *stroke_count));
+            }
+            NodeId::File(id) => {
+                file_ids.insert(*id);
+            }
+            NodeId::GenerateTemplate(id) => {
+                generate_template_ids.insert(*id);
+            }
+            NodeId::GenerationJob(id) => {
+                generation_job_ids.insert(*id);
+            }
+        }
+    }
+
+    // 種類ごとにまとめて読み込む
+    let figure_records = loaders
+        .figure_record_by_id_loader
+        .load_many(
+            FigureRecordByIdLoaderParams {
+                user_id: user_id.clone(),
+                include_disabled: false,
+            },
+            figure_record_ids.into_iter().collect(),
+        )
+        .await
+        .context("load FigureRecord")?;
+    let character_configs = loaders
+        .character_config_by_id_loader
+        .load_many(
+            CharacterConfigByIdLoaderParams {
+                user_id: user_id.clone(),
+            },
+            character_config_ids.into_iter().collect(),
+        )
+        .await
+        .context("load character_config")?;
+    let character_config_seeds = loaders
+        .character_config_seed_by_id_loader
+        .load_many(
+            CharacterConfigSeedByIdLoaderParams {},
+            character_config_seed_ids.into_iter().collect(),
+        )
+        .await
+        .context("load character_config_seed")?;
+    let files = loaders
+        .file_by_id_loader
+        .load_many(
+            FileByIdLoaderParams {
+                user_id: user_id.clone(),
+                verified_only: false,
+            },
+            file_ids.into_iter().collect(),
+        )
+        .await
+        .context("load file")?;
+    let generate_templates = loaders
+        .generate_template_by_id_loader
+        .load_many(
+            GenerateTemplateByIdLoaderParams {
+                user_id: user_id.clone(),
+            },
+            generate_template_ids.into_iter().collect(),
+        )
+        .await
+        .context("load generate_template")?;
+    let generation_jobs = loaders
+        .generation_job_by_id_loader
+        .load_many(
+            GenerationJobByIdLoaderParams {
+                user_id: user_id.clone(),
+            },
+            generation_job_ids.into_iter().collect(),
+        )
+        .await
+        .context("load generation_job")?;
+    let user_config = if has_user_config {
+        let mut user_config_repository = UserConfigsRepositoryImpl::new(pool.clone());
+        Some(user_config_repository.get(user_id).await?)
+    } else {
+        None
+    };
+
+    ids.into_iter()
+        .map(|id| -> Result<_, ApiError> {
+            let Some(id) = id else {
+                return Ok(None);
+            };
+            Ok(match id {
+                NodeId::FigureRecord(id) => figure_records
+                    .get(&id)
+                    .cloned()
+                    .transpose()?
+                    .flatten()
+                    .map(FigureRecord::from)
+                    .map(NodeValue::FigureRecord),
+                NodeId::CharacterConfig(_, character, stroke_count) => character_configs
+                    .get(&(character, stroke_count))
+                    .cloned()
+                    .transpose()?
+                    .map(CharacterConfig::from)
+                    .map(NodeValue::CharacterConfig),
+                NodeId::Character(character) => {
+                    Some(NodeValue::Character(Character::from(character)))
+                }
+                NodeId::UserConfig(_) => user_config
+                    .clone()
+                    .map(UserConfig)
+                    .map(NodeValue::UserConfig),
+                NodeId::CharacterConfigSeed(character, stroke_count) => character_config_seeds
+                    .get(&(character, stroke_count))
+                    .cloned()
+                    .transpose()?
+                    .flatten()
+                    .map(CharacterConfigSeed::from)
+                    .map(NodeValue::CharacterConfigSeed),
+                NodeId::File(id) => files
+                    .get(&id)
+                    .cloned()
+                    .transpose()?
+                    .flatten()
+                    .map(File::from)
+                    .map(NodeValue::File),
+                NodeId::GenerateTemplate(id) => generate_templates
+                    .get(&id)
+                    .cloned()
+                    .transpose()?
+                    .flatten()
+                    .map(GenerateTemplate::from)
+                    .map(NodeValue::GenerateTemplate),
+                NodeId::GenerationJob(id) => generation_jobs
+                    .get(&id)
+                    .cloned()
+                    .transpose()?
+                    .flatten()
+                    .map(GenerationJob::from)
+                    .map(NodeValue::GenerationJob),
+            })
+        })
+        .collect()
+}
+
+// ジョブを積めなかった場合はキュー待ちのまま残らないように失敗にする
+async fn enqueue_generation_job<'de, J: Job<'de>>(
+    ctx: &AppCtx,
+    generation_jobs_repository: &mut GenerationJobsRepositoryImpl<sqlx::PgPool>,
+    generation_job: entities::GenerationJob,
+    job: J,
+) -> Result<entities::GenerationJob, ApiError> {
+    if let Err(e) = job.enqueue(&ctx.faktory_pool).await {
+        let generation_job = generation_jobs_repository
+            .update(
+                ctx.now,
+                generation_job.failed("Failed to enqueue job".to_string()),
+            )
+            .await
+            .context("update generation_job")?;
+        event_bus::publish_or_log(
+            &ctx.pool,
+            &Event::GenerationJobUpdated {
+                user_id: generation_job.user_id.clone(),
+                generation_job_id: generation_job.id,
+            },
+        )
+        .await;
+        return Err(e.context(format!("enqueue {}", J::JOB_TYPE)).into());
+    }
+
+    Ok(generation_job)
+}
+
+// 購読が追いつかず取りこぼしたイベントは捨てる
+fn event_stream(ctx: &AppCtx) -> impl Stream<Item = Event> + Send + 'static {
+    BroadcastStream::new(ctx.event_bus.subscribe()).filter_map(|event| async move { event.ok() })
+}
+
+// QueryRoot.figureRecords/characterConfigs/coverageで一度に指定できる文字の数
+const MAX_FILTER_CHARACTERS: usize = 100;
+
+// Character.averageFigureで平均に使う自分/他人それぞれのfigure_recordの最大数
+// 変更する場合はaverageFigureのdescriptionも合わせて変更する
+const AVERAGE_FIGURE_MAX_RECORDS_PER_USER_TYPE: i32 = 100;
+
+// QueryRoot.nodesで一度に指定できるIDの数
+const MAX_NODE_IDS: usize = 100;
+
+// MutationRoot.createFigureRecordsで一度に作成できる数
+const MAX_CREATE_FIGURE_RECORDS: usize = 100;
+
+// MutationRoot.updateCharacterConfigsで一度に更新できる数
+const MAX_UPDATE_CHARACTER_CONFIGS: usize = 1000;
+
+async fn query_figure_records(
+    ctx: &AppCtx,
+    filter: ports::FigureRecordsQueryFilter,
+    first: Option<i32>,
+    after: Option<String>,
+    last: Option<i32>,
+    before: Option<String>,
+) -> Result<FigureRecordConnection, ApiError> {
+    let user_id = ctx
+        .user_id
+        .clone()
+        .ok_or_else(GraphqlUserError::unauthenticated)?;
+
+    let limit = encode_limit(first, last)?;
+
+    let after_id = after
+        .map(|after| -> anyhow::Result<_> {
+            let Some(NodeId::FigureRecord(id)) = NodeId::from_id(&ID::new(after)) else {
+                return Err(GraphqlUserError::validation(
+                    &["after"],
+                    "after must be a valid cursor",
+                )
+                .into());
+            };
+
+            Ok(id)
+        })
+        .transpose()?;
+
+    let before_id = before
+        .map(|before| -> anyhow::Result<_> {
+            let Some(NodeId::FigureRecord(id)) = NodeId::from_id(&ID::new(before)) else {
+                return Err(GraphqlUserError::validation(
+                    &["before"],
+                    "before must be a valid cursor",
+                )
+                .into());
+            };
+
+            Ok(id)
+        })
+        .transpose()?;
+
+    let result = ctx
+        .loaders
+        .figure_records_loader
+        .load(
+            FigureRecordsLoaderParams {
+                user_id,
+                filter,
+                after_id,
+                before_id,
+                limit: limit.clone(),
+            },
+            (),
+        )
+        .await
+        .context("load figure_records")??;
+
+    let records = result
+        .values
+        .into_iter()
+        .map(FigureRecord::from)
+        .collect::<Vec<_>>();
+
+    Ok(FigureRecordConnection {
+        page_info: PageInfo {
+            has_next_page: result.has_next && limit.kind() == entities::LimitKind::First,
+            has_previous_page: result.has_next && limit.kind() == entities::LimitKind::Last,
+            start_cursor: records.first().map(|record| record.node_id().to_string()),
+            end_cursor: records.last().map(|record| record.node_id().to_string()),
+        },
+        edges: records
+            .into_iter()
+            .map(|figure_record| FigureRecordEdge {
+                cursor: figure_record.node_id().to_string(),
+                node: figure_record,
+            })
+            .collect(),
+    })
+}
+
+pub type Schema = RootNode<'static, QueryRoot, MutationRoot, SubscriptionRoot>;
 
 pub fn create_schema() -> Schema {
-    Schema::new(
-        QueryRoot {},
-        MutationRoot {},
-        EmptySubscription::<AppCtx>::new(),
-    )
+    Schema::new(QueryRoot {}, MutationRoot {}, SubscriptionRoot {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn figure() -> entities::Figure {
+        entities::Figure::from_json(
+            r#"{"strokes":[{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]}],"width":1,"height":1}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stroke_deviation_nearest() {
+        let reference_figure = |variant: &str, json: &str| {
+            entities::ReferenceFigure::new(
+                entities::UserId::from("user".to_string()),
+                entities::Character::from('あ'),
+                entities::StrokeCount::try_from(1).unwrap(),
+                entities::ReferenceFigureVariant::try_from(variant.to_string()).unwrap(),
+                entities::Figure::from_json(json).unwrap(),
+            )
+        };
+        // 逆向きの字形と同じ向きの字形。同じ向きのほうが近い
+        let reference_figures = vec![
+            reference_figure(
+                "a",
+                r#"{"strokes":[{"points":[{"x":1,"y":1,"z":1},{"x":0,"y":0,"z":1}]}],"width":1,"height":1}"#,
+            ),
+            reference_figure(
+                "b",
+                r#"{"strokes":[{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]}],"width":1,"height":1}"#,
+            ),
+        ];
+
+        let deviation = StrokeDeviation::nearest(&figure(), &reference_figures).unwrap();
+        assert_eq!(deviation.variant, "b");
+        assert_eq!(deviation.distance, 0.0);
+        assert!(deviation.reversed_strokes.is_empty());
+
+        assert!(StrokeDeviation::nearest(&figure(), &[]).is_none());
+    }
+
+    #[sqlx::test]
+    async fn test_load_nodes(pool: sqlx::PgPool) {
+        let user_id = entities::UserId::from("user".to_string());
+        let other_user_id = entities::UserId::from("other_user".to_string());
+        let now = Utc::now();
+
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool.clone());
+        let figure_record = figure_records_repository
+            .create(
+                user_id.clone(),
+                now,
+                entities::Character::from('あ'),
+                figure(),
+            )
+            .await
+            .unwrap();
+        let other_figure_record = figure_records_repository
+            .create(
+                other_user_id.clone(),
+                now,
+                entities::Character::from('あ'),
+                figure(),
+            )
+            .await
+            .unwrap();
+
+        let figure_record_id = NodeId::FigureRecord(figure_record.id).to_id();
+        let character_id = NodeId::Character(entities::Character::from('い')).to_id();
+        let user_config_id = NodeId::UserConfig(user_id.clone()).to_id();
+        let ids = vec![
+            figure_record_id.clone(),
+            ID::new("invalid"),
+            character_id.clone(),
+            NodeId::FigureRecord(entities::FigureRecordId::from(Ulid::new())).to_id(),
+            NodeId::FigureRecord(other_figure_record.id).to_id(),
+            user_config_id.clone(),
+            figure_record_id.clone(),
+        ];
+
+        let nodes = load_nodes(&Loaders::new(&pool), &pool, user_id, &ids)
+            .await
+            .unwrap();
+        // 存在しない・他人のもの・不正なIDはnullになり、重複したIDもそれぞれ返る
+        assert_eq!(
+            nodes
+                .iter()
+                .map(|node| node.as_ref().map(|node| node.node_id()))
+                .collect::<Vec<_>>(),
+            vec![
+                Some(figure_record_id.clone()),
+                None,
+                Some(character_id),
+                None,
+                None,
+                Some(user_config_id),
+                Some(figure_record_id),
+            ]
+        );
+
+        let nodes = load_nodes(&Loaders::new(&pool), &pool, other_user_id, &[])
+            .await
+            .unwrap();
+        assert!(nodes.is_empty());
+    }
+
+    #[sqlx::test]
+    async fn test_create_many_figure_records(pool: sqlx::PgPool) {
+        let user_id = entities::UserId::from("user".to_string());
+        let input = |character: char, figure: entities::Figure| CreateFigureRecordInput {
+            character: CharacterValueScalar(entities::Character::from(character)),
+            figure: FigureScalar(figure),
+        };
+        let too_few_points = entities::Figure::from_json(
+            r#"{"strokes":[{"points":[{"x":0,"y":0,"z":1}]}],"width":1,"height":1}"#,
+        )
+        .unwrap();
+
+        let results = create_many_figure_records(
+            &Loaders::new(&pool),
+            &pool,
+            user_id.clone(),
+            Utc::now(),
+            vec![
+                input('あ', figure()),
+                input('い', too_few_points),
+                input('う', figure()),
+                input('え', figure()),
+            ],
+        )
+        .await
+        .unwrap();
+
+        // 不正な要素だけがerrorsになり、結果はinputsと同じ順
+        assert_eq!(
+            results
+                .iter()
+                .map(|result| result
+                    .figure_record
+                    .as_ref()
+                    .map(|figure_record| figure_record.0.character.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                Some(entities::Character::from('あ')),
+                None,
+                Some(entities::Character::from('う')),
+                Some(entities::Character::from('え')),
+            ]
+        );
+        let errors = results
+            .iter()
+            .map(|result| {
+                result.errors.as_ref().map(|errors| {
+                    errors
+                        .iter()
+                        .map(|error| (error.code, error.field.clone()))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                None,
+                Some(vec![(
+                    GraphqlErrorCode::Validation,
+                    Some(vec![
+                        "inputs".to_string(),
+                        "1".to_string(),
+                        "figure".to_string()
+                    ])
+                )]),
+                None,
+                None,
+            ]
+        );
+
+        // 同じ時刻に作成してもIDは入力順
+        let ids = results
+            .iter()
+            .filter_map(|result| result.figure_record.as_ref())
+            .map(|figure_record| figure_record.0.id)
+            .collect::<Vec<_>>();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool.clone());
+        assert_eq!(
+            figure_records_repository
+                .get_by_ids(user_id, &ids)
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+    }
 }
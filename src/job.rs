@@ -2,6 +2,7 @@ use r2d2::Pool;
 
 use sqlx::PgPool;
 
+use crate::app_config::AppConfig;
 use crate::faktory::FaktoryConnectionManager;
 use crate::jobs;
 use faktory::ConsumerBuilder;
@@ -49,11 +50,17 @@ pub trait Job<'de>: Deserialize<'de> + Serialize + 'static + Send {
 #[derive(Debug, Clone)]
 pub struct Ctx {
     pub pool: PgPool,
+    pub config: AppConfig,
+    pub s3_client: aws_sdk_s3::Client,
 }
 
 pub fn run_worker(url: &str, ctx: Ctx) -> anyhow::Result<()> {
     let mut c = ConsumerBuilder::default();
     jobs::UpdateSeeds::register(&mut c, &ctx);
+    jobs::PurgeFigureRecords::register(&mut c, &ctx);
+    jobs::GenerateDocument::register(&mut c, &ctx);
+    jobs::ExportUserData::register(&mut c, &ctx);
+    jobs::DeleteStorageObjects::register(&mut c, &ctx);
 
     let c = c.connect(Some(url)).unwrap();
     // 終了しないタスクはtokioのspawn_blockingを使ってはいけない
@@ -1,10 +1,25 @@
+use std::str::FromStr;
+
 use crate::{
-    adapters::CharacterConfigSeedsRepositoryImpl,
+    adapters::{
+        CharacterConfigSeedsRepositoryImpl, FigureRecordsRepositoryImpl,
+        GenerateTemplatesRepositoryImpl, GenerationJobsRepositoryImpl, StorageImpl,
+    },
+    data_exporter::DataExporter,
+    document_generator::DocumentGenerator,
+    entities,
+    event_bus::{self, Event},
     job::{Ctx, Job},
-    ports::CharacterConfigSeedsRepository,
+    ports::{
+        CharacterConfigSeedsRepository, FigureRecordsRepository, GenerateTemplatesRepository,
+        GenerationJobsRepository, Storage,
+    },
 };
-use chrono::Utc;
+use anyhow::{anyhow, Context};
+use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use ulid::Ulid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateSeeds {}
@@ -18,6 +33,245 @@ impl<'de> Job<'de> for UpdateSeeds {
             CharacterConfigSeedsRepositoryImpl::new(ctx.pool.clone());
 
         character_config_seeds_repository.update_seeds(now).await?;
+        event_bus::publish_or_log(
+            &ctx.pool,
+            &Event::CharacterConfigSeedsUpdated { updated_at: now },
+        )
+        .await;
+        Ok(())
+    }
+}
+
+// 削除してから保持期間を過ぎたfigure_recordsを物理削除する
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurgeFigureRecords {}
+
+impl<'de> Job<'de> for PurgeFigureRecords {
+    const JOB_TYPE: &'static str = "PURGE_FIGURE_RECORDS";
+
+    async fn run(self, ctx: Ctx) -> Result<(), anyhow::Error> {
+        let now = Utc::now();
+        let mut figure_records_repository = FigureRecordsRepositoryImpl::new(ctx.pool.clone());
+        let mut storage = StorageImpl::new(ctx.config.clone(), ctx.s3_client.clone());
+
+        let before = now - Duration::days(entities::FIGURE_RECORD_RETENTION_DAYS);
+        let ids = figure_records_repository
+            .get_disabled_before(before)
+            .await?;
+        // 行を先に消すとサムネイルの削除に失敗したときにキーが分からなくなるので、サムネイルから消す
+        // 途中で失敗しても行は残るので次回やり直せる
+        for (user_id, id) in &ids {
+            storage
+                .delete_by_prefix(&entities::FileKey::figure_record_thumbnail_prefix(
+                    user_id, *id,
+                ))
+                .await
+                .context("delete thumbnails")?;
+        }
+        figure_records_repository
+            .delete_disabled_before(
+                before,
+                &ids.into_iter().map(|(_, id)| id).collect::<Vec<_>>(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateDocument {
+    pub user_id: String,
+    pub generation_job_id: String,
+}
+
+impl<'de> Job<'de> for GenerateDocument {
+    const JOB_TYPE: &'static str = "GENERATE_DOCUMENT";
+
+    async fn run(self, ctx: Ctx) -> Result<(), anyhow::Error> {
+        let mut generation_jobs_repository = GenerationJobsRepositoryImpl::new(ctx.pool.clone());
+        let mut generate_templates_repository =
+            GenerateTemplatesRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = entities::UserId::from(self.user_id);
+        let Some(generation_job) = start_generation_job(
+            &ctx.pool,
+            &mut generation_jobs_repository,
+            user_id.clone(),
+            &self.generation_job_id,
+        )
+        .await?
+        else {
+            return Ok(());
+        };
+
+        let entities::GenerationJobKind::Document {
+            generate_template_id,
+            text,
+        } = generation_job.kind.clone()
+        else {
+            return Err(anyhow!("generation_job is not a document job"));
+        };
+
+        let generate_template = generate_templates_repository
+            .get_by_ids(user_id.clone(), &[generate_template_id])
+            .await?
+            .into_iter()
+            .next();
+        let Some(generate_template) = generate_template else {
+            generation_jobs_repository
+                .update(
+                    Utc::now(),
+                    generation_job.failed("Generate template not found".to_string()),
+                )
+                .await?;
+            return Ok(());
+        };
+
+        let mut document_generator = DocumentGenerator::new(
+            ctx.pool.clone(),
+            StorageImpl::new(ctx.config.clone(), ctx.s3_client.clone()),
+        );
+        let result = document_generator
+            .generate(
+                user_id,
+                Utc::now(),
+                &generate_template,
+                &text,
+                generation_job.created_at.timestamp_millis() as u64,
+            )
+            .await;
+
+        // 失敗してもリトライはせずジョブの状態として返す
+        let generation_job = match result {
+            Ok(file) => generation_job.succeeded(file.id),
+            Err(e) => {
+                tracing::error!("generate document error: {:?}", e);
+                generation_job.failed("Failed to generate document".to_string())
+            }
+        };
+        let generation_job = generation_jobs_repository
+            .update(Utc::now(), generation_job)
+            .await?;
+        publish_generation_job_updated(&ctx.pool, &generation_job).await;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportUserData {
+    pub user_id: String,
+    pub generation_job_id: String,
+}
+
+impl<'de> Job<'de> for ExportUserData {
+    const JOB_TYPE: &'static str = "EXPORT_USER_DATA";
+
+    async fn run(self, ctx: Ctx) -> Result<(), anyhow::Error> {
+        let mut generation_jobs_repository = GenerationJobsRepositoryImpl::new(ctx.pool.clone());
+
+        let user_id = entities::UserId::from(self.user_id);
+        let Some(generation_job) = start_generation_job(
+            &ctx.pool,
+            &mut generation_jobs_repository,
+            user_id.clone(),
+            &self.generation_job_id,
+        )
+        .await?
+        else {
+            return Ok(());
+        };
+
+        let mut data_exporter = DataExporter::new(
+            ctx.pool.clone(),
+            StorageImpl::new(ctx.config.clone(), ctx.s3_client.clone()),
+        );
+        let result = data_exporter.export(user_id, Utc::now()).await;
+
+        let generation_job = match result {
+            Ok(file) => generation_job.succeeded(file.id),
+            Err(e) => {
+                tracing::error!("export user data error: {:?}", e);
+                generation_job.failed("Failed to export data".to_string())
+            }
+        };
+        let generation_job = generation_jobs_repository
+            .update(Utc::now(), generation_job)
+            .await?;
+        publish_generation_job_updated(&ctx.pool, &generation_job).await;
+
+        Ok(())
+    }
+}
+
+// キュー待ちのジョブを実行中にする。リトライなどで既に処理されたジョブ、他のワーカーが実行中のジョブならNone
+// 実行中のままタイムアウトしたジョブは引き継ぐ。同時に引き継いだ場合はversionの不一致で片方が失敗する
+async fn start_generation_job(
+    pool: &PgPool,
+    generation_jobs_repository: &mut GenerationJobsRepositoryImpl<PgPool>,
+    user_id: entities::UserId,
+    generation_job_id: &str,
+) -> anyhow::Result<Option<entities::GenerationJob>> {
+    let id = entities::GenerationJobId::from(
+        Ulid::from_str(generation_job_id).context("ulid decode error")?,
+    );
+    let generation_job = generation_jobs_repository
+        .get_by_ids(user_id, &[id])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("generation_job not found"))?;
+
+    let now = Utc::now();
+    if !generation_job.startable(now) {
+        return Ok(None);
+    }
+
+    let generation_job = generation_jobs_repository
+        .update(now, generation_job.running())
+        .await?;
+    publish_generation_job_updated(pool, &generation_job).await;
+    Ok(Some(generation_job))
+}
+
+async fn publish_generation_job_updated(pool: &PgPool, generation_job: &entities::GenerationJob) {
+    event_bus::publish_or_log(
+        pool,
+        &Event::GenerationJobUpdated {
+            user_id: generation_job.user_id.clone(),
+            generation_job_id: generation_job.id,
+        },
+    )
+    .await;
+}
+
+// アカウント削除などでDBから消えたレコードのオブジェクトを削除する
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteStorageObjects {
+    pub keys: Vec<String>,
+    pub prefixes: Vec<String>,
+}
+
+impl<'de> Job<'de> for DeleteStorageObjects {
+    const JOB_TYPE: &'static str = "DELETE_STORAGE_OBJECTS";
+
+    async fn run(self, ctx: Ctx) -> Result<(), anyhow::Error> {
+        let mut storage = StorageImpl::new(ctx.config.clone(), ctx.s3_client.clone());
+
+        let keys = self
+            .keys
+            .into_iter()
+            .map(entities::FileKey::from_unchecked)
+            .collect::<Vec<_>>();
+        storage.delete(&keys).await.context("delete objects")?;
+        for prefix in self.prefixes {
+            storage
+                .delete_by_prefix(&prefix)
+                .await
+                .context("delete objects by prefix")?;
+        }
+
         Ok(())
     }
 }
@@ -1,16 +1,24 @@
 #![cfg_attr(not(debug_assertions), deny(warnings))]
 #![allow(async_fn_in_trait)]
+pub mod account_deleter;
 pub mod app_config;
+pub mod data_exporter;
 mod dataloader_with_params;
+pub mod document_generator;
 pub use dataloader_with_params::{BatchFnWithParams, DataloaderWithParams};
 pub mod entities;
+pub mod event_bus;
 pub mod google_public_key_provider;
 pub mod graphql;
 mod shareable_error;
 pub use shareable_error::ShareableError;
 pub mod adapters;
 pub mod faktory;
+pub mod figure_importer;
 pub mod job;
 pub mod jobs;
 pub mod loaders;
+pub mod persisted_queries;
 pub mod ports;
+pub mod query_limits;
+pub mod render;
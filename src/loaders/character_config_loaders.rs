@@ -70,8 +70,10 @@ pub struct CharacterConfigLoader<A> {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct CharacterConfigLoaderParams {
     pub user_id: entities::UserId,
-    pub after_id: Option<(entities::Character, entities::StrokeCount)>,
-    pub before_id: Option<(entities::Character, entities::StrokeCount)>,
+    pub filter: ports::CharacterConfigsQueryFilter,
+    pub order: ports::CharacterConfigsOrder,
+    pub after: Option<ports::CharacterConfigsCursor>,
+    pub before: Option<ports::CharacterConfigsCursor>,
     pub limit: entities::Limit,
 }
 
@@ -80,7 +82,10 @@ where
     A: ports::CharacterConfigsRepository<Error = anyhow::Error> + Send + Clone,
 {
     type K = ();
-    type V = Result<ports::PaginationResult<entities::CharacterConfig>, ShareableError>;
+    type V = Result<
+        ports::PaginationResult<(entities::CharacterConfig, ports::CharacterConfigsCursor)>,
+        ShareableError,
+    >;
     type P = CharacterConfigLoaderParams;
 
     async fn load_with_params(
@@ -92,8 +97,10 @@ where
             .character_configs_repository
             .query(
                 params.user_id.clone(),
-                params.after_id.clone(),
-                params.before_id.clone(),
+                &params.filter,
+                params.order,
+                params.after.clone(),
+                params.before.clone(),
                 params.limit.increment_unchecked(),
             )
             .await
@@ -15,6 +15,8 @@ pub struct FigureRecordByIdLoader<A> {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FigureRecordByIdLoaderParams {
     pub user_id: entities::UserId,
+    // 自分のもののみ、disabledなものも含めて読み込む
+    pub include_disabled: bool,
 }
 
 impl<A> BatchFnWithParams for FigureRecordByIdLoader<A>
@@ -30,10 +32,16 @@ where
         params: &Self::P,
         keys: &[Self::K],
     ) -> HashMap<Self::K, Self::V> {
-        let figure_record_map = self
-            .figure_records_repository
-            .get_by_ids(params.user_id.clone(), keys)
-            .await
+        let figure_records = if params.include_disabled {
+            self.figure_records_repository
+                .get_by_ids_include_disabled(params.user_id.clone(), keys)
+                .await
+        } else {
+            self.figure_records_repository
+                .get_by_ids(params.user_id.clone(), keys)
+                .await
+        };
+        let figure_record_map = figure_records
             .map(|figure_records| {
                 figure_records
                     .into_iter()
@@ -61,21 +69,17 @@ pub struct FigureRecordsByCharacterConfigIdLoader<A> {
     pub figure_records_repository: A,
 }
 
+// カーソルなどはキーに含めることで、引数が異なってもまとめて1回で取得する
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FigureRecordsByCharacterConfigIdLoaderParams {
     pub user_id: entities::UserId,
-    pub ids: Option<Vec<entities::FigureRecordId>>,
-    pub after_id: Option<entities::FigureRecordId>,
-    pub before_id: Option<entities::FigureRecordId>,
-    pub limit: entities::Limit,
-    pub user_type: Option<ports::UserType>,
 }
 
 impl<A> BatchFnWithParams for FigureRecordsByCharacterConfigIdLoader<A>
 where
     A: ports::FigureRecordsRepository<Error = anyhow::Error> + Send + Clone,
 {
-    type K = (entities::Character, entities::StrokeCount);
+    type K = ports::FigureRecordsByCharacterConfigIdQuery;
     type V = Result<ports::PaginationResult<entities::FigureRecord>, ShareableError>;
     type P = FigureRecordsByCharacterConfigIdLoaderParams;
 
@@ -84,30 +88,195 @@ where
         params: &Self::P,
         keys: &[Self::K],
     ) -> HashMap<Self::K, Self::V> {
-        let figure_record_map = self
+        let queries = keys
+            .iter()
+            .map(|key| ports::FigureRecordsByCharacterConfigIdQuery {
+                limit: key.limit.increment_unchecked(),
+                ..key.clone()
+            })
+            .collect::<Vec<_>>();
+
+        let figure_records_list = self
             .figure_records_repository
-            .get_by_character_config_ids(
+            .get_by_character_config_ids(params.user_id.clone(), &queries)
+            .await
+            .map_err(ShareableError::from);
+
+        keys.iter()
+            .enumerate()
+            .map(|(i, key)| {
+                (
+                    key.clone(),
+                    figure_records_list
+                        .as_ref()
+                        .map_err(|e| e.clone())
+                        .and_then(|figure_records_list| -> Result<_, ShareableError> {
+                            let mut figure_records =
+                                figure_records_list.get(i).cloned().unwrap_or_default();
+                            let limit = usize::try_from(key.limit.value()).context("into usize")?;
+                            let has_next = figure_records.len() > limit;
+                            // Lastでも新しい順なので、余分に取得したもの(before_idから遠いもの)は先頭にある
+                            if key.limit.kind() == entities::LimitKind::Last {
+                                figure_records.drain(..figure_records.len().saturating_sub(limit));
+                            } else {
+                                figure_records.truncate(limit);
+                            }
+                            Ok(ports::PaginationResult {
+                                values: figure_records,
+                                has_next,
+                            })
+                        }),
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FigureRecordsLoader<A> {
+    pub figure_records_repository: A,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FigureRecordsLoaderParams {
+    pub user_id: entities::UserId,
+    pub filter: ports::FigureRecordsQueryFilter,
+    pub after_id: Option<entities::FigureRecordId>,
+    pub before_id: Option<entities::FigureRecordId>,
+    pub limit: entities::Limit,
+}
+
+impl<A> BatchFnWithParams for FigureRecordsLoader<A>
+where
+    A: ports::FigureRecordsRepository<Error = anyhow::Error> + Send + Clone,
+{
+    type K = ();
+    type V = Result<ports::PaginationResult<entities::FigureRecord>, ShareableError>;
+    type P = FigureRecordsLoaderParams;
+
+    async fn load_with_params(
+        &mut self,
+        params: &Self::P,
+        _: &[Self::K],
+    ) -> HashMap<Self::K, Self::V> {
+        let result = self
+            .figure_records_repository
+            .query(
                 params.user_id.clone(),
-                keys,
-                params.ids.as_ref().map(|ids| ids.as_slice()),
+                &params.filter,
                 params.after_id,
                 params.before_id,
                 params.limit.increment_unchecked(),
-                params.user_type,
             )
             .await
-            .map(|figure_records| {
+            .and_then(|mut figure_records| {
+                let has_next = figure_records.len()
+                    > usize::try_from(params.limit.value()).context("into usize")?;
                 figure_records
-                    .into_iter()
-                    .fold(HashMap::new(), |mut map, figure_record| {
-                        map.entry((
-                            figure_record.character.clone(),
-                            figure_record.figure.stroke_count(),
-                        ))
+                    .truncate(usize::try_from(params.limit.value()).context("into usize")?);
+                if params.limit.kind() == entities::LimitKind::Last {
+                    figure_records.reverse();
+                }
+                Ok(ports::PaginationResult {
+                    values: figure_records,
+                    has_next,
+                })
+            })
+            .map_err(ShareableError::from);
+        vec![((), result)].into_iter().collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TrashedFigureRecordsLoader<A> {
+    pub figure_records_repository: A,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TrashedFigureRecordsLoaderParams {
+    pub user_id: entities::UserId,
+    pub after: Option<ports::TrashedFigureRecordsCursor>,
+    pub before: Option<ports::TrashedFigureRecordsCursor>,
+    pub limit: entities::Limit,
+}
+
+impl<A> BatchFnWithParams for TrashedFigureRecordsLoader<A>
+where
+    A: ports::FigureRecordsRepository<Error = anyhow::Error> + Send + Clone,
+{
+    type K = ();
+    type V = Result<
+        ports::PaginationResult<(entities::FigureRecord, ports::TrashedFigureRecordsCursor)>,
+        ShareableError,
+    >;
+    type P = TrashedFigureRecordsLoaderParams;
+
+    async fn load_with_params(
+        &mut self,
+        params: &Self::P,
+        _: &[Self::K],
+    ) -> HashMap<Self::K, Self::V> {
+        let result = self
+            .figure_records_repository
+            .query_trashed(
+                params.user_id.clone(),
+                params.after.clone(),
+                params.before.clone(),
+                params.limit.increment_unchecked(),
+            )
+            .await
+            .and_then(|mut figure_records| {
+                let has_next = figure_records.len()
+                    > usize::try_from(params.limit.value()).context("into usize")?;
+                figure_records
+                    .truncate(usize::try_from(params.limit.value()).context("into usize")?);
+                if params.limit.kind() == entities::LimitKind::Last {
+                    figure_records.reverse();
+                }
+                Ok(ports::PaginationResult {
+                    values: figure_records,
+                    has_next,
+                })
+            })
+            .map_err(ShareableError::from);
+        vec![((), result)].into_iter().collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FigureRecordStatsByCharacterLoader<A> {
+    pub figure_records_repository: A,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FigureRecordStatsByCharacterLoaderParams {
+    pub user_id: entities::UserId,
+}
+
+impl<A> BatchFnWithParams for FigureRecordStatsByCharacterLoader<A>
+where
+    A: ports::FigureRecordsRepository<Error = anyhow::Error> + Send + Clone,
+{
+    type K = entities::Character;
+    type V = Result<Vec<ports::FigureRecordStats>, ShareableError>;
+    type P = FigureRecordStatsByCharacterLoaderParams;
+
+    async fn load_with_params(
+        &mut self,
+        params: &Self::P,
+        keys: &[Self::K],
+    ) -> HashMap<Self::K, Self::V> {
+        let stats_map = self
+            .figure_records_repository
+            .get_stats_by_characters(params.user_id.clone(), keys)
+            .await
+            .map(|stats| {
+                stats.into_iter().fold(HashMap::new(), |mut acc, stats| {
+                    acc.entry(stats.character.clone())
                         .or_insert_with(Vec::new)
-                        .push(figure_record);
-                        map
-                    })
+                        .push(stats);
+                    acc
+                })
             })
             .map_err(ShareableError::from);
 
@@ -115,23 +284,98 @@ where
             .map(|key| {
                 (
                     key.clone(),
-                    figure_record_map.as_ref().map_err(|e| e.clone()).and_then(
-                        |figure_records_map| -> Result<_, ShareableError> {
-                            let mut figure_records =
-                                figure_records_map.get(key).cloned().unwrap_or_default();
-                            let has_next = figure_records.len()
-                                > usize::try_from(params.limit.value()).context("into usize")?;
-                            figure_records.truncate(
-                                usize::try_from(params.limit.value()).context("into usize")?,
-                            );
-                            Ok(ports::PaginationResult {
-                                values: figure_records,
-                                has_next,
-                            })
-                        },
-                    ),
+                    stats_map
+                        .as_ref()
+                        .map(|stats_map| stats_map.get(key).cloned().unwrap_or_default())
+                        .map_err(|e| e.clone()),
                 )
             })
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::*;
+    use crate::adapters::FigureRecordsRepositoryImpl;
+    use crate::ports::FigureRecordsRepository;
+
+    #[sqlx::test]
+    async fn test_figure_records_by_character_config_id_loader(pool: sqlx::PgPool) {
+        let mut repo = FigureRecordsRepositoryImpl::new(pool.clone());
+        let user_id = entities::UserId::from("user".to_string());
+        let now = Utc::now();
+
+        let mut figure_records = Vec::new();
+        for i in 0..5 {
+            figure_records.push(
+                repo.create(
+                    user_id.clone(),
+                    now + Duration::seconds(i),
+                    entities::Character::from('一'),
+                    entities::Figure::from_json(
+                        r#"{"strokes":[{"points":[{"x":0,"y":0,"z":1},{"x":1,"y":1,"z":1}]}],"width":1,"height":1}"#,
+                    )
+                    .unwrap(),
+                )
+                .await
+                .unwrap(),
+            );
+        }
+        let ids = figure_records
+            .iter()
+            .map(|figure_record| figure_record.id)
+            .collect::<Vec<_>>();
+
+        let query =
+            |after_id, before_id, kind, value| ports::FigureRecordsByCharacterConfigIdQuery {
+                character: entities::Character::from('一'),
+                stroke_count: entities::StrokeCount::try_from(1).unwrap(),
+                ids: None,
+                after_id,
+                before_id,
+                limit: entities::Limit::new(kind, value).unwrap(),
+                user_type: None,
+            };
+        let keys = vec![
+            query(None, None, entities::LimitKind::First, 2),
+            query(Some(ids[2]), None, entities::LimitKind::First, 2),
+            query(None, Some(ids[1]), entities::LimitKind::Last, 2),
+            query(None, Some(ids[2]), entities::LimitKind::Last, 2),
+        ];
+
+        let mut loader = FigureRecordsByCharacterConfigIdLoader {
+            figure_records_repository: repo,
+        };
+        let results = loader
+            .load_with_params(
+                &FigureRecordsByCharacterConfigIdLoaderParams { user_id },
+                &keys,
+            )
+            .await;
+
+        // (values, has_next)。Lastはbefore_idに近いものから取得し、新しい順に返す
+        let expected = [
+            (vec![ids[4], ids[3]], true),
+            (vec![ids[1], ids[0]], false),
+            (vec![ids[3], ids[2]], true),
+            (vec![ids[4], ids[3]], false),
+        ];
+        for (key, (values, has_next)) in keys.iter().zip(expected) {
+            let result = results.get(key).unwrap().as_ref().unwrap();
+            assert_eq!(
+                result
+                    .values
+                    .iter()
+                    .map(|figure_record| figure_record.id)
+                    .collect::<Vec<_>>(),
+                values,
+                "{:?}",
+                key
+            );
+            assert_eq!(result.has_next, has_next, "{:?}", key);
+        }
+    }
+}
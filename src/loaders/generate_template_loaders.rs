@@ -63,6 +63,7 @@ pub struct GenerateTemplatesLoader<A> {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GenerateTemplatesLoaderParams {
     pub user_id: entities::UserId,
+    pub disabled: bool,
     pub after_id: Option<entities::GenerateTemplateId>,
     pub before_id: Option<entities::GenerateTemplateId>,
     pub limit: entities::Limit,
@@ -85,6 +86,7 @@ where
             .generate_templates_repository
             .query(
                 params.user_id.clone(),
+                params.disabled,
                 params.after_id.clone(),
                 params.before_id.clone(),
                 params.limit.increment_unchecked(),
@@ -0,0 +1,54 @@
+use crate::entities;
+use crate::ports;
+use crate::BatchFnWithParams;
+use crate::ShareableError;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct GenerationJobByIdLoader<A> {
+    pub generation_jobs_repository: A,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GenerationJobByIdLoaderParams {
+    pub user_id: entities::UserId,
+}
+
+impl<A> BatchFnWithParams for GenerationJobByIdLoader<A>
+where
+    A: ports::GenerationJobsRepository<Error = anyhow::Error> + Send + Clone,
+{
+    type K = entities::GenerationJobId;
+    type V = Result<Option<entities::GenerationJob>, ShareableError>;
+    type P = GenerationJobByIdLoaderParams;
+
+    async fn load_with_params(
+        &mut self,
+        params: &Self::P,
+        keys: &[Self::K],
+    ) -> HashMap<Self::K, Self::V> {
+        let generation_job_map = self
+            .generation_jobs_repository
+            .get_by_ids(params.user_id.clone(), keys)
+            .await
+            .map(|generation_jobs| {
+                generation_jobs
+                    .into_iter()
+                    .map(|generation_job| (generation_job.id, generation_job))
+                    .collect::<HashMap<_, _>>()
+            })
+            .map_err(ShareableError::from);
+
+        keys.iter()
+            .map(|key| {
+                (
+                    *key,
+                    generation_job_map
+                        .as_ref()
+                        .map(|generation_job_map| generation_job_map.get(key).cloned())
+                        .map_err(|e| e.clone()),
+                )
+            })
+            .collect()
+    }
+}
@@ -3,9 +3,13 @@ mod character_config_seed_loaders;
 mod figure_record_loaders;
 mod file_loaders;
 mod generate_template_loaders;
+mod generation_job_loaders;
+mod reference_figure_loaders;
 
 pub use character_config_loaders::*;
 pub use character_config_seed_loaders::*;
 pub use figure_record_loaders::*;
 pub use file_loaders::*;
 pub use generate_template_loaders::*;
+pub use generation_job_loaders::*;
+pub use reference_figure_loaders::*;
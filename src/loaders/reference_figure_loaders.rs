@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use crate::entities;
+use crate::ports;
+use crate::BatchFnWithParams;
+use crate::ShareableError;
+
+#[derive(Clone, Debug)]
+pub struct ReferenceFiguresByCharacterConfigIdLoader<A> {
+    pub reference_figures_repository: A,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ReferenceFiguresByCharacterConfigIdLoaderParams {
+    pub user_id: entities::UserId,
+}
+
+impl<A> BatchFnWithParams for ReferenceFiguresByCharacterConfigIdLoader<A>
+where
+    A: ports::ReferenceFiguresRepository<Error = anyhow::Error> + Send + Clone,
+{
+    type K = (entities::Character, entities::StrokeCount);
+    type V = Result<Vec<entities::ReferenceFigure>, ShareableError>;
+    type P = ReferenceFiguresByCharacterConfigIdLoaderParams;
+
+    async fn load_with_params(
+        &mut self,
+        params: &Self::P,
+        keys: &[Self::K],
+    ) -> HashMap<Self::K, Self::V> {
+        let reference_figure_map = self
+            .reference_figures_repository
+            .get_by_character_config_ids(params.user_id.clone(), keys)
+            .await
+            .map_err(ShareableError::from);
+
+        keys.iter()
+            .map(|key| {
+                (
+                    key.clone(),
+                    reference_figure_map
+                        .as_ref()
+                        .map(|reference_figure_map| {
+                            reference_figure_map.get(key).cloned().unwrap_or_default()
+                        })
+                        .map_err(|e| e.clone()),
+                )
+            })
+            .collect()
+    }
+}
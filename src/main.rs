@@ -3,6 +3,7 @@
 use actix_session::storage::RedisSessionStore;
 use actix_session::{Session, SessionLength, SessionMiddleware};
 use actix_web::cookie::Key;
+use actix_web::http::header;
 use actix_web::{error, get, middleware, post, web, App, HttpRequest, HttpResponse, HttpServer};
 use anyhow::Context;
 use average_character_cloud_backend::faktory::FaktoryConnectionManager;
@@ -10,26 +11,45 @@ use average_character_cloud_backend::google_public_key_provider::{
     GooglePublicKeyProvider, GooglePublicKeyProviderCommand,
 };
 use chrono::Utc;
+use futures::{SinkExt, StreamExt};
 use juniper::http::graphiql::graphiql_source;
 use juniper::http::GraphQLRequest;
+use juniper::DefaultScalarValue;
+use juniper_graphql_ws::{ClientMessage, Connection, ConnectionConfig, ServerMessage};
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::collections::HashMap;
 use std::io;
+use std::ops::ControlFlow;
 use std::str::FromStr;
 use time::Duration;
-use tracing_actix_web::TracingLogger;
+use tracing_actix_web::{RequestId, TracingLogger};
 
 use actix_web_extras::middleware::Condition as OptionalCondition;
+use average_character_cloud_backend::adapters::{
+    FigureRecordsRepositoryImpl, PersistedQueriesRepositoryImpl, StorageImpl,
+};
 use average_character_cloud_backend::app_config::{AppConfig, AuthConfig, SessionConfig};
-use average_character_cloud_backend::graphql::{create_schema, AppCtx, Loaders, Schema};
+use average_character_cloud_backend::event_bus::{self, Event, EventBus};
+use average_character_cloud_backend::graphql::{
+    add_request_id, create_schema, request_error, AppCtx, GraphqlErrorCode, Loaders, Schema,
+};
 use average_character_cloud_backend::job::Job;
-use average_character_cloud_backend::{entities, job, jobs};
+use average_character_cloud_backend::persisted_queries::{
+    self, PersistedQueryError, PersistedQueryRegistry, PersistedQueryRequest, ResolvedQuery,
+};
+use average_character_cloud_backend::ports::{
+    FigureRecordsRepository, PersistedQueriesRepository, Storage,
+};
+use average_character_cloud_backend::query_limits::{check_query_limits, is_subscription};
+use average_character_cloud_backend::{entities, figure_importer, job, jobs, render};
 use clap::{Parser, Subcommand};
 use jsonwebtoken::jwk::{self, JwkSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use ulid::Ulid;
 #[derive(Parser)]
 #[clap(name = "average-character-cloud-backend")]
 struct Cli {
@@ -40,6 +60,18 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     Migrate,
+    // 1行に1つ {"character": "あ", "figure": {...}} のJSONLからfigure_recordsを作成する
+    ImportFigures {
+        #[clap(long)]
+        user: String,
+        #[clap(long, default_value_t = 500)]
+        batch_size: usize,
+        file: std::path::PathBuf,
+    },
+    // {"<sha256>": "<query>"} のJSONからpersisted_queriesを登録する
+    RegisterPersistedQueries {
+        file: std::path::PathBuf,
+    },
 }
 
 #[get("/graphiql")]
@@ -57,40 +89,558 @@ async fn graphiql(config: web::Data<AppConfig>) -> HttpResponse {
         .body(html)
 }
 
+fn session_user_id(config: &AppConfig, session: &Session) -> Option<entities::UserId> {
+    if let SessionConfig::Dummy { user_id } = &config.session {
+        Some(entities::UserId::from(user_id.clone()))
+    } else {
+        session
+            .get::<String>("user_id")
+            .unwrap_or_else(|e| {
+                tracing::warn!("session decode error: : {}", e);
+                None
+            })
+            .map(entities::UserId::from)
+    }
+}
+
+fn new_app_ctx(
+    pool: &PgPool,
+    user_id: Option<entities::UserId>,
+    config: &AppConfig,
+    s3_client: &aws_sdk_s3::Client,
+    faktory_pool: &r2d2::Pool<FaktoryConnectionManager>,
+    event_bus: &EventBus,
+) -> AppCtx {
+    AppCtx {
+        pool: pool.clone(),
+        user_id,
+        now: Utc::now(),
+        loaders: Loaders::new(pool),
+        config: config.clone(),
+        s3_client: s3_client.clone(),
+        faktory_pool: faktory_pool.clone(),
+        event_bus: event_bus.clone(),
+        clear_session: AtomicBool::new(false),
+    }
+}
+
+// 実行前に弾いたリクエスト。Errorはクライアントが扱えるようにGraphQLのエラーとして返す
+enum RejectedRequest {
+    Error(serde_json::Value),
+    Internal,
+}
+
+// persisted queryの解決とクエリの制限の確認。HTTPとWebSocketで同じものを使う
+async fn prepare_request(
+    persisted_query_registry: &PersistedQueryRegistry,
+    config: &AppConfig,
+    request: PersistedQueryRequest,
+) -> Result<ResolvedQuery, RejectedRequest> {
+    let data = match persisted_query_registry.resolve(request).await {
+        Ok(data) => data,
+        Err(PersistedQueryError::Other(e)) => {
+            tracing::error!("resolve persisted query error: {:?}", e);
+            return Err(RejectedRequest::Internal);
+        }
+        Err(e) => {
+            return Err(RejectedRequest::Error(request_error(
+                e.code(),
+                &e.to_string(),
+                None,
+            )));
+        }
+    };
+    let variables = serde_json::to_value(&data.variables).map_err(|e| {
+        tracing::error!("serialize variables error: {:?}", e);
+        RejectedRequest::Internal
+    })?;
+    if let Err(e) = check_query_limits(
+        &config.query_limits,
+        &data.query,
+        data.operation_name.as_deref(),
+        &variables,
+    ) {
+        return Err(RejectedRequest::Error(request_error(
+            e.code(),
+            &e.to_string(),
+            e.limit(),
+        )));
+    }
+    Ok(data)
+}
+
 #[post("/graphql")]
 async fn graphql(
     st: web::Data<Arc<Schema>>,
     pool: web::Data<PgPool>,
     s3_client: web::Data<aws_sdk_s3::Client>,
-    data: web::Json<GraphQLRequest>,
+    faktory_pool: web::Data<r2d2::Pool<FaktoryConnectionManager>>,
+    data: web::Json<PersistedQueryRequest>,
+    persisted_query_registry: web::Data<PersistedQueryRegistry>,
+    event_bus: web::Data<EventBus>,
     session: Session,
     config: web::Data<AppConfig>,
+    request_id: RequestId,
 ) -> Result<HttpResponse, error::Error> {
-    let ctx = AppCtx {
-        pool: pool.get_ref().clone(),
-        user_id: if let SessionConfig::Dummy { user_id } = &config.session {
-            Some(entities::UserId::from(user_id.clone()))
-        } else {
-            session
-                .get::<String>("user_id")
-                .unwrap_or_else(|e| {
-                    tracing::warn!("session decode error: : {}", e);
-                    None
-                })
-                .map(entities::UserId::from)
-        },
-        now: Utc::now(),
-        loaders: Loaders::new(pool.get_ref()),
-        config: config.get_ref().clone(),
-        s3_client: s3_client.get_ref().clone(),
+    let data = match prepare_request(&persisted_query_registry, &config, data.into_inner()).await {
+        Ok(data) => data,
+        Err(RejectedRequest::Internal) => {
+            return Err(error::ErrorInternalServerError("Internal error"));
+        }
+        Err(RejectedRequest::Error(e)) => {
+            let mut errors = serde_json::json!([e]);
+            add_request_id(&mut errors, &request_id.to_string());
+            return Ok(HttpResponse::Ok().json(serde_json::json!({ "errors": errors })));
+        }
     };
+    let data = GraphQLRequest::from(data);
+    let ctx = new_app_ctx(
+        &pool,
+        session_user_id(&config, &session),
+        &config,
+        &s3_client,
+        &faktory_pool,
+        &event_bus,
+    );
     let res = data.execute(&st, &ctx).await;
+    if ctx.clear_session.load(Ordering::SeqCst) {
+        session.clear();
+    }
+    let mut res = serde_json::to_value(&res)?;
+    if let Some(errors) = res.get_mut("errors") {
+        add_request_id(errors, &request_id.to_string());
+    }
     let json = serde_json::to_string(&res)?;
     Ok(HttpResponse::Ok()
         .content_type("application/json")
         .body(json))
 }
 
+// 1つのWebSocket接続で同時に実行できるサブスクリプションの数
+const MAX_SUBSCRIPTION_OPERATIONS: usize = 10;
+
+// keep alive(ka)を送る間隔
+const SUBSCRIPTION_KEEP_ALIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+// サブスクリプションの各操作からWebSocket接続への通知
+enum SubscriptionOutput {
+    Message(ServerMessage<DefaultScalarValue>),
+    // 操作が終わった。同じIDで開始し直した操作を消さないよう開始時の連番も持つ
+    Finished { id: String, seq: u64 },
+}
+
+struct SubscriptionState {
+    schema: Arc<Schema>,
+    persisted_query_registry: PersistedQueryRegistry,
+    pool: PgPool,
+    user_id: Option<entities::UserId>,
+    config: AppConfig,
+    s3_client: aws_sdk_s3::Client,
+    faktory_pool: r2d2::Pool<FaktoryConnectionManager>,
+    event_bus: EventBus,
+    // WebSocket接続を開始したリクエストのID。この接続で返す全てのエラーに入れる
+    request_id: String,
+    // connection_initのメッセージ。操作ごとのConnectionに同じものを送る
+    init: Option<serde_json::Value>,
+    // 実行中の操作。Senderを破棄すると操作が止まる
+    operations: HashMap<String, (u64, oneshot::Sender<()>)>,
+    next_seq: u64,
+    output: mpsc::UnboundedSender<SubscriptionOutput>,
+}
+
+impl SubscriptionState {
+    async fn handle_message(
+        &mut self,
+        ws_session: &mut actix_ws::Session,
+        text: &str,
+    ) -> ControlFlow<()> {
+        let msg = match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(msg) => msg,
+            Err(e) => {
+                tracing::warn!("invalid subscription message: {}", e);
+                return ControlFlow::Break(());
+            }
+        };
+        let id = msg
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string());
+        match (msg.get("type").and_then(|ty| ty.as_str()), id) {
+            (Some("connection_init"), _) => {
+                if let Err(e) =
+                    serde_json::from_value::<ClientMessage<DefaultScalarValue>>(msg.clone())
+                {
+                    tracing::warn!("invalid subscription message: {}", e);
+                    return ControlFlow::Break(());
+                }
+                self.init = Some(msg);
+                send_json(ws_session, &serde_json::json!({ "type": "connection_ack" })).await
+            }
+            (Some("start"), Some(id)) => {
+                let payload = msg.get("payload").cloned().unwrap_or_default();
+                let error = match self.start(id.clone(), payload).await {
+                    Ok(()) => return ControlFlow::Continue(()),
+                    Err(RejectedRequest::Error(e)) => e,
+                    Err(RejectedRequest::Internal) => {
+                        request_error(GraphqlErrorCode::Internal, "Internal error", None)
+                    }
+                };
+                let mut msg = serde_json::json!({ "type": "error", "id": id, "payload": [error] });
+                add_subscription_request_id(&mut msg, &self.request_id);
+                send_json(ws_session, &msg).await
+            }
+            (Some("stop"), Some(id)) => {
+                self.operations.remove(&id);
+                ControlFlow::Continue(())
+            }
+            (Some("connection_terminate"), _) => ControlFlow::Break(()),
+            _ => {
+                tracing::warn!("invalid subscription message: {}", text);
+                ControlFlow::Break(())
+            }
+        }
+    }
+
+    // 操作ごとにコンテキストとConnectionを作る。ローダーのキャッシュやnowを接続中ずっと使い回さないため
+    async fn start(
+        &mut self,
+        id: String,
+        payload: serde_json::Value,
+    ) -> Result<(), RejectedRequest> {
+        let rejected = |message: &str| {
+            RejectedRequest::Error(request_error(GraphqlErrorCode::Validation, message, None))
+        };
+        let Some(init) = self.init.clone() else {
+            return Err(rejected("connection_init is required"));
+        };
+        if self.operations.contains_key(&id) {
+            return Err(rejected("Subscription id is already in use"));
+        }
+        if self.operations.len() >= MAX_SUBSCRIPTION_OPERATIONS {
+            return Err(RejectedRequest::Error(request_error(
+                GraphqlErrorCode::TooManySubscriptions,
+                "Too many subscriptions",
+                Some(MAX_SUBSCRIPTION_OPERATIONS as u64),
+            )));
+        }
+        let request = serde_json::from_value::<PersistedQueryRequest>(payload)
+            .map_err(|e| rejected(&format!("Invalid payload: {}", e)))?;
+        let data = prepare_request(&self.persisted_query_registry, &self.config, request).await?;
+        // クエリやミューテーションはHTTPで送る。deleteMyAccountのセッション破棄などはHTTP側でしか扱わない
+        if !is_subscription(&data.query, data.operation_name.as_deref()) {
+            return Err(rejected(
+                "Only subscriptions can be sent over this connection",
+            ));
+        }
+
+        let mut payload = serde_json::json!({
+            "query": data.query,
+            "operationName": data.operation_name,
+        });
+        if let Some(variables) = &data.variables {
+            payload["variables"] = serde_json::to_value(variables).map_err(|e| {
+                tracing::error!("serialize variables error: {:?}", e);
+                RejectedRequest::Internal
+            })?;
+        }
+        let (init, start) = match (
+            serde_json::from_value::<ClientMessage<DefaultScalarValue>>(init),
+            serde_json::from_value::<ClientMessage<DefaultScalarValue>>(serde_json::json!({
+                "type": "start",
+                "id": id,
+                "payload": payload,
+            })),
+        ) {
+            (Ok(init), Ok(start)) => (init, start),
+            (Err(e), _) | (_, Err(e)) => {
+                tracing::error!("build subscription message error: {:?}", e);
+                return Err(RejectedRequest::Internal);
+            }
+        };
+
+        let ctx = new_app_ctx(
+            &self.pool,
+            self.user_id.clone(),
+            &self.config,
+            &self.s3_client,
+            &self.faktory_pool,
+            &self.event_bus,
+        );
+        let (stop_tx, mut stop_rx) = oneshot::channel();
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.operations.insert(id.clone(), (seq, stop_tx));
+
+        let schema = self.schema.clone();
+        let output = self.output.clone();
+        actix_web::rt::spawn(async move {
+            // keep aliveはWebSocket接続の方でまとめて送る
+            let (mut sink, mut stream) = Connection::new(
+                schema,
+                ConnectionConfig::new(ctx)
+                    .with_max_in_flight_operations(1)
+                    .with_keep_alive_interval(std::time::Duration::ZERO),
+            )
+            .split();
+            if sink.send(init).await.is_ok() && sink.send(start).await.is_ok() {
+                loop {
+                    tokio::select! {
+                        msg = stream.next() => match msg {
+                            Some(ServerMessage::ConnectionAck)
+                            | Some(ServerMessage::ConnectionKeepAlive) => {}
+                            Some(msg) => {
+                                let finished = matches!(
+                                    msg,
+                                    ServerMessage::Complete { .. }
+                                        | ServerMessage::Error { .. }
+                                        | ServerMessage::ConnectionError { .. }
+                                );
+                                if output.send(SubscriptionOutput::Message(msg)).is_err()
+                                    || finished
+                                {
+                                    break;
+                                }
+                            }
+                            None => break,
+                        },
+                        // stopされたか、WebSocket接続が終わった
+                        _ = &mut stop_rx => break,
+                    }
+                }
+            }
+            let _ = output.send(SubscriptionOutput::Finished { id, seq });
+        });
+
+        Ok(())
+    }
+}
+
+// graphql-wsのdataのerrorsと、errorのpayloadにリクエストIDを入れる
+fn add_subscription_request_id(msg: &mut serde_json::Value, request_id: &str) {
+    let errors = match msg.get("type").and_then(|ty| ty.as_str()) {
+        Some("data") => msg
+            .get_mut("payload")
+            .and_then(|payload| payload.get_mut("errors")),
+        Some("error") => msg.get_mut("payload"),
+        _ => None,
+    };
+    if let Some(errors) = errors {
+        add_request_id(errors, request_id);
+    }
+}
+
+async fn send_json(
+    ws_session: &mut actix_ws::Session,
+    value: &serde_json::Value,
+) -> ControlFlow<()> {
+    match ws_session.text(value.to_string()).await {
+        Ok(()) => ControlFlow::Continue(()),
+        Err(_) => ControlFlow::Break(()),
+    }
+}
+
+/*
+graphql-wsプロトコル(subscriptions-transport-ws)でサブスクリプションを提供する
+startはHTTPと同じくpersisted queryの解決とクエリの制限の確認をしてから、操作ごとのConnectionで実行する
+ログアウトやアカウント削除でセッションが破棄されたユーザーの接続は切る
+*/
+#[get("/subscriptions")]
+async fn subscriptions(
+    req: HttpRequest,
+    body: web::Payload,
+    st: web::Data<Arc<Schema>>,
+    pool: web::Data<PgPool>,
+    s3_client: web::Data<aws_sdk_s3::Client>,
+    faktory_pool: web::Data<r2d2::Pool<FaktoryConnectionManager>>,
+    persisted_query_registry: web::Data<PersistedQueryRegistry>,
+    event_bus: web::Data<EventBus>,
+    session: Session,
+    config: web::Data<AppConfig>,
+    request_id: RequestId,
+) -> Result<HttpResponse, error::Error> {
+    let (mut res, mut ws_session, mut ws_stream) = actix_ws::handle(&req, body)?;
+    res.headers_mut().insert(
+        header::SEC_WEBSOCKET_PROTOCOL,
+        header::HeaderValue::from_static("graphql-ws"),
+    );
+
+    let (output_tx, mut output_rx) = mpsc::unbounded_channel();
+    let mut state = SubscriptionState {
+        schema: st.get_ref().clone(),
+        persisted_query_registry: persisted_query_registry.get_ref().clone(),
+        pool: pool.get_ref().clone(),
+        user_id: session_user_id(&config, &session),
+        config: config.get_ref().clone(),
+        s3_client: s3_client.get_ref().clone(),
+        faktory_pool: faktory_pool.get_ref().clone(),
+        event_bus: event_bus.get_ref().clone(),
+        request_id: request_id.to_string(),
+        init: None,
+        operations: HashMap::new(),
+        next_seq: 0,
+        output: output_tx,
+    };
+    let mut events = event_bus.subscribe();
+
+    actix_web::rt::spawn(async move {
+        let mut keep_alive = tokio::time::interval(SUBSCRIPTION_KEEP_ALIVE_INTERVAL);
+        loop {
+            tokio::select! {
+                msg = ws_stream.next() => match msg {
+                    Some(Ok(actix_ws::Message::Text(text))) => {
+                        if state.handle_message(&mut ws_session, &text).await.is_break() {
+                            break;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if ws_session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+                Some(output) = output_rx.recv() => match output {
+                    SubscriptionOutput::Message(msg) => {
+                        let mut msg = match serde_json::to_value(&msg) {
+                            Ok(msg) => msg,
+                            Err(e) => {
+                                tracing::error!("serialize subscription message error: {:?}", e);
+                                break;
+                            }
+                        };
+                        add_subscription_request_id(&mut msg, &state.request_id);
+                        if send_json(&mut ws_session, &msg).await.is_break() {
+                            break;
+                        }
+                    }
+                    SubscriptionOutput::Finished { id, seq } => {
+                        if state
+                            .operations
+                            .get(&id)
+                            .is_some_and(|(operation_seq, _)| *operation_seq == seq)
+                        {
+                            state.operations.remove(&id);
+                        }
+                    }
+                },
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        if state
+                            .user_id
+                            .as_ref()
+                            .is_some_and(|user_id| event.ends_session_of(user_id))
+                        {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                _ = keep_alive.tick(), if state.init.is_some() => {
+                    if send_json(&mut ws_session, &serde_json::json!({ "type": "ka" }))
+                        .await
+                        .is_break()
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+        // 実行中の操作はSenderの破棄で止まる
+        drop(state);
+        let _ = ws_session.close(None).await;
+    });
+
+    Ok(res)
+}
+
+#[get("/figure_records/{id}.svg")]
+async fn figure_record_svg(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    session: Session,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, error::Error> {
+    let user_id = session_user_id(&config, &session)
+        .ok_or_else(|| error::ErrorUnauthorized("Authentication required"))?;
+    let id = Ulid::from_str(&path.into_inner()).map_err(|_| error::ErrorNotFound("Not found"))?;
+
+    let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool.get_ref().clone());
+    let figure_record = figure_records_repository
+        .get_by_ids(user_id, &[entities::FigureRecordId::from(id)])
+        .await
+        .map_err(|e| {
+            tracing::error!("get figure_record error: {}", e);
+            error::ErrorInternalServerError("Internal error")
+        })?
+        .pop()
+        .ok_or_else(|| error::ErrorNotFound("Not found"))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/svg+xml")
+        .body(render::figure_to_svg(&figure_record.figure)))
+}
+
+// FigureRecord.thumbnailUrlの実体。無ければ生成してS3に置き、署名付きURLにリダイレクトする
+#[get("/figure_records/{id}/thumbnails/{size}.png")]
+async fn figure_record_thumbnail(
+    pool: web::Data<PgPool>,
+    s3_client: web::Data<aws_sdk_s3::Client>,
+    path: web::Path<(String, i32)>,
+    session: Session,
+    config: web::Data<AppConfig>,
+) -> Result<HttpResponse, error::Error> {
+    let user_id = session_user_id(&config, &session)
+        .ok_or_else(|| error::ErrorUnauthorized("Authentication required"))?;
+    let (id, size) = path.into_inner();
+    let id = Ulid::from_str(&id).map_err(|_| error::ErrorNotFound("Not found"))?;
+    let size =
+        entities::ThumbnailSize::try_from(size).map_err(|_| error::ErrorNotFound("Not found"))?;
+
+    let mut figure_records_repository = FigureRecordsRepositoryImpl::new(pool.get_ref().clone());
+    let figure_record = figure_records_repository
+        .get_by_ids(user_id, &[entities::FigureRecordId::from(id)])
+        .await
+        .map_err(|e| {
+            tracing::error!("get figure_record error: {}", e);
+            error::ErrorInternalServerError("Internal error")
+        })?
+        .pop()
+        .ok_or_else(|| error::ErrorNotFound("Not found"))?;
+
+    let mut storage = StorageImpl::new(config.get_ref().clone(), s3_client.get_ref().clone());
+    async {
+        let key = entities::FileKey::figure_record_thumbnail(
+            &figure_record.user_id,
+            figure_record.id,
+            size,
+        );
+        if !storage.exists(&key).await.context("check thumbnail")? {
+            let figure = figure_record.figure;
+            let png =
+                tokio::task::spawn_blocking(move || render::figure_to_png(&figure, size)).await??;
+            let mime_type = entities::MimeType::try_from("image/png".to_string())?;
+            storage
+                .put(&key, &mime_type, png)
+                .await
+                .context("put thumbnail")?;
+        }
+        let url = storage
+            .generate_download_url_by_key(&key)
+            .await
+            .context("generate download url")?;
+        Ok(HttpResponse::Found()
+            .insert_header((header::LOCATION, url))
+            .finish())
+    }
+    .await
+    .map_err(|e: anyhow::Error| {
+        tracing::error!("figure_record_thumbnail error: {}", e);
+        error::ErrorInternalServerError("Internal error")
+    })
+}
+
 #[derive(Serialize, Deserialize)]
 struct GoogleCallbackParams {
     g_csrf_token: String,
@@ -154,6 +704,7 @@ async fn run_task_front(
 ) -> Result<HttpResponse, error::Error> {
     async {
         (jobs::UpdateSeeds {}).enqueue(&faktory_pool).await?;
+        (jobs::PurgeFigureRecords {}).enqueue(&faktory_pool).await?;
         Ok(HttpResponse::Ok()
             .content_type("text/html; charset=utf-8")
             .body("ok"))
@@ -206,10 +757,15 @@ fn verify_google_token(
 
 #[post("/logout")]
 async fn logout(
+    pool: web::Data<PgPool>,
     config: web::Data<AppConfig>,
     session: Session,
 ) -> Result<HttpResponse, error::Error> {
+    let user_id = session_user_id(&config, &session);
     session.clear();
+    if let Some(user_id) = user_id {
+        event_bus::publish_or_log(&pool, &Event::UserSessionEnded { user_id }).await;
+    }
     Ok(HttpResponse::SeeOther()
         .append_header((
             actix_web::http::header::LOCATION,
@@ -297,11 +853,64 @@ async fn main() -> anyhow::Result<()> {
             .run(&pool)
             .await
             .context("migrate"),
+        Some(Commands::ImportFigures {
+            user,
+            batch_size,
+            file,
+        }) => {
+            let reader = io::BufReader::new(
+                std::fs::File::open(&file).with_context(|| format!("open {}", file.display()))?,
+            );
+            let report = figure_importer::import_figures(
+                pool,
+                entities::UserId::from(user),
+                Utc::now(),
+                reader,
+                batch_size.max(1),
+            )
+            .await?;
+
+            for (line_number, error) in &report.errors {
+                eprintln!("line {}: {}", line_number, error);
+            }
+            println!(
+                "imported: {}, failed: {}",
+                report.imported,
+                report.errors.len()
+            );
+
+            if report.errors.is_empty() {
+                Ok(())
+            } else {
+                Err(anyhow::anyhow!("{} lines failed", report.errors.len()))
+            }
+        }
+        Some(Commands::RegisterPersistedQueries { file }) => {
+            let queries = persisted_queries::load_file(&file)?;
+            let mut persisted_queries_repository = PersistedQueriesRepositoryImpl::new(pool);
+            let created = persisted_queries_repository
+                .create_many(Utc::now(), &queries)
+                .await?;
+            println!("registered: {}, total: {}", created, queries.len());
+            Ok(())
+        }
         None => {
             let host = config.host.clone();
             let port = config.port;
             let workers = config.workers;
             let schema = Arc::new(create_schema());
+            let event_bus = EventBus::listen(&pool).await?;
+            let persisted_query_registry = PersistedQueryRegistry::new(
+                config
+                    .persisted_queries
+                    .file
+                    .as_ref()
+                    .map(|file| persisted_queries::load_file(std::path::Path::new(file)))
+                    .transpose()?
+                    .unwrap_or_default(),
+                pool.clone(),
+                config.persisted_queries.only,
+            );
 
             let redis_session_config = if let SessionConfig::Redis {
                 redis_url,
@@ -321,7 +930,14 @@ async fn main() -> anyhow::Result<()> {
                 GooglePublicKeyProvider::run(google_public_key_provider_rx).await;
             });
 
-            job::run_worker(&config.faktory_url, job::Ctx { pool: pool.clone() })?;
+            job::run_worker(
+                &config.faktory_url,
+                job::Ctx {
+                    pool: pool.clone(),
+                    config: config.clone(),
+                    s3_client: s3_client.clone(),
+                },
+            )?;
 
             if config.enqueue_cron_task {
                 let faktory_pool = faktory_pool.clone();
@@ -343,6 +959,9 @@ async fn main() -> anyhow::Result<()> {
                         if let Err(e) = (jobs::UpdateSeeds {}).enqueue(&faktory_pool).await {
                             tracing::error!("enqueue update_seeds error: {}", e);
                         }
+                        if let Err(e) = (jobs::PurgeFigureRecords {}).enqueue(&faktory_pool).await {
+                            tracing::error!("enqueue purge_figure_records error: {}", e);
+                        }
                     }
                 });
             }
@@ -355,8 +974,13 @@ async fn main() -> anyhow::Result<()> {
                     .app_data(web::Data::new(pool.clone()))
                     .app_data(web::Data::new(s3_client.clone()))
                     .app_data(web::Data::new(faktory_pool.clone()))
+                    .app_data(web::Data::new(persisted_query_registry.clone()))
+                    .app_data(web::Data::new(event_bus.clone()))
                     .service(graphql)
+                    .service(subscriptions)
                     .service(graphiql)
+                    .service(figure_record_svg)
+                    .service(figure_record_thumbnail)
                     .service(logout);
                 if config.enable_task_front {
                     app = app.service(run_task_front);
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use juniper::http::GraphQLRequest;
+use juniper::InputValue;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use thiserror::Error;
+
+use crate::graphql::GraphqlErrorCode;
+use crate::{
+    adapters::PersistedQueriesRepositoryImpl, entities, ports::PersistedQueriesRepository,
+};
+
+/*
+永続化クエリ(persisted queries)
+クライアントはクエリ文字列の代わりにそのsha256を送り、起動時に読み込んだファイルかDBに登録されたクエリを実行する
+リクエストの形式はApolloに合わせる: {"extensions": {"persistedQuery": {"version": 1, "sha256Hash": "..."}}}
+only=trueなら登録されていないクエリは実行しない
+*/
+
+pub fn hash_query(query: &str) -> entities::PersistedQueryHash {
+    entities::PersistedQueryHash::try_from(format!("{:x}", Sha256::digest(query.as_bytes())))
+        .expect("sha256 hex must be a valid hash")
+}
+
+// {"<sha256>": "<query>", ...} のJSON(relay-compilerなどが出力する形式)を読み込む
+pub fn load_file(path: &std::path::Path) -> anyhow::Result<Vec<entities::PersistedQuery>> {
+    let file = std::fs::File::open(path).with_context(|| format!("open {}", path.display()))?;
+    let queries: HashMap<String, String> = serde_json::from_reader(std::io::BufReader::new(file))
+        .context("parse persisted queries")?;
+
+    queries
+        .into_iter()
+        .map(|(hash, query)| {
+            let hash = entities::PersistedQueryHash::try_from(hash)?;
+            anyhow::ensure!(
+                hash == hash_query(&query),
+                "hash mismatch: {}",
+                hash.value()
+            );
+            Ok(entities::PersistedQuery { hash, query })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedQueryRequest {
+    query: Option<String>,
+    operation_name: Option<String>,
+    variables: Option<InputValue>,
+    extensions: Option<PersistedQueryRequestExtensions>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedQueryRequestExtensions {
+    persisted_query: Option<PersistedQueryExtension>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedQueryExtension {
+    sha256_hash: String,
+}
+
+// 実行するクエリ文字列が決まったリクエスト
+#[derive(Debug, Clone)]
+pub struct ResolvedQuery {
+    pub query: String,
+    pub operation_name: Option<String>,
+    pub variables: Option<InputValue>,
+}
+
+impl From<ResolvedQuery> for GraphQLRequest {
+    fn from(value: ResolvedQuery) -> Self {
+        GraphQLRequest::new(value.query, value.operation_name, value.variables)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PersistedQueryError {
+    // Apolloのクライアントはこのメッセージを見てクエリ文字列付きで再送する
+    #[error("PersistedQueryNotFound")]
+    NotFound,
+    #[error("Only persisted queries are allowed")]
+    NotAllowed,
+    #[error("Provided sha256Hash does not match query")]
+    HashMismatch,
+    #[error("Query is required")]
+    MissingQuery,
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl PersistedQueryError {
+    // extensions.codeの値
+    pub fn code(&self) -> GraphqlErrorCode {
+        match self {
+            PersistedQueryError::NotFound => GraphqlErrorCode::PersistedQueryNotFound,
+            PersistedQueryError::NotAllowed => GraphqlErrorCode::PersistedQueryNotAllowed,
+            PersistedQueryError::HashMismatch | PersistedQueryError::MissingQuery => {
+                GraphqlErrorCode::Validation
+            }
+            PersistedQueryError::Other(_) => GraphqlErrorCode::Internal,
+        }
+    }
+}
+
+/*
+登録されていないハッシュでリクエストされるたびにDBを引かないように、見つからなかったハッシュをしばらく覚えておく
+register-persisted-queriesで後から登録されたものもTTLが過ぎれば使えるようになる
+*/
+const NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(60);
+const NEGATIVE_CACHE_MAX_ENTRIES: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub struct PersistedQueryRegistry {
+    queries: Arc<HashMap<entities::PersistedQueryHash, String>>,
+    missing: Arc<Mutex<HashMap<entities::PersistedQueryHash, Instant>>>,
+    pool: PgPool,
+    only: bool,
+}
+
+impl PersistedQueryRegistry {
+    pub fn new(queries: Vec<entities::PersistedQuery>, pool: PgPool, only: bool) -> Self {
+        Self {
+            queries: Arc::new(
+                queries
+                    .into_iter()
+                    .map(|persisted_query| (persisted_query.hash, persisted_query.query))
+                    .collect(),
+            ),
+            missing: Arc::new(Mutex::new(HashMap::new())),
+            pool,
+            only,
+        }
+    }
+
+    pub async fn resolve(
+        &self,
+        request: PersistedQueryRequest,
+    ) -> Result<ResolvedQuery, PersistedQueryError> {
+        let hash = request
+            .extensions
+            .and_then(|extensions| extensions.persisted_query)
+            .map(|persisted_query| persisted_query.sha256_hash);
+
+        let query = match (hash, request.query) {
+            (Some(hash), query) => {
+                // 不正なハッシュは登録されていないものとして扱う
+                let hash = entities::PersistedQueryHash::try_from(hash)
+                    .map_err(|_| PersistedQueryError::NotFound)?;
+                if query
+                    .as_ref()
+                    .is_some_and(|query| hash_query(query) != hash)
+                {
+                    return Err(PersistedQueryError::HashMismatch);
+                }
+
+                match self.get(&hash).await? {
+                    Some(persisted_query) => persisted_query,
+                    // 登録はしない(許可リストとして使うため)
+                    None => match query {
+                        Some(query) if !self.only => query,
+                        Some(_) => return Err(PersistedQueryError::NotAllowed),
+                        None => return Err(PersistedQueryError::NotFound),
+                    },
+                }
+            }
+            (None, Some(query)) => {
+                if self.only && self.get(&hash_query(&query)).await?.is_none() {
+                    return Err(PersistedQueryError::NotAllowed);
+                }
+                query
+            }
+            (None, None) => return Err(PersistedQueryError::MissingQuery),
+        };
+
+        Ok(ResolvedQuery {
+            query,
+            operation_name: request.operation_name,
+            variables: request.variables,
+        })
+    }
+
+    async fn get(
+        &self,
+        hash: &entities::PersistedQueryHash,
+    ) -> Result<Option<String>, PersistedQueryError> {
+        if let Some(query) = self.queries.get(hash) {
+            return Ok(Some(query.clone()));
+        }
+        if self.is_missing(hash) {
+            return Ok(None);
+        }
+
+        let mut persisted_queries_repository =
+            PersistedQueriesRepositoryImpl::new(self.pool.clone());
+        let persisted_query = persisted_queries_repository
+            .get_by_hash(hash)
+            .await
+            .context("load persisted_query")?;
+
+        if persisted_query.is_none() {
+            self.set_missing(hash.clone());
+        }
+
+        Ok(persisted_query.map(|persisted_query| persisted_query.query))
+    }
+
+    fn is_missing(&self, hash: &entities::PersistedQueryHash) -> bool {
+        let mut missing = self.missing.lock().unwrap_or_else(PoisonError::into_inner);
+        match missing.get(hash) {
+            Some(cached_at) if cached_at.elapsed() < NEGATIVE_CACHE_TTL => true,
+            Some(_) => {
+                missing.remove(hash);
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn set_missing(&self, hash: entities::PersistedQueryHash) {
+        let mut missing = self.missing.lock().unwrap_or_else(PoisonError::into_inner);
+        // ランダムなハッシュを送り続けられても際限なく増えないようにする
+        if missing.len() >= NEGATIVE_CACHE_MAX_ENTRIES {
+            missing.retain(|_, cached_at| cached_at.elapsed() < NEGATIVE_CACHE_TTL);
+            if missing.len() >= NEGATIVE_CACHE_MAX_ENTRIES {
+                missing.clear();
+            }
+        }
+        missing.insert(hash, Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    const QUERY: &str = "{ loginUser { id } }";
+    const FILE_QUERY: &str = "{ userConfig { id } }";
+
+    fn request(
+        query: Option<&str>,
+        hash: Option<&entities::PersistedQueryHash>,
+    ) -> PersistedQueryRequest {
+        serde_json::from_value(serde_json::json!({
+            "query": query,
+            "extensions": hash.map(|hash| serde_json::json!({
+                "persistedQuery": { "version": 1, "sha256Hash": hash.value() },
+            })),
+        }))
+        .unwrap()
+    }
+
+    async fn registry(pool: &PgPool, only: bool) -> PersistedQueryRegistry {
+        PersistedQueriesRepositoryImpl::new(pool.clone())
+            .create_many(
+                Utc::now(),
+                &[entities::PersistedQuery {
+                    hash: hash_query(QUERY),
+                    query: QUERY.to_string(),
+                }],
+            )
+            .await
+            .unwrap();
+        PersistedQueryRegistry::new(
+            vec![entities::PersistedQuery {
+                hash: hash_query(FILE_QUERY),
+                query: FILE_QUERY.to_string(),
+            }],
+            pool.clone(),
+            only,
+        )
+    }
+
+    #[sqlx::test]
+    async fn test_resolve(pool: PgPool) {
+        let registry = registry(&pool, false).await;
+        let unknown_query = "{ characterConfigs { edges { cursor } } }";
+
+        // ファイルとDBに登録されたものはハッシュのみで実行できる
+        for query in [QUERY, FILE_QUERY] {
+            let resolved = registry
+                .resolve(request(None, Some(&hash_query(query))))
+                .await
+                .unwrap();
+            assert_eq!(resolved.query, query);
+        }
+        let resolved = registry
+            .resolve(request(
+                Some(unknown_query),
+                Some(&hash_query(unknown_query)),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resolved.query, unknown_query);
+        let resolved = registry
+            .resolve(request(Some(unknown_query), None))
+            .await
+            .unwrap();
+        assert_eq!(resolved.query, unknown_query);
+
+        assert!(matches!(
+            registry
+                .resolve(request(None, Some(&hash_query(unknown_query))))
+                .await,
+            Err(PersistedQueryError::NotFound)
+        ));
+        assert!(matches!(
+            registry
+                .resolve(request(Some(unknown_query), Some(&hash_query(QUERY))))
+                .await,
+            Err(PersistedQueryError::HashMismatch)
+        ));
+        assert!(matches!(
+            registry.resolve(request(None, None)).await,
+            Err(PersistedQueryError::MissingQuery)
+        ));
+    }
+
+    #[sqlx::test]
+    async fn test_resolve_only(pool: PgPool) {
+        let registry = registry(&pool, true).await;
+        let unknown_query = "{ characterConfigs { edges { cursor } } }";
+
+        for query in [QUERY, FILE_QUERY] {
+            let resolved = registry
+                .resolve(request(None, Some(&hash_query(query))))
+                .await
+                .unwrap();
+            assert_eq!(resolved.query, query);
+            // 登録されていればクエリ文字列のみでも実行できる
+            let resolved = registry.resolve(request(Some(query), None)).await.unwrap();
+            assert_eq!(resolved.query, query);
+        }
+
+        assert!(matches!(
+            registry
+                .resolve(request(
+                    Some(unknown_query),
+                    Some(&hash_query(unknown_query))
+                ))
+                .await,
+            Err(PersistedQueryError::NotAllowed)
+        ));
+        assert!(matches!(
+            registry.resolve(request(Some(unknown_query), None)).await,
+            Err(PersistedQueryError::NotAllowed)
+        ));
+        assert!(matches!(
+            registry
+                .resolve(request(None, Some(&hash_query(unknown_query))))
+                .await,
+            Err(PersistedQueryError::NotFound)
+        ));
+    }
+
+    #[sqlx::test]
+    async fn test_resolve_caches_missing(pool: PgPool) {
+        let registry = registry(&pool, false).await;
+        let unknown_query = "{ characterConfigs { edges { cursor } } }";
+        let hash = hash_query(unknown_query);
+
+        assert!(matches!(
+            registry.resolve(request(None, Some(&hash))).await,
+            Err(PersistedQueryError::NotFound)
+        ));
+
+        // 見つからなかったハッシュはTTLの間DBを引かない
+        PersistedQueriesRepositoryImpl::new(pool.clone())
+            .create_many(
+                Utc::now(),
+                &[entities::PersistedQuery {
+                    hash: hash.clone(),
+                    query: unknown_query.to_string(),
+                }],
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            registry.resolve(request(None, Some(&hash))).await,
+            Err(PersistedQueryError::NotFound)
+        ));
+
+        registry
+            .missing
+            .lock()
+            .unwrap()
+            .insert(hash.clone(), Instant::now() - NEGATIVE_CACHE_TTL);
+        let resolved = registry.resolve(request(None, Some(&hash))).await.unwrap();
+        assert_eq!(resolved.query, unknown_query);
+    }
+}
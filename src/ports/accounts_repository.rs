@@ -0,0 +1,10 @@
+use crate::entities;
+
+pub trait AccountsRepository {
+    type Error;
+
+    // アカウント削除用。ユーザーの全てのテーブルの行を1つのトランザクションで削除する
+    // ストレージのオブジェクトは削除しない
+    async fn delete_all_by_user_id(&mut self, user_id: entities::UserId)
+        -> Result<(), Self::Error>;
+}
@@ -1,8 +1,46 @@
 use std::collections::HashMap;
 
+use super::ConflictError;
 use crate::entities;
 use chrono::{DateTime, Utc};
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CharacterConfigsOrderBy {
+    #[default]
+    Character,
+    UpdatedAt,
+    FigureRecordCount,
+    Ratio,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CharacterConfigsOrder {
+    pub by: CharacterConfigsOrderBy,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CharacterConfigsQueryFilter {
+    pub characters: Option<Vec<entities::Character>>,
+    pub min_stroke_count: Option<entities::StrokeCount>,
+    pub max_stroke_count: Option<entities::StrokeCount>,
+    pub include_disabled: bool,
+    // 自分のfigure_records(disabled=falseのもの)が1件もないもののみ
+    pub without_figure_records: bool,
+}
+
+/*
+keyset paginationのカーソル
+sort_keyはorderごとの並び順の値(降順なら符号を反転したもの)で、(sort_key, character, stroke_count)で一意に並ぶ
+異なるorderで得たカーソルを渡してはいけない
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CharacterConfigsCursor {
+    pub sort_key: i64,
+    pub character: entities::Character,
+    pub stroke_count: entities::StrokeCount,
+}
+
 pub trait CharacterConfigsRepository {
     type Error;
 
@@ -12,6 +50,13 @@ pub trait CharacterConfigsRepository {
         character_config: entities::CharacterConfig,
     ) -> Result<entities::CharacterConfig, Self::Error>;
 
+    // 全て同じトランザクションで保存する。versionが一致しないものは保存せずConflictErrorを返す
+    async fn save_many(
+        &mut self,
+        now: DateTime<Utc>,
+        character_configs: Vec<entities::CharacterConfig>,
+    ) -> Result<Vec<Result<entities::CharacterConfig, ConflictError>>, Self::Error>;
+
     // disabled=falseのみ
     async fn get_by_characters(
         &mut self,
@@ -19,14 +64,15 @@ pub trait CharacterConfigsRepository {
         user_id: entities::UserId,
     ) -> Result<Vec<entities::CharacterConfig>, Self::Error>;
 
-    // disabled=falseのみ
     async fn query(
         &mut self,
         user_id: entities::UserId,
-        after_id: Option<(entities::Character, entities::StrokeCount)>,
-        before_id: Option<(entities::Character, entities::StrokeCount)>,
+        filter: &CharacterConfigsQueryFilter,
+        order: CharacterConfigsOrder,
+        after: Option<CharacterConfigsCursor>,
+        before: Option<CharacterConfigsCursor>,
         limit: entities::Limit,
-    ) -> Result<Vec<entities::CharacterConfig>, Self::Error>;
+    ) -> Result<Vec<(entities::CharacterConfig, CharacterConfigsCursor)>, Self::Error>;
 
     // disabled=trueも含む全て
     async fn get_by_ids(
@@ -37,6 +83,12 @@ pub trait CharacterConfigsRepository {
         HashMap<(entities::Character, entities::StrokeCount), entities::CharacterConfig>,
         Self::Error,
     >;
+
+    // disabled=trueも含む全て
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::CharacterConfig>, Self::Error>;
 }
 
 #[derive(Clone, thiserror::Error, Debug)]
@@ -9,3 +9,8 @@ pub struct PaginationResult<T> {
     pub values: Vec<T>,
     pub has_next: bool,
 }
+
+// 楽観ロック(version)の不一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("conflict")]
+pub struct ConflictError;
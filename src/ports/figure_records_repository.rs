@@ -2,6 +2,57 @@ use super::common;
 use crate::entities;
 use chrono::{DateTime, Utc};
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FigureRecordsQueryFilter {
+    pub characters: Option<Vec<entities::Character>>,
+    pub min_stroke_count: Option<entities::StrokeCount>,
+    pub max_stroke_count: Option<entities::StrokeCount>,
+    // created_after <= created_at < created_before
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    pub user_type: Option<common::UserType>,
+    // Noneなら両方。他人のdisabledなものはどちらにしても含まない
+    pub disabled: Option<bool>,
+}
+
+// 文字・画数ごとのfigure_recordsの件数。disabledなものは含まない
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FigureRecordStats {
+    pub character: entities::Character,
+    pub stroke_count: entities::StrokeCount,
+    pub my_count: i64,
+    // 共有されている他のユーザーのもの
+    pub other_count: i64,
+    // 自分のものの最新のcreated_at
+    pub last_recorded_at: Option<DateTime<Utc>>,
+}
+
+/*
+(character, stroke_count)ごとのfigure_recordsの問い合わせ
+First: after_idより前(古い方)からlimit件を新しい順に
+Last: before_idより後(新しい方)からlimit件を新しい順に
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FigureRecordsByCharacterConfigIdQuery {
+    pub character: entities::Character,
+    pub stroke_count: entities::StrokeCount,
+    pub ids: Option<Vec<entities::FigureRecordId>>,
+    pub after_id: Option<entities::FigureRecordId>,
+    pub before_id: Option<entities::FigureRecordId>,
+    pub limit: entities::Limit,
+    pub user_type: Option<common::UserType>,
+}
+
+/*
+ゴミ箱のkeyset paginationのカーソル
+disabled_atの新しい順に並べ、同じ時刻ならidの降順
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrashedFigureRecordsCursor {
+    pub disabled_at: DateTime<Utc>,
+    pub id: entities::FigureRecordId,
+}
+
 pub trait FigureRecordsRepository {
     type Error;
 
@@ -13,26 +64,85 @@ pub trait FigureRecordsRepository {
         figure: entities::Figure,
     ) -> Result<entities::FigureRecord, Self::Error>;
 
+    // 全て同じトランザクションで作成する
+    // IDはgeneratorで採番するので入力順になる。分けて作成する場合も同じgeneratorを渡せば順序が保たれる
+    async fn create_many(
+        &mut self,
+        user_id: entities::UserId,
+        now: DateTime<Utc>,
+        generator: &mut ulid::Generator,
+        figures: Vec<(entities::Character, entities::Figure)>,
+    ) -> Result<Vec<entities::FigureRecord>, Self::Error>;
+
+    // disabled, disabled_atを保存する
     async fn update(
         &mut self,
         figure_record: entities::FigureRecord,
-        disabled: Option<bool>,
     ) -> Result<entities::FigureRecord, Self::Error>;
 
+    // disabledなものは含まない
     async fn get_by_ids(
         &mut self,
         user_id: entities::UserId,
         ids: &[entities::FigureRecordId],
     ) -> Result<Vec<entities::FigureRecord>, Self::Error>;
 
+    // 自分のもののみ、disabledなものも含めて返す(更新・ゴミ箱からの復元用)
+    async fn get_by_ids_include_disabled(
+        &mut self,
+        user_id: entities::UserId,
+        ids: &[entities::FigureRecordId],
+    ) -> Result<Vec<entities::FigureRecord>, Self::Error>;
+
+    // queriesの各要素に対応する結果を同じ順で返す。それぞれidの降順
     async fn get_by_character_config_ids(
         &mut self,
         user_id: entities::UserId,
-        character_config_ids: &[(entities::Character, entities::StrokeCount)],
-        ids: Option<&[entities::FigureRecordId]>,
+        queries: &[FigureRecordsByCharacterConfigIdQuery],
+    ) -> Result<Vec<Vec<entities::FigureRecord>>, Self::Error>;
+
+    // idの昇順(作成順)
+    async fn query(
+        &mut self,
+        user_id: entities::UserId,
+        filter: &FigureRecordsQueryFilter,
         after_id: Option<entities::FigureRecordId>,
         before_id: Option<entities::FigureRecordId>,
-        limit_per_character: entities::Limit,
-        user_type: Option<common::UserType>,
+        limit: entities::Limit,
+    ) -> Result<Vec<entities::FigureRecord>, Self::Error>;
+
+    // 自分のdisabledなもののみ。disabled_atの新しい順(ゴミ箱)
+    async fn query_trashed(
+        &mut self,
+        user_id: entities::UserId,
+        after: Option<TrashedFigureRecordsCursor>,
+        before: Option<TrashedFigureRecordsCursor>,
+        limit: entities::Limit,
+    ) -> Result<Vec<(entities::FigureRecord, TrashedFigureRecordsCursor)>, Self::Error>;
+
+    // 記録のある(character, stroke_count)のみ返す
+    async fn get_stats_by_characters(
+        &mut self,
+        user_id: entities::UserId,
+        characters: &[entities::Character],
+    ) -> Result<Vec<FigureRecordStats>, Self::Error>;
+
+    // disabled=trueも含む全て
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
     ) -> Result<Vec<entities::FigureRecord>, Self::Error>;
+
+    // 保持期間を過ぎたものの物理削除用。対象のuser_idとidを返す
+    async fn get_disabled_before(
+        &mut self,
+        before: DateTime<Utc>,
+    ) -> Result<Vec<(entities::UserId, entities::FigureRecordId)>, Self::Error>;
+
+    // idsのうち保持期間を過ぎたままのもの(その後復元されていないもの)を物理削除する
+    async fn delete_disabled_before(
+        &mut self,
+        before: DateTime<Utc>,
+        ids: &[entities::FigureRecordId],
+    ) -> Result<(), Self::Error>;
 }
@@ -24,4 +24,10 @@ pub trait FilesRepository {
         ids: &[entities::FileId],
         verified_only: bool,
     ) -> Result<Vec<entities::File>, Self::Error>;
+
+    // verified=falseも含む全て
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::File>, Self::Error>;
 }
@@ -21,11 +21,19 @@ pub trait GenerateTemplatesRepository {
         generate_template: entities::GenerateTemplate,
     ) -> Result<entities::GenerateTemplate, Self::Error>;
 
+    // disabled=trueならdisabledなもの(ゴミ箱)のみ、falseならdisabledでないもののみ
     async fn query(
         &mut self,
         user_id: entities::UserId,
+        disabled: bool,
         after_id: Option<entities::GenerateTemplateId>,
         before_id: Option<entities::GenerateTemplateId>,
         limit: entities::Limit,
     ) -> Result<Vec<entities::GenerateTemplate>, Self::Error>;
+
+    // disabled=trueも含む全て
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::GenerateTemplate>, Self::Error>;
 }
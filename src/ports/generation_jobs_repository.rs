@@ -0,0 +1,23 @@
+use crate::entities;
+use chrono::{DateTime, Utc};
+
+pub trait GenerationJobsRepository {
+    type Error;
+
+    async fn create(
+        &mut self,
+        generation_job: entities::GenerationJob,
+    ) -> Result<entities::GenerationJob, Self::Error>;
+
+    async fn get_by_ids(
+        &mut self,
+        user_id: entities::UserId,
+        ids: &[entities::GenerationJobId],
+    ) -> Result<Vec<entities::GenerationJob>, Self::Error>;
+
+    async fn update(
+        &mut self,
+        now: DateTime<Utc>,
+        generation_job: entities::GenerationJob,
+    ) -> Result<entities::GenerationJob, Self::Error>;
+}
@@ -1,17 +1,25 @@
+mod accounts_repository;
 mod character_config_seeds_repository;
 mod character_configs_repository;
 mod common;
 mod figure_records_repository;
 mod files_repository;
 mod generate_templates_repository;
+mod generation_jobs_repository;
+mod persisted_queries_repository;
+mod reference_figures_repository;
 mod storage;
 mod user_configs_repository;
 
+pub use accounts_repository::*;
 pub use character_config_seeds_repository::*;
 pub use character_configs_repository::*;
 pub use common::*;
 pub use figure_records_repository::*;
 pub use files_repository::*;
 pub use generate_templates_repository::*;
+pub use generation_jobs_repository::*;
+pub use persisted_queries_repository::*;
+pub use reference_figures_repository::*;
 pub use storage::*;
 pub use user_configs_repository::*;
@@ -0,0 +1,18 @@
+use crate::entities;
+use chrono::{DateTime, Utc};
+
+pub trait PersistedQueriesRepository {
+    type Error;
+
+    async fn get_by_hash(
+        &mut self,
+        hash: &entities::PersistedQueryHash,
+    ) -> Result<Option<entities::PersistedQuery>, Self::Error>;
+
+    // 既に登録されているハッシュは無視する。新しく登録した件数を返す
+    async fn create_many(
+        &mut self,
+        now: DateTime<Utc>,
+        persisted_queries: &[entities::PersistedQuery],
+    ) -> Result<u64, Self::Error>;
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use crate::entities;
+use chrono::{DateTime, Utc};
+
+pub trait ReferenceFiguresRepository {
+    type Error;
+
+    // versionが一致しない場合はConflictErrorを返す
+    async fn save(
+        &mut self,
+        now: DateTime<Utc>,
+        reference_figure: entities::ReferenceFigure,
+    ) -> Result<entities::ReferenceFigure, Self::Error>;
+
+    // versionが一致しない場合はConflictErrorを返す
+    async fn delete(
+        &mut self,
+        reference_figure: entities::ReferenceFigure,
+    ) -> Result<(), Self::Error>;
+
+    // 各keyのものをvariant順で返す。手本がないkeyは含まない
+    async fn get_by_character_config_ids(
+        &mut self,
+        user_id: entities::UserId,
+        keys: &[(entities::Character, entities::StrokeCount)],
+    ) -> Result<
+        HashMap<(entities::Character, entities::StrokeCount), Vec<entities::ReferenceFigure>>,
+        Self::Error,
+    >;
+
+    async fn get_all_by_user_id(
+        &mut self,
+        user_id: entities::UserId,
+    ) -> Result<Vec<entities::ReferenceFigure>, Self::Error>;
+}
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use crate::entities;
 
 pub trait Storage {
@@ -9,4 +11,34 @@ pub trait Storage {
         -> Result<String, Self::Error>;
 
     async fn verify(&mut self, file: &entities::File) -> Result<(), Self::Error>;
+
+    // サーバー側で生成したオブジェクト(サムネイルなど)用
+    async fn exists(&mut self, key: &entities::FileKey) -> Result<bool, Self::Error>;
+
+    async fn put(
+        &mut self,
+        key: &entities::FileKey,
+        mime_type: &entities::MimeType,
+        body: Vec<u8>,
+    ) -> Result<(), Self::Error>;
+
+    // メモリに載せずにファイルの中身をそのままアップロードする
+    async fn put_file(
+        &mut self,
+        key: &entities::FileKey,
+        mime_type: &entities::MimeType,
+        path: &Path,
+    ) -> Result<(), Self::Error>;
+
+    async fn get(&mut self, key: &entities::FileKey) -> Result<Vec<u8>, Self::Error>;
+
+    async fn generate_download_url_by_key(
+        &mut self,
+        key: &entities::FileKey,
+    ) -> Result<String, Self::Error>;
+
+    // 存在しないキーはエラーにしない
+    async fn delete(&mut self, keys: &[entities::FileKey]) -> Result<(), Self::Error>;
+
+    async fn delete_by_prefix(&mut self, prefix: &str) -> Result<(), Self::Error>;
 }
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+
+use graphql_parser::query::{
+    parse_query, Definition, OperationDefinition, Selection, SelectionSet, Value,
+    VariableDefinition,
+};
+use thiserror::Error;
+
+use crate::app_config::QueryLimitsConfig;
+use crate::entities;
+use crate::graphql::GraphqlErrorCode;
+
+/*
+クエリの深さ・エイリアスの数・推定コストを実行前に検査する
+推定コストは各フィールドを1とし、子のコストにはfirst/last(なければvalues/idsの要素数)を掛ける
+例えば characters(values: [50文字]) { characterConfigs { figureRecords(first: 100) { ... } } } は50 * 100倍になる
+変数は渡された値、なければ変数定義のデフォルト値を使い、first/lastの値が分からなければ最大のページサイズとみなす
+解析できないクエリは検査をすり抜けないように拒否する
+*/
+
+// 子の数を表す引数
+const PAGINATION_ARGUMENTS: &[&str] = &["first", "last"];
+const LIST_ARGUMENTS: &[&str] = &["values", "ids"];
+
+#[derive(Debug, Clone, Error)]
+pub enum QueryLimitError {
+    #[error("Query depth exceeds the limit of {max}")]
+    TooDeep { max: usize },
+    #[error("Query has more than {max} aliases")]
+    TooManyAliases { max: usize },
+    #[error("Query complexity exceeds the limit of {max}")]
+    TooComplex { max: u64 },
+    #[error("Query could not be parsed: {message}")]
+    Unparsable { message: String },
+}
+
+impl QueryLimitError {
+    pub fn code(&self) -> GraphqlErrorCode {
+        match self {
+            QueryLimitError::TooDeep { .. } => GraphqlErrorCode::QueryTooDeep,
+            QueryLimitError::TooManyAliases { .. } => GraphqlErrorCode::QueryTooManyAliases,
+            QueryLimitError::TooComplex { .. } => GraphqlErrorCode::QueryTooComplex,
+            QueryLimitError::Unparsable { .. } => GraphqlErrorCode::Validation,
+        }
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        match *self {
+            QueryLimitError::TooDeep { max } => Some(max as u64),
+            QueryLimitError::TooManyAliases { max } => Some(max as u64),
+            QueryLimitError::TooComplex { max } => Some(max),
+            QueryLimitError::Unparsable { .. } => None,
+        }
+    }
+}
+
+pub fn check_query_limits(
+    config: &QueryLimitsConfig,
+    query: &str,
+    operation_name: Option<&str>,
+    variables: &serde_json::Value,
+) -> Result<(), QueryLimitError> {
+    let document = parse_query::<String>(query).map_err(|e| QueryLimitError::Unparsable {
+        message: e.to_string(),
+    })?;
+
+    let fragments = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::Fragment(fragment) => {
+                Some((fragment.name.as_str(), &fragment.selection_set))
+            }
+            Definition::Operation(_) => None,
+        })
+        .collect::<HashMap<_, _>>();
+
+    for definition in &document.definitions {
+        let Definition::Operation(operation) = definition else {
+            continue;
+        };
+        let (name, variable_definitions, selection_set) = match operation {
+            OperationDefinition::SelectionSet(selection_set) => (None, &[][..], selection_set),
+            OperationDefinition::Query(query) => (
+                query.name.as_deref(),
+                &query.variable_definitions[..],
+                &query.selection_set,
+            ),
+            OperationDefinition::Mutation(mutation) => (
+                mutation.name.as_deref(),
+                &mutation.variable_definitions[..],
+                &mutation.selection_set,
+            ),
+            OperationDefinition::Subscription(subscription) => (
+                subscription.name.as_deref(),
+                &subscription.variable_definitions[..],
+                &subscription.selection_set,
+            ),
+        };
+        if operation_name.is_some() && operation_name != name {
+            continue;
+        }
+
+        let mut analyzer = Analyzer {
+            config,
+            fragments: &fragments,
+            variables,
+            variable_definitions,
+            fragment_stack: Vec::new(),
+            aliases: 0,
+        };
+        analyzer.selection_set_cost(selection_set, 1)?;
+    }
+
+    Ok(())
+}
+
+// 実行される操作がsubscriptionか。解析できない、または操作を特定できない場合はfalse
+pub fn is_subscription(query: &str, operation_name: Option<&str>) -> bool {
+    let Ok(document) = parse_query::<String>(query) else {
+        return false;
+    };
+
+    let operations = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            Definition::Operation(operation) => Some(operation),
+            Definition::Fragment(_) => None,
+        })
+        .collect::<Vec<_>>();
+    let operation = match operation_name {
+        Some(operation_name) => operations.into_iter().find(|operation| {
+            let name = match operation {
+                OperationDefinition::SelectionSet(_) => None,
+                OperationDefinition::Query(query) => query.name.as_deref(),
+                OperationDefinition::Mutation(mutation) => mutation.name.as_deref(),
+                OperationDefinition::Subscription(subscription) => subscription.name.as_deref(),
+            };
+            name == Some(operation_name)
+        }),
+        None if operations.len() == 1 => operations.into_iter().next(),
+        None => None,
+    };
+
+    matches!(operation, Some(OperationDefinition::Subscription(_)))
+}
+
+// 'qはクエリ文字列、'aは解析したドキュメントなどの借用
+struct Analyzer<'a, 'q> {
+    config: &'a QueryLimitsConfig,
+    fragments: &'a HashMap<&'a str, &'a SelectionSet<'q, String>>,
+    variables: &'a serde_json::Value,
+    variable_definitions: &'a [VariableDefinition<'q, String>],
+    // 循環したfragmentはjuniperの検証でエラーになるので、ここでは展開しないだけにする
+    fragment_stack: Vec<&'a str>,
+    aliases: usize,
+}
+
+impl<'a, 'q> Analyzer<'a, 'q> {
+    fn selection_set_cost(
+        &mut self,
+        selection_set: &'a SelectionSet<'q, String>,
+        depth: usize,
+    ) -> Result<u64, QueryLimitError> {
+        if depth > self.config.max_depth {
+            return Err(QueryLimitError::TooDeep {
+                max: self.config.max_depth,
+            });
+        }
+
+        let mut cost = 0u64;
+        for selection in &selection_set.items {
+            let selection_cost = match selection {
+                Selection::Field(field) => {
+                    if field.alias.is_some() {
+                        self.aliases += 1;
+                        if self.aliases > self.config.max_aliases {
+                            return Err(QueryLimitError::TooManyAliases {
+                                max: self.config.max_aliases,
+                            });
+                        }
+                    }
+
+                    let children_cost = if field.selection_set.items.is_empty() {
+                        0
+                    } else {
+                        self.selection_set_cost(&field.selection_set, depth + 1)?
+                    };
+                    self.multiplier(&field.arguments)
+                        .saturating_mul(children_cost)
+                        .saturating_add(1)
+                }
+                Selection::FragmentSpread(spread) => {
+                    let name = spread.fragment_name.as_str();
+                    match self.fragments.get(name) {
+                        Some(&fragment) if !self.fragment_stack.contains(&name) => {
+                            self.fragment_stack.push(name);
+                            let cost = self.selection_set_cost(fragment, depth);
+                            self.fragment_stack.pop();
+                            cost?
+                        }
+                        _ => 0,
+                    }
+                }
+                Selection::InlineFragment(fragment) => {
+                    self.selection_set_cost(&fragment.selection_set, depth)?
+                }
+            };
+
+            cost = cost.saturating_add(selection_cost);
+            if cost > self.config.max_complexity {
+                return Err(QueryLimitError::TooComplex {
+                    max: self.config.max_complexity,
+                });
+            }
+        }
+
+        Ok(cost)
+    }
+
+    fn multiplier(&self, arguments: &[(String, Value<'q, String>)]) -> u64 {
+        let pagination = arguments
+            .iter()
+            .filter(|(name, _)| PAGINATION_ARGUMENTS.contains(&name.as_str()))
+            .map(|(_, value)| {
+                // 負の値などはjuniperの実行時にエラーになるが、ここでは最大とみなしておく
+                self.resolve(value)
+                    .and_then(|value| value.as_i64())
+                    .and_then(|value| u64::try_from(value).ok())
+                    .unwrap_or(entities::MAX_LIMIT as u64)
+            })
+            .max();
+        if let Some(pagination) = pagination {
+            return pagination;
+        }
+
+        arguments
+            .iter()
+            .filter(|(name, _)| LIST_ARGUMENTS.contains(&name.as_str()))
+            .filter_map(|(_, value)| self.resolve(value)?.list_len())
+            .max()
+            .map(|len| len as u64)
+            .unwrap_or(1)
+    }
+
+    // 変数は渡された値、渡されていなければデフォルト値に置き換える
+    fn resolve<'v>(&'v self, value: &'v Value<'q, String>) -> Option<ArgumentValue<'v, 'q>> {
+        let Value::Variable(name) = value else {
+            return Some(ArgumentValue::Literal(value));
+        };
+        if let Some(value) = self.variables.get(name) {
+            return Some(ArgumentValue::Json(value));
+        }
+        self.variable_definitions
+            .iter()
+            .find(|definition| &definition.name == name)?
+            .default_value
+            .as_ref()
+            .map(ArgumentValue::Literal)
+    }
+}
+
+enum ArgumentValue<'v, 'q> {
+    Literal(&'v Value<'q, String>),
+    Json(&'v serde_json::Value),
+}
+
+impl ArgumentValue<'_, '_> {
+    fn as_i64(&self) -> Option<i64> {
+        match *self {
+            ArgumentValue::Literal(Value::Int(number)) => number.as_i64(),
+            ArgumentValue::Literal(_) => None,
+            ArgumentValue::Json(value) => value.as_i64(),
+        }
+    }
+
+    fn list_len(&self) -> Option<usize> {
+        match *self {
+            ArgumentValue::Literal(Value::List(values)) => Some(values.len()),
+            ArgumentValue::Literal(_) => None,
+            ArgumentValue::Json(value) => value.as_array().map(Vec::len),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> QueryLimitsConfig {
+        QueryLimitsConfig {
+            max_depth: 6,
+            max_aliases: 2,
+            max_complexity: 1000,
+        }
+    }
+
+    #[test]
+    fn test_check_query_limits() {
+        let variables = serde_json::json!({ "first": 100 });
+
+        assert!(check_query_limits(
+            &config(),
+            "query { characters(values: [\"a\", \"b\"]) { characterConfigs { figureRecords(first: 4) { edges { node { id } } } } } }",
+            None,
+            &variables,
+        )
+        .is_ok());
+
+        assert!(matches!(
+            check_query_limits(
+                &config(),
+                "{ a { b { c { d { e { f { g } } } } } } }",
+                None,
+                &variables,
+            ),
+            Err(QueryLimitError::TooDeep { .. })
+        ));
+
+        assert!(matches!(
+            check_query_limits(
+                &config(),
+                "query Q($first: Int) { figureRecords(first: $first) { edges { node { id figure createdAt disabled } } } ...F } fragment F on Query { figureRecords(first: 100) { edges { node { id figure createdAt disabled } } } }",
+                Some("Q"),
+                &variables,
+            ),
+            Err(QueryLimitError::TooComplex { .. })
+        ));
+
+        assert!(matches!(
+            check_query_limits(
+                &config(),
+                "{ a: me { id } b: me { id } c: me { id } }",
+                None,
+                &variables
+            ),
+            Err(QueryLimitError::TooManyAliases { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_query_limits_variables() {
+        let query = "query Q($first: Int = 100, $values: [String!]! = [\"a\", \"b\", \"c\"]) { characters(values: $values) { characterConfigs { figureRecords(first: $first) { edges { node { id figure } } } } } }";
+
+        // 変数が渡されなければデフォルト値を使う
+        assert!(matches!(
+            check_query_limits(&config(), query, None, &serde_json::json!({})),
+            Err(QueryLimitError::TooComplex { .. })
+        ));
+        assert!(check_query_limits(
+            &config(),
+            query,
+            None,
+            &serde_json::json!({ "first": 2, "values": ["a"] }),
+        )
+        .is_ok());
+
+        // 値が分からないfirstは最大のページサイズとみなす
+        for variables in [
+            serde_json::json!({}),
+            serde_json::json!({ "first": null }),
+            serde_json::json!({ "first": "2" }),
+        ] {
+            assert!(matches!(
+                check_query_limits(
+                    &config(),
+                    "query Q($first: Int) { a { b(first: $first) { c d e f g h i j k l } } }",
+                    None,
+                    &variables,
+                ),
+                Err(QueryLimitError::TooComplex { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_check_query_limits_unparsable() {
+        assert!(matches!(
+            check_query_limits(&config(), "{ a { b }", None, &serde_json::json!({})),
+            Err(QueryLimitError::Unparsable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_is_subscription() {
+        assert!(is_subscription("subscription { a }", None));
+        assert!(!is_subscription("{ a }", None));
+        assert!(!is_subscription("mutation { a }", None));
+        assert!(!is_subscription("{ a", None));
+
+        let query = "subscription S { a } mutation M { b }";
+        assert!(is_subscription(query, Some("S")));
+        assert!(!is_subscription(query, Some("M")));
+        assert!(!is_subscription(query, Some("X")));
+        // 操作が複数あるのに名前がない
+        assert!(!is_subscription(query, None));
+    }
+}
@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use crate::entities;
+
+use super::raster::Canvas;
+use super::MAX_LINE_WIDTH_RATIO;
+
+// font_weightがこの値の時に線の太さがMAX_LINE_WIDTH_RATIOになる
+const STANDARD_FONT_WEIGHT: f64 = 50.0;
+
+// 背景画像の幅/高さの上限
+const MAX_BACKGROUND_SIZE: u32 = 4096;
+
+// 背景画像のデコード時に確保するメモリの上限
+const MAX_BACKGROUND_ALLOC: u64 = 256 * 1024 * 1024;
+
+// 1文字を描く位置(font_size x font_sizeの正方形の左上)
+#[derive(Clone, Debug, PartialEq)]
+pub struct GlyphPosition {
+    pub character: entities::Character,
+    pub x: f64,
+    pub y: f64,
+}
+
+/*
+width x heightの画像にtextを配置する
+行方向(inline)と行の進む方向(block)で考え、横書きでは左→右/上→下、縦書きでは上→下/右→左になる
+余白は開始側と同じだけ終了側にも取り、行に収まらなければ折り返し、最後の行に収まらなければそれ以降は切り捨てる
+空白文字は位置だけ進めて描かない
+*/
+pub fn layout(
+    template: &entities::GenerateTemplate,
+    width: u32,
+    height: u32,
+    text: &str,
+) -> Vec<GlyphPosition> {
+    let font_size = f64::from(i32::from(template.font_size));
+    let margin_block_start = f64::from(i32::from(template.margin_block_start));
+    let margin_inline_start = f64::from(i32::from(template.margin_inline_start));
+    let letter_advance = font_size + f64::from(i32::from(template.letter_spacing));
+    let line_advance = font_size + f64::from(i32::from(template.line_spacing));
+
+    let (inline_size, block_size) = match template.writing_mode {
+        entities::WritingMode::Horizontal => (f64::from(width), f64::from(height)),
+        entities::WritingMode::Vertical => (f64::from(height), f64::from(width)),
+    };
+    let inline_end = inline_size - margin_inline_start;
+    let block_end = block_size - margin_block_start;
+
+    let mut result = Vec::new();
+    let mut inline = margin_inline_start;
+    let mut block = margin_block_start;
+    for c in text.chars() {
+        if c == '\n' {
+            inline = margin_inline_start;
+            block += line_advance;
+            continue;
+        }
+
+        if inline + font_size > inline_end && inline > margin_inline_start {
+            inline = margin_inline_start;
+            block += line_advance;
+        }
+        if inline + font_size > inline_end || block + font_size > block_end {
+            break;
+        }
+
+        if !c.is_whitespace() {
+            let (x, y) = match template.writing_mode {
+                entities::WritingMode::Horizontal => (inline, block),
+                entities::WritingMode::Vertical => (block_size - block - font_size, inline),
+            };
+            result.push(GlyphPosition {
+                character: entities::Character::from(c),
+                x,
+                y,
+            });
+        }
+        inline += letter_advance;
+    }
+
+    result
+}
+
+// 背景画像の上にtextを描いたPNGを返す。figuresに無い文字は空白として扱う
+pub fn render_document(
+    background: &[u8],
+    template: &entities::GenerateTemplate,
+    text: &str,
+    figures: &HashMap<entities::Character, entities::Figure>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut image = decode_background(background)?;
+    let (width, height) = image.dimensions();
+
+    let font_size = f64::from(i32::from(template.font_size));
+    let line_width_ratio =
+        MAX_LINE_WIDTH_RATIO * f64::from(i32::from(template.font_weight)) / STANDARD_FONT_WEIGHT;
+
+    let mut canvas = Canvas::new(width, height);
+    for glyph in layout(template, width, height, text) {
+        if let Some(figure) = figures.get(&glyph.character) {
+            canvas.draw_figure(figure, glyph.x, glyph.y, font_size, line_width_ratio);
+        }
+    }
+
+    let color = i32::from(template.font_color);
+    let font_color = [
+        ((color >> 16) & 0xFF) as f64,
+        ((color >> 8) & 0xFF) as f64,
+        (color & 0xFF) as f64,
+    ];
+    for (pixel, coverage) in image.pixels_mut().zip(canvas.coverage()) {
+        if *coverage <= 0.0 {
+            continue;
+        }
+        for (channel, color) in pixel.0.iter_mut().zip(font_color) {
+            *channel = (f64::from(*channel) * (1.0 - coverage) + color * coverage).round() as u8;
+        }
+        pixel.0[3] =
+            (f64::from(pixel.0[3]) + (255.0 - f64::from(pixel.0[3])) * coverage).round() as u8;
+    }
+
+    let mut buf = Vec::new();
+    image.write_to(&mut Cursor::new(&mut buf), image::ImageFormat::Png)?;
+
+    Ok(buf)
+}
+
+// 展開後のサイズはファイルサイズから分からないので、ヘッダの幅/高さを見てから展開する
+fn decode_background(background: &[u8]) -> anyhow::Result<image::RgbaImage> {
+    let reader = || -> anyhow::Result<_> {
+        let mut reader = image::ImageReader::new(Cursor::new(background)).with_guessed_format()?;
+        let mut limits = image::Limits::default();
+        limits.max_image_width = Some(MAX_BACKGROUND_SIZE);
+        limits.max_image_height = Some(MAX_BACKGROUND_SIZE);
+        limits.max_alloc = Some(MAX_BACKGROUND_ALLOC);
+        reader.limits(limits);
+        Ok(reader)
+    };
+
+    let (width, height) = reader()?.into_dimensions()?;
+    if width > MAX_BACKGROUND_SIZE || height > MAX_BACKGROUND_SIZE {
+        anyhow::bail!(
+            "background image must be at most {}x{}",
+            MAX_BACKGROUND_SIZE,
+            MAX_BACKGROUND_SIZE
+        );
+    }
+
+    Ok(reader()?.decode()?.to_rgba8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn template(writing_mode: entities::WritingMode) -> entities::GenerateTemplate {
+        entities::GenerateTemplate {
+            id: entities::GenerateTemplateId::from(ulid::Ulid::nil()),
+            user_id: entities::UserId::from("user".to_string()),
+            background_image_file_id: entities::FileId::from(ulid::Ulid::nil()),
+            font_color: entities::Color::try_from(0xFF0000).unwrap(),
+            writing_mode,
+            margin_block_start: entities::Margin::try_from(10).unwrap(),
+            margin_inline_start: entities::Margin::try_from(5).unwrap(),
+            line_spacing: entities::Spacing::try_from(4).unwrap(),
+            letter_spacing: entities::Spacing::try_from(2).unwrap(),
+            font_size: entities::FontSize::try_from(20).unwrap(),
+            font_weight: entities::FontWeight::try_from(50).unwrap(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            disabled: false,
+            version: entities::Version::new(),
+        }
+    }
+
+    fn positions(glyphs: &[GlyphPosition]) -> Vec<(char, f64, f64)> {
+        glyphs
+            .iter()
+            .map(|glyph| (char::from(glyph.character.clone()), glyph.x, glyph.y))
+            .collect()
+    }
+
+    #[test]
+    fn test_layout_horizontal() {
+        // 1行に(100 - 5 * 2 + 2) / 22 = 4文字まで、(80 - 10 * 2 + 4) / 24 = 2行まで
+        let glyphs = layout(
+            &template(entities::WritingMode::Horizontal),
+            100,
+            80,
+            "あい う\nえおかきくけこ",
+        );
+        assert_eq!(
+            positions(&glyphs),
+            vec![
+                ('あ', 5.0, 10.0),
+                ('い', 27.0, 10.0),
+                ('う', 71.0, 10.0),
+                ('え', 5.0, 34.0),
+                ('お', 27.0, 34.0),
+                ('か', 49.0, 34.0),
+                ('き', 71.0, 34.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_layout_vertical() {
+        let glyphs = layout(
+            &template(entities::WritingMode::Vertical),
+            80,
+            100,
+            "あいうえお",
+        );
+        assert_eq!(
+            positions(&glyphs),
+            vec![
+                ('あ', 50.0, 5.0),
+                ('い', 50.0, 27.0),
+                ('う', 50.0, 49.0),
+                ('え', 50.0, 71.0),
+                ('お', 26.0, 5.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_document() {
+        let background = image::RgbaImage::from_pixel(100, 80, image::Rgba([255, 255, 255, 255]));
+        let mut background_png = Vec::new();
+        background
+            .write_to(
+                &mut Cursor::new(&mut background_png),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+
+        let figure = entities::Figure::from_json(
+            r#"{"strokes":[{"points":[{"x":0,"y":52.5,"z":1},{"x":100,"y":52.5,"z":1}]}],"width":100,"height":100}"#,
+        )
+        .unwrap();
+        let figures = HashMap::from([(entities::Character::from('一'), figure)]);
+
+        let png = render_document(
+            &background_png,
+            &template(entities::WritingMode::Horizontal),
+            "一",
+            &figures,
+        )
+        .unwrap();
+        let image = image::load_from_memory(&png).unwrap().to_rgba8();
+
+        assert_eq!(image.dimensions(), (100, 80));
+        assert_eq!(image.get_pixel(15, 20).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(15, 12).0, [255, 255, 255, 255]);
+        assert_eq!(image.get_pixel(60, 20).0, [255, 255, 255, 255]);
+
+        let background = image::GrayImage::new(MAX_BACKGROUND_SIZE + 1, 1);
+        let mut background_png = Vec::new();
+        background
+            .write_to(
+                &mut Cursor::new(&mut background_png),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        assert!(render_document(
+            &background_png,
+            &template(entities::WritingMode::Horizontal),
+            "一",
+            &figures,
+        )
+        .is_err());
+    }
+}
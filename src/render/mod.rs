@@ -0,0 +1,11 @@
+mod document;
+mod png;
+mod raster;
+mod svg;
+
+pub use self::png::figure_to_png;
+pub use document::{layout, render_document, GlyphPosition};
+pub use svg::figure_to_svg;
+
+// 筆圧(z)が1の時の線の太さ(width/heightの大きい方に対する割合)
+const MAX_LINE_WIDTH_RATIO: f64 = 0.04;
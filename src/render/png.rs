@@ -0,0 +1,50 @@
+use crate::entities;
+
+use super::raster::Canvas;
+use super::MAX_LINE_WIDTH_RATIO;
+
+// 透明な背景に黒で描いたグレースケール+アルファのPNGを返す
+pub fn figure_to_png(
+    figure: &entities::Figure,
+    size: entities::ThumbnailSize,
+) -> anyhow::Result<Vec<u8>> {
+    let size = u32::from(size);
+    let mut canvas = Canvas::new(size, size);
+    canvas.draw_figure(figure, 0.0, 0.0, f64::from(size), MAX_LINE_WIDTH_RATIO);
+
+    let data = canvas
+        .coverage()
+        .iter()
+        .flat_map(|alpha| [0, (alpha * 255.0).round() as u8])
+        .collect::<Vec<_>>();
+
+    let mut buf = Vec::new();
+    let mut encoder = png::Encoder::new(&mut buf, size, size);
+    encoder.set_color(png::ColorType::GrayscaleAlpha);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_figure_to_png() {
+        let figure = entities::Figure::from_json(
+            r#"{"strokes":[{"points":[{"x":10,"y":55,"z":1},{"x":90,"y":55,"z":1}]}],"width":100,"height":100}"#,
+        )
+        .unwrap();
+        let png = figure_to_png(&figure, entities::ThumbnailSize::try_from(16).unwrap()).unwrap();
+
+        let decoder = png::Decoder::new(png.as_slice());
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().width, 16);
+        assert_eq!(reader.info().height, 16);
+        assert_eq!(reader.info().color_type, png::ColorType::GrayscaleAlpha);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::entities;
+
+// 小さく描いても線が消えないようにする最小の線の太さの半分(px)
+const MIN_LINE_RADIUS: f64 = 0.5;
+
+// 各ピクセルの塗られている割合(0～1)を持つキャンバス
+#[derive(Clone, Debug)]
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    coverage: Vec<f64>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            coverage: vec![0.0; width as usize * height as usize],
+        }
+    }
+
+    pub fn coverage(&self) -> &[f64] {
+        &self.coverage
+    }
+
+    /*
+    (x, y)を左上とするsize x sizeの正方形にアスペクト比を保って中央に配置して描く
+    線の太さは筆圧(z)が1の時にsize * line_width_ratioになる
+    隣り合う2点を太さが線形に変わるカプセルとみなし、ピクセル中心からの距離でアンチエイリアスする
+    */
+    pub fn draw_figure(
+        &mut self,
+        figure: &entities::Figure,
+        x: f64,
+        y: f64,
+        size: f64,
+        line_width_ratio: f64,
+    ) {
+        let longer_side = figure.width().max(figure.height());
+        if !(longer_side.is_finite() && longer_side > 0.0) {
+            return;
+        }
+
+        let scale = size / longer_side;
+        let offset_x = x + (size - figure.width() * scale) / 2.0;
+        let offset_y = y + (size - figure.height() * scale) / 2.0;
+        let max_radius = size * line_width_ratio / 2.0;
+
+        let to_pixel = |point: &entities::FigurePoint| {
+            (
+                point.x * scale + offset_x,
+                point.y * scale + offset_y,
+                (point.z * max_radius).max(MIN_LINE_RADIUS),
+            )
+        };
+
+        for points in figure.strokes() {
+            for pair in points.windows(2) {
+                let (x0, y0, r0) = to_pixel(&pair[0]);
+                let (x1, y1, r1) = to_pixel(&pair[1]);
+                self.draw_segment(x0, y0, r0, x1, y1, r1);
+            }
+        }
+    }
+
+    fn draw_segment(&mut self, x0: f64, y0: f64, r0: f64, x1: f64, y1: f64, r1: f64) {
+        let (dx, dy) = (x1 - x0, y1 - y0);
+        let length_sq = dx * dx + dy * dy;
+        let margin = r0.max(r1) + 1.0;
+
+        let min_x = (x0.min(x1) - margin).floor().max(0.0) as usize;
+        let min_y = (y0.min(y1) - margin).floor().max(0.0) as usize;
+        let max_x = ((x0.max(x1) + margin).ceil().max(0.0) as usize).min(self.width as usize);
+        let max_y = ((y0.max(y1) + margin).ceil().max(0.0) as usize).min(self.height as usize);
+
+        for py in min_y..max_y {
+            for px in min_x..max_x {
+                let (cx, cy) = (px as f64 + 0.5, py as f64 + 0.5);
+                let t = if length_sq > 0.0 {
+                    (((cx - x0) * dx + (cy - y0) * dy) / length_sq).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let distance = ((cx - (x0 + dx * t)).powi(2) + (cy - (y0 + dy * t)).powi(2)).sqrt();
+                let radius = r0 + (r1 - r0) * t;
+                let value = (radius + 0.5 - distance).clamp(0.0, 1.0);
+
+                let pixel = &mut self.coverage[py * self.width as usize + px];
+                *pixel = f64::max(*pixel, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_figure() {
+        let figure = entities::Figure::from_json(
+            r#"{"strokes":[{"points":[{"x":10,"y":55,"z":1},{"x":90,"y":55,"z":1}]}],"width":100,"height":100}"#,
+        )
+        .unwrap();
+        let mut canvas = Canvas::new(20, 10);
+        canvas.draw_figure(&figure, 10.0, 0.0, 10.0, 0.04);
+        let coverage = canvas.coverage();
+
+        assert_eq!(coverage.len(), 200);
+        // 線の上
+        assert_eq!(coverage[5 * 20 + 15], 1.0);
+        assert_eq!(coverage[5 * 20 + 18], 1.0);
+        // 線から離れた場所
+        assert_eq!(coverage[5 * 20 + 5], 0.0);
+        assert_eq!(coverage[3 * 20 + 15], 0.0);
+        assert_eq!(coverage[9 * 20 + 19], 0.0);
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt::Write;
+
+use crate::entities;
+
+use super::MAX_LINE_WIDTH_RATIO;
+
+/*
+線の太さを筆圧で変えるため、隣り合う2点ごとに1つのpathにする
+*/
+pub fn figure_to_svg(figure: &entities::Figure) -> String {
+    let max_line_width = figure.width().max(figure.height()) * MAX_LINE_WIDTH_RATIO;
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" width="{}" height="{}">"#,
+        figure.width(),
+        figure.height(),
+        figure.width(),
+        figure.height(),
+    )
+    .unwrap();
+    svg.push_str(
+        r#"<g fill="none" stroke="black" stroke-linecap="round" stroke-linejoin="round">"#,
+    );
+
+    for points in figure.strokes() {
+        for pair in points.windows(2) {
+            write!(
+                svg,
+                r#"<path d="M {} {} L {} {}" stroke-width="{}"/>"#,
+                pair[0].x,
+                pair[0].y,
+                pair[1].x,
+                pair[1].y,
+                (pair[0].z + pair[1].z) / 2.0 * max_line_width,
+            )
+            .unwrap();
+        }
+    }
+
+    svg.push_str("</g></svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_figure_to_svg() {
+        let figure = entities::Figure::from_json(
+            r#"{"strokes":[{"points":[{"x":0,"y":0,"z":1},{"x":10,"y":20,"z":0.5},{"x":30,"y":20,"z":0}]}],"width":50,"height":100}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            figure_to_svg(&figure),
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 50 100" width="50" height="100">"#,
+                r#"<g fill="none" stroke="black" stroke-linecap="round" stroke-linejoin="round">"#,
+                r#"<path d="M 0 0 L 10 20" stroke-width="3"/>"#,
+                r#"<path d="M 10 20 L 30 20" stroke-width="1"/>"#,
+                r#"</g></svg>"#,
+            )
+        );
+    }
+}